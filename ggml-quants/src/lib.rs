@@ -84,7 +84,7 @@ where
     T: Send + Sync,
 {
     fn quantize_slice(dst: &mut [Self], src: &[T]) -> Result<(), QuantizeError> {
-        if src.len() % N != 0 {
+        if !src.len().is_multiple_of(N) {
             return Err(QuantizeError::Indivisible);
         }
         if dst.len() != src.len() / N {
@@ -98,7 +98,7 @@ where
     }
 
     fn dequantize_slice(dst: &mut [T], src: &[Self]) -> Result<(), QuantizeError> {
-        if dst.len() % N != 0 {
+        if !dst.len().is_multiple_of(N) {
             return Err(QuantizeError::Indivisible);
         }
         if src.len() != dst.len() / N {
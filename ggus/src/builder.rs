@@ -0,0 +1,355 @@
+use crate::{
+    GENERAL_ALIGNMENT, GGmlType, GGufMetaBuf, GGufMetaDataValueType, GGufMetaError, GGufMetaMapExt,
+    GGufMetaScalar, GGufMetaValue, GGufModel,
+};
+use ggml_quants::{
+    DataBlock, Q4_0, Q4_1, Q5_0, Q5_1, Q8_0, Q8_1, Q8K, QuantExt, Quantize, bf16, f16,
+};
+use std::{error::Error, fmt, io, slice::from_raw_parts};
+
+/// Collects typed metadata and tensors to build a [`GGufModel`] from scratch.
+///
+/// Tensors given as `f32` or `f16` are converted to the target type on insertion,
+/// header counts are computed when the model is written.
+#[derive(Default)]
+pub struct GGufBuilder {
+    model: GGufModel<'static>,
+}
+
+#[derive(Debug)]
+pub enum GGufBuildError {
+    /// Tensor data cannot be converted to the type.
+    UnsupportedType(GGmlType),
+    /// The length of tensor data does not match its shape, in elements or bytes.
+    LengthMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The first dimension of a tensor is not a multiple of the block size of its type.
+    Indivisible { name: String, ty: GGmlType },
+    /// `general.alignment` is not an u32 or u64 of a non-zero power of two.
+    InvalidAlignment(GGufMetaError),
+    /// A meta value cannot be encoded, such as an array with elements of another type.
+    InvalidValue { key: String, error: io::Error },
+}
+
+impl fmt::Display for GGufBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedType(ty) => write!(f, "cannot convert tensor data to {ty:?}"),
+            Self::LengthMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(f, "tensor {name} expects {expected} data, got {actual}"),
+            Self::Indivisible { name, ty } => {
+                write!(
+                    f,
+                    "first dimension of tensor {name} is not divisible by blocks of {ty:?}"
+                )
+            }
+            Self::InvalidAlignment(e) => write!(f, "invalid alignment: {e:?}"),
+            Self::InvalidValue { key, error } => write!(f, "invalid value of {key}: {error}"),
+        }
+    }
+}
+
+impl Error for GGufBuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidValue { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl GGufBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn meta_str(&mut self, key: impl Into<String>, val: impl AsRef<str>) -> &mut Self {
+        self.insert_meta(key, GGufMetaBuf::string(val))
+    }
+
+    #[inline]
+    pub fn meta_bool(&mut self, key: impl Into<String>, val: bool) -> &mut Self {
+        self.insert_meta(key, meta_buf(GGufMetaDataValueType::Bool, vec![val as u8]))
+    }
+
+    /// Sets a scalar meta kv, `general.alignment` takes effect when the model is built.
+    #[inline]
+    pub fn meta<T: GGufMetaScalar>(&mut self, key: impl Into<String>, val: T) -> &mut Self {
+        self.insert_meta(key, GGufMetaBuf::scalar(val))
+    }
+
+    #[inline]
+    pub fn meta_arr<T: GGufMetaScalar>(&mut self, key: impl Into<String>, val: &[T]) -> &mut Self {
+        self.insert_meta(key, GGufMetaBuf::array(val))
+    }
+
+    #[inline]
+    pub fn meta_str_arr(&mut self, key: impl Into<String>, val: &[impl AsRef<str>]) -> &mut Self {
+        self.insert_meta(key, GGufMetaBuf::str_array(val))
+    }
+
+    /// Sets a meta kv of any value, including nested arrays.
+    pub fn meta_value(
+        &mut self,
+        key: impl Into<String>,
+        val: &GGufMetaValue,
+    ) -> Result<&mut Self, GGufBuildError> {
+        let key = key.into();
+        match val.to_bytes() {
+            Ok(value) => Ok(self.insert_meta(key, meta_buf(val.ty(), value))),
+            Err(error) => Err(GGufBuildError::InvalidValue { key, error }),
+        }
+    }
+
+    /// Adds a tensor with `f32` data, converted to `ty`.
+    pub fn tensor_f32(
+        &mut self,
+        name: impl Into<String>,
+        ty: GGmlType,
+        shape: impl Into<Vec<u64>>,
+        data: &[f32],
+    ) -> Result<&mut Self, GGufBuildError> {
+        let name = name.into();
+        let shape = shape.into();
+        check_shape(&name, ty, &shape, data.len())?;
+        let data = match ty {
+            GGmlType::F32 => as_bytes(data).to_vec(),
+            _ => convert(data, ty)?,
+        };
+        self.model.insert_tensor(name, ty, shape, data);
+        Ok(self)
+    }
+
+    /// Adds a tensor with `f16` data, converted to `ty`.
+    pub fn tensor_f16(
+        &mut self,
+        name: impl Into<String>,
+        ty: GGmlType,
+        shape: impl Into<Vec<u64>>,
+        data: &[f16],
+    ) -> Result<&mut Self, GGufBuildError> {
+        let name = name.into();
+        let shape = shape.into();
+        check_shape(&name, ty, &shape, data.len())?;
+        let data = match ty {
+            GGmlType::F32 => quantize::<f32, f16, 1>(data, f16::dequantize_slice),
+            GGmlType::F16 => as_bytes(data).to_vec(),
+            _ => convert(data, ty)?,
+        };
+        self.model.insert_tensor(name, ty, shape, data);
+        Ok(self)
+    }
+
+    /// Adds a tensor with data already encoded in `ty`.
+    pub fn tensor_bytes(
+        &mut self,
+        name: impl Into<String>,
+        ty: GGmlType,
+        shape: impl Into<Vec<u64>>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<&mut Self, GGufBuildError> {
+        let name = name.into();
+        let shape = shape.into();
+        let data = data.into();
+        let expected = ty.size().elements_to_bytes(&shape);
+        if data.len() != expected {
+            return Err(GGufBuildError::LengthMismatch {
+                name,
+                expected,
+                actual: data.len(),
+            });
+        }
+        self.model.insert_tensor(name, ty, shape, data);
+        Ok(self)
+    }
+
+    /// Builds the model, taking `general.alignment` out of the metadata.
+    pub fn build(self) -> Result<GGufModel<'static>, GGufBuildError> {
+        let Self { mut model } = self;
+        model.alignment = model
+            .general_alignment()
+            .map_err(GGufBuildError::InvalidAlignment)?;
+        model.remove_meta(GENERAL_ALIGNMENT);
+        Ok(model)
+    }
+
+    #[inline]
+    fn insert_meta(&mut self, key: impl Into<String>, val: GGufMetaBuf<'static>) -> &mut Self {
+        self.model.insert_meta(key.into(), val.ty, val.value);
+        self
+    }
+}
+
+#[inline]
+fn meta_buf(ty: GGufMetaDataValueType, value: Vec<u8>) -> GGufMetaBuf<'static> {
+    GGufMetaBuf {
+        ty,
+        value: value.into(),
+    }
+}
+
+/// Checks the element count and the block division of the first dimension.
+fn check_shape(name: &str, ty: GGmlType, shape: &[u64], len: usize) -> Result<(), GGufBuildError> {
+    let expected = shape.iter().product::<u64>() as usize;
+    if len != expected {
+        return Err(GGufBuildError::LengthMismatch {
+            name: name.into(),
+            expected,
+            actual: len,
+        });
+    }
+    let block = ty.size().block_size as u64;
+    if shape.first().is_some_and(|&d| !d.is_multiple_of(block)) {
+        return Err(GGufBuildError::Indivisible {
+            name: name.into(),
+            ty,
+        });
+    }
+    Ok(())
+}
+
+/// Converts `f32` or `f16` data to a type supported by ggml-quants.
+fn convert<T>(data: &[T], ty: GGmlType) -> Result<Vec<u8>, GGufBuildError>
+where
+    T: Send + Sync,
+    f16: Quantize<T, 1>,
+    bf16: Quantize<T, 1>,
+    Q4_0: Quantize<T, 32>,
+    Q4_1: Quantize<T, 32>,
+    Q5_0: Quantize<T, 32>,
+    Q5_1: Quantize<T, 32>,
+    Q8_0: Quantize<T, 32>,
+    Q8_1: Quantize<T, 32>,
+    Q8K: Quantize<T, 256>,
+{
+    macro_rules! quantize {
+        ($blk:ty, $n:literal) => {
+            quantize::<$blk, T, $n>(data, |dst, src| <$blk>::quantize_slice(dst, src))
+        };
+    }
+    #[rustfmt::skip]
+    let ans = match ty {
+        GGmlType::F16  => quantize!(f16 ,   1),
+        GGmlType::BF16 => quantize!(bf16,   1),
+        GGmlType::Q4_0 => quantize!(Q4_0,  32),
+        GGmlType::Q4_1 => quantize!(Q4_1,  32),
+        GGmlType::Q5_0 => quantize!(Q5_0,  32),
+        GGmlType::Q5_1 => quantize!(Q5_1,  32),
+        GGmlType::Q8_0 => quantize!(Q8_0,  32),
+        GGmlType::Q8_1 => quantize!(Q8_1,  32),
+        GGmlType::Q8K  => quantize!(Q8K , 256),
+        ty => return Err(GGufBuildError::UnsupportedType(ty)),
+    };
+    Ok(ans)
+}
+
+/// Fills a buffer of `Dst` converted from `src`, `N` source elements for each destination element.
+fn quantize<Dst: DataBlock, Src, const N: usize>(
+    src: &[Src],
+    f: impl FnOnce(&mut [Dst], &[Src]) -> Result<(), ggml_quants::QuantizeError>,
+) -> Vec<u8> {
+    let mut dst = (0..src.len() / N).map(|_| Dst::ZEROS).collect::<Vec<_>>();
+    // 形状已检查，转换不会失败
+    f(&mut dst, src).unwrap();
+    as_bytes(&dst).to_vec()
+}
+
+#[inline]
+fn as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe { from_raw_parts(data.as_ptr().cast(), size_of_val(data)) }
+}
+
+#[test]
+fn test_builder() {
+    use crate::{GGuf, GGufMetaMapExt};
+
+    let f32s = (0..64).map(|i| i as f32 / 8.).collect::<Vec<_>>();
+    let f16s = [1., 2., 3., 4.].map(f16::from_f32);
+
+    let mut builder = GGufBuilder::new();
+    builder
+        .meta_str("general.name", "test")
+        .meta("general.alignment", 64u64)
+        .meta_bool("test.bool", true)
+        .meta_arr("test.arr", &[1u16, 2])
+        .meta_str_arr("test.strs", &["a", "b"]);
+    builder
+        .tensor_f32("q8", GGmlType::Q8_0, [32, 2], &f32s)
+        .unwrap()
+        .tensor_f32("f32", GGmlType::F32, [64], &f32s)
+        .unwrap()
+        .tensor_f16("f16", GGmlType::F32, [4], &f16s)
+        .unwrap()
+        .tensor_bytes("i8", GGmlType::I8, [3], [1, 2, 3])
+        .unwrap();
+    let model = builder.build().unwrap();
+    assert_eq!(model.alignment, 64);
+
+    let mut buf = Vec::new();
+    model.write(&mut buf, true).unwrap();
+    let gguf = GGuf::new(&buf).unwrap();
+    assert_eq!(gguf.header.metadata_kv_count, 5);
+    assert_eq!(gguf.header.tensor_count, 4);
+    assert_eq!(gguf.alignment, 64);
+    assert_eq!(gguf.general_name().unwrap(), "test");
+    assert!(gguf.get_bool("test.bool").unwrap());
+    assert_eq!(gguf.tensor_data("f32"), Some(as_bytes(&f32s)));
+    assert_eq!(gguf.tensor_data("f16"), Some(as_bytes(&[1f32, 2., 3., 4.])));
+    assert_eq!(gguf.tensor_data("i8"), Some(&[1, 2, 3][..]));
+
+    let q8 = gguf.tensor_data("q8").unwrap();
+    let q8 = unsafe { from_raw_parts(q8.as_ptr().cast::<Q8_0>(), 2) };
+    let mut dequant = [0f32; 64];
+    Q8_0::dequantize_slice(&mut dequant, q8).unwrap();
+    for (a, b) in std::iter::zip(f32s, dequant) {
+        assert!((a - b).abs() < 0.05)
+    }
+
+    let mut builder = GGufBuilder::new();
+    assert!(matches!(
+        builder.tensor_f32("x", GGmlType::Q2K, [256], &[0.; 256]),
+        Err(GGufBuildError::UnsupportedType(GGmlType::Q2K))
+    ));
+    assert!(matches!(
+        builder.tensor_f32("x", GGmlType::F32, [4], &[0.; 3]),
+        Err(GGufBuildError::LengthMismatch {
+            expected: 4,
+            actual: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        builder.tensor_f32("x", GGmlType::Q8_0, [16, 2], &[0.; 32]),
+        Err(GGufBuildError::Indivisible { .. })
+    ));
+    assert!(matches!(
+        builder.tensor_bytes("x", GGmlType::Q8_0, [32], vec![0; 32]),
+        Err(GGufBuildError::LengthMismatch { expected: 34, .. })
+    ));
+    assert!(matches!(
+        builder.meta_value(
+            "x",
+            &GGufMetaValue::Array(GGufMetaDataValueType::U8, vec!["s".into()])
+        ),
+        Err(GGufBuildError::InvalidValue { .. })
+    ));
+    builder
+        .meta_value(
+            "x",
+            &GGufMetaValue::Array(GGufMetaDataValueType::U8, vec![1u8.into()]),
+        )
+        .unwrap();
+    builder.meta("general.alignment", 48u32);
+    assert!(matches!(
+        builder.build(),
+        Err(GGufBuildError::InvalidAlignment(_))
+    ));
+}
@@ -0,0 +1,216 @@
+use super::GGufTemplateError;
+
+#[derive(Clone, PartialEq, Debug)]
+pub(super) enum Token {
+    Text(String),
+    /// `{{`
+    ExprStart,
+    /// `}}`
+    ExprEnd,
+    /// `{%`
+    StmtStart,
+    /// `%}`
+    StmtEnd,
+    Name(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Punct(&'static str),
+}
+
+#[rustfmt::skip]
+const PUNCTS: &[&str] = &[
+    "==", "!=", "<=", ">=", "//", "**",
+    "<", ">", "+", "-", "*", "/", "%", "~", "|", ".", ",", ":", "=",
+    "(", ")", "[", "]", "{", "}",
+];
+
+/// Whitespace handling of the text after a tag.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Trim {
+    None,
+    /// `trim_blocks`: removes the first newline.
+    Newline,
+    /// `-`: removes all leading whitespace.
+    All,
+}
+
+/// Splits the template into tokens, applying whitespace control as HF does,
+/// that is with `trim_blocks` and `lstrip_blocks` enabled.
+pub(super) fn tokenize(src: &str) -> Result<Vec<Token>, GGufTemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = src;
+    let mut trim = Trim::None;
+    loop {
+        let next = ["{{", "{%", "{#"]
+            .iter()
+            .filter_map(|open| rest.find(open))
+            .min();
+        let (text, tag) = match next {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+
+        let mut text = match trim {
+            Trim::None => text,
+            Trim::Newline => text
+                .strip_prefix("\r\n")
+                .or_else(|| text.strip_prefix('\n'))
+                .unwrap_or(text),
+            Trim::All => text.trim_start(),
+        };
+        if tag.is_empty() {
+            if !text.is_empty() {
+                tokens.push(Token::Text(text.into()))
+            }
+            break Ok(tokens);
+        }
+
+        let open = &tag[..2];
+        let modifier = tag[2..].chars().next();
+        let is_block = open != "{{";
+        if modifier == Some('-') {
+            text = text.trim_end()
+        } else if is_block && modifier != Some('+') {
+            // lstrip_blocks：去掉标签前同一行内的空白
+            let line = text.rfind('\n').map_or(0, |i| i + 1);
+            if text[line..].chars().all(|c| c == ' ' || c == '\t') {
+                text = &text[..line]
+            }
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.into()))
+        }
+
+        let body = &tag[2 + modifier.filter(|c| *c == '-' || *c == '+').map_or(0, |_| 1)..];
+        let (end, modifier) = match open {
+            "{#" => {
+                let end = body.find("#}").ok_or_else(|| syntax("unclosed comment"))?;
+                (&body[end + 2..], body[..end].chars().next_back())
+            }
+            _ => {
+                let (start, close) = if open == "{{" {
+                    (Token::ExprStart, Token::ExprEnd)
+                } else {
+                    (Token::StmtStart, Token::StmtEnd)
+                };
+                tokens.push(start);
+                let (rest, modifier) = tokenize_tag(body, &close, &mut tokens)?;
+                tokens.push(close);
+                (rest, modifier)
+            }
+        };
+        trim = match modifier {
+            Some('-') => Trim::All,
+            Some('+') => Trim::None,
+            _ if is_block => Trim::Newline,
+            _ => Trim::None,
+        };
+        rest = end
+    }
+}
+
+/// Tokenizes the content of a tag, returns the text after the tag and the modifier before the closer.
+fn tokenize_tag<'a>(
+    mut src: &'a str,
+    close: &Token,
+    tokens: &mut Vec<Token>,
+) -> Result<(&'a str, Option<char>), GGufTemplateError> {
+    let closer = if *close == Token::ExprEnd { "}}" } else { "%}" };
+    let mut depth = 0usize;
+    loop {
+        src = src.trim_start();
+        if depth == 0 {
+            for modifier in ["-", "+", ""] {
+                if let Some(rest) = src
+                    .strip_prefix(modifier)
+                    .and_then(|s| s.strip_prefix(closer))
+                {
+                    return Ok((rest, modifier.chars().next()));
+                }
+            }
+        }
+        let Some(c) = src.chars().next() else {
+            return Err(syntax("unclosed tag"));
+        };
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let end = src
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(src.len());
+            tokens.push(Token::Name(src[..end].into()));
+            src = &src[end..]
+        } else if c.is_ascii_digit() {
+            let end = src.find(|c: char| !c.is_ascii_digit()).unwrap_or(src.len());
+            let frac = src[end..]
+                .strip_prefix('.')
+                .filter(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+                .map(|s| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+            match frac {
+                Some(len) => {
+                    let end = end + 1 + len;
+                    tokens.push(Token::Float(src[..end].parse().unwrap()));
+                    src = &src[end..]
+                }
+                None => {
+                    let n = src[..end]
+                        .parse()
+                        .map_err(|_| syntax("integer out of range"))?;
+                    tokens.push(Token::Int(n));
+                    src = &src[end..]
+                }
+            }
+        } else if c == '\'' || c == '"' {
+            let (s, rest) = read_str(&src[1..], c)?;
+            tokens.push(Token::Str(s));
+            src = rest
+        } else {
+            let Some(&punct) = PUNCTS.iter().find(|p| src.starts_with(**p)) else {
+                return Err(syntax(format!("unexpected character `{c}`")));
+            };
+            match punct {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            tokens.push(Token::Punct(punct));
+            src = &src[punct.len()..]
+        }
+    }
+}
+
+fn read_str(src: &str, quote: char) -> Result<(String, &str), GGufTemplateError> {
+    let mut ans = String::new();
+    let mut chars = src.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((ans, &src[i + 1..])),
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => ans.push('\n'),
+                Some('t') => ans.push('\t'),
+                Some('r') => ans.push('\r'),
+                Some('u') => {
+                    let hex = chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| syntax("invalid unicode escape"))?;
+                    ans.push(c)
+                }
+                Some(c @ ('\\' | '\'' | '"')) => ans.push(c),
+                Some(c) => {
+                    ans.push('\\');
+                    ans.push(c)
+                }
+                None => break,
+            },
+            c => ans.push(c),
+        }
+    }
+    Err(syntax("unclosed string"))
+}
+
+#[inline]
+fn syntax(msg: impl Into<String>) -> GGufTemplateError {
+    GGufTemplateError::Syntax(msg.into())
+}
@@ -0,0 +1,219 @@
+//! Renderer for the subset of jinja used by `tokenizer.chat_template`, following HF transformers.
+
+mod lexer;
+mod parser;
+mod render;
+mod value;
+
+use indexmap::IndexMap;
+use parser::{Node, Parser};
+use render::Context;
+use std::{collections::HashMap, error::Error, fmt};
+use value::Value;
+
+/// A parsed chat template.
+pub struct GGufChatTemplate {
+    nodes: Vec<Node>,
+}
+
+/// A message in a conversation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GGufChatMessage<'a> {
+    pub role: &'a str,
+    pub content: &'a str,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GGufTemplateError {
+    /// The template is not valid jinja.
+    Syntax(String),
+    /// The template failed during rendering.
+    Render(String),
+    /// The template called `raise_exception`.
+    Raised(String),
+}
+
+impl fmt::Display for GGufTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(msg) => write!(f, "template syntax error: {msg}"),
+            Self::Render(msg) => write!(f, "template render error: {msg}"),
+            Self::Raised(msg) => write!(f, "template raised exception: {msg}"),
+        }
+    }
+}
+
+impl Error for GGufTemplateError {}
+
+impl GGufChatTemplate {
+    /// Parses a template, usually the value of `tokenizer.chat_template`.
+    pub fn new(src: &str) -> Result<Self, GGufTemplateError> {
+        let tokens = lexer::tokenize(src)?;
+        let nodes = Parser::new(tokens).parse()?;
+        Ok(Self { nodes })
+    }
+
+    /// Renders the conversation into the prompt string.
+    ///
+    /// With `add_generation_prompt`, the template appends the header of the assistant turn.
+    pub fn render(
+        &self,
+        messages: &[GGufChatMessage],
+        bos_token: &str,
+        eos_token: &str,
+        add_generation_prompt: bool,
+    ) -> Result<String, GGufTemplateError> {
+        let messages = messages
+            .iter()
+            .map(|msg| {
+                let mut map = IndexMap::new();
+                map.insert("role".to_string(), Value::from(msg.role));
+                map.insert("content".to_string(), Value::from(msg.content));
+                Value::from(map)
+            })
+            .collect::<Vec<_>>();
+
+        let mut globals = HashMap::new();
+        globals.insert("messages".into(), messages.into());
+        globals.insert("bos_token".into(), bos_token.into());
+        globals.insert("eos_token".into(), eos_token.into());
+        globals.insert("add_generation_prompt".into(), add_generation_prompt.into());
+
+        let mut out = String::new();
+        Context::new(globals).render(&self.nodes, &mut out)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+const CONVERSATION: &[GGufChatMessage] = &[
+    GGufChatMessage {
+        role: "system",
+        content: "You are a helpful assistant.",
+    },
+    GGufChatMessage {
+        role: "user",
+        content: "Hello",
+    },
+    GGufChatMessage {
+        role: "assistant",
+        content: " Hi! ",
+    },
+    GGufChatMessage {
+        role: "user",
+        content: "Who are you?",
+    },
+];
+
+#[test]
+fn test_chatml() {
+    const TEMPLATE: &str = "\
+{% for message in messages %}
+    {{- '<|im_start|>' + message['role'] + '\\n' + message['content'] + '<|im_end|>' + '\\n' }}
+{%- endfor %}
+{%- if add_generation_prompt %}
+    {{- '<|im_start|>assistant\\n' }}
+{%- endif %}
+";
+    let template = GGufChatTemplate::new(TEMPLATE).unwrap();
+    assert_eq!(
+        template.render(CONVERSATION, "", "", true).unwrap(),
+        "\
+<|im_start|>system\nYou are a helpful assistant.<|im_end|>
+<|im_start|>user\nHello<|im_end|>
+<|im_start|>assistant\n Hi! <|im_end|>
+<|im_start|>user\nWho are you?<|im_end|>
+<|im_start|>assistant\n"
+    );
+}
+
+#[test]
+fn test_llama2() {
+    const TEMPLATE: &str = "\
+{% if messages[0]['role'] == 'system' %}
+    {% set loop_messages = messages[1:] %}
+    {% set system_message = messages[0]['content'] %}
+{% else %}
+    {% set loop_messages = messages %}
+    {% set system_message = false %}
+{% endif %}
+{% for message in loop_messages %}
+    {% if (message['role'] == 'user') != (loop.index0 % 2 == 0) %}
+        {{ raise_exception('Conversation roles must alternate user/assistant/user/assistant/...') }}
+    {% endif %}
+    {% if loop.index0 == 0 and system_message != false %}
+        {% set content = '<<SYS>>\\n' + system_message + '\\n<</SYS>>\\n\\n' + message['content'] %}
+    {% else %}
+        {% set content = message['content'] %}
+    {% endif %}
+    {% if message['role'] == 'user' %}
+        {{ bos_token + '[INST] ' + content.strip() + ' [/INST]' }}
+    {% elif message['role'] == 'assistant' %}
+        {{ ' '  + content.strip() + ' ' + eos_token }}
+    {% endif %}
+{% endfor %}";
+    let template = GGufChatTemplate::new(TEMPLATE).unwrap();
+    let text = template.render(CONVERSATION, "<s>", "</s>", false).unwrap();
+    assert_eq!(
+        text.split_whitespace().collect::<Vec<_>>().join(" "),
+        "<s>[INST] <<SYS>> You are a helpful assistant. <</SYS>> Hello [/INST] Hi! </s> <s>[INST] Who are you? [/INST]"
+    );
+
+    assert!(matches!(
+        template.render(&CONVERSATION[2..], "<s>", "</s>", false),
+        Err(GGufTemplateError::Raised(msg)) if msg.contains("alternate")
+    ));
+}
+
+#[test]
+fn test_features() {
+    const TEMPLATE: &str = r#"
+{%- macro tag(name, close=false) -%}
+    <{{ '/' if close }}{{ name | upper }}>
+{%- endmacro -%}
+{%- set ns = namespace(count=0, roles=[]) -%}
+{%- for m in messages if m.role != 'system' -%}
+    {%- set ns.count = ns.count + 1 -%}
+    {%- set ns.roles = ns.roles + [m.role] -%}
+    {{ tag(m.role) }}{{ m.content | trim }}{{ tag(m.role, close=true) }}
+    {%- if not loop.last %}|{% endif -%}
+{%- else -%}
+    empty
+{%- endfor -%}
+;{{ ns.count }};{{ ns.roles | unique | join(',') }};{{ messages | selectattr('role', 'equalto', 'user') | map(attribute='content') | list | tojson }}
+;{{ range(3) | list }};{{ 'a,b' .split(',')[-1] }};{{ 7 // 2 }}{{ 7 % 3 }}{{ "x" * 3 }};{{ undefined_var | default('d') }};{{ 'abc'[::-1] }}
+{%- for k, v in {'x': 1, 'y': 2}.items() %};{{ k }}={{ v }}{% endfor -%}
+{# comment #}"#;
+    let template = GGufChatTemplate::new(TEMPLATE).unwrap();
+    assert_eq!(
+        template.render(CONVERSATION, "", "", false).unwrap(),
+        r#"<USER>Hello</USER>|<ASSISTANT>Hi!</ASSISTANT>|<USER>Who are you?</USER>;3;user,assistant;["Hello", "Who are you?"]
+;[0, 1, 2];b;31xxx;d;cba;x=1;y=2"#
+    );
+    assert_eq!(
+        template.render(&CONVERSATION[..1], "", "", false).unwrap(),
+        "empty;0;;[]\n;[0, 1, 2];b;31xxx;d;cba;x=1;y=2"
+    );
+    assert!(matches!(
+        GGufChatTemplate::new("{% if x %}"),
+        Err(GGufTemplateError::Syntax(_))
+    ));
+
+    // 不可信的模板不能让渲染崩溃
+    let render = |s| GGufChatTemplate::new(s)?.render(CONVERSATION, "", "", false);
+    assert_eq!(render("{{ 'abc'[1::9223372036854775807] }}").unwrap(), "b");
+    assert_eq!(
+        render("{{ 'abc'[-2::-9223372036854775807] }}").unwrap(),
+        "b"
+    );
+    assert_eq!(
+        render("{{ range(9223372036854775800, 9223372036854775807, 5) | list }}").unwrap(),
+        "[9223372036854775800, 9223372036854775805]"
+    );
+    for s in [
+        "{{ range(9223372036854775807) | list }}",
+        "{{ 'ab' * 9223372036854775807 }}",
+    ] {
+        assert!(matches!(render(s), Err(GGufTemplateError::Render(_))))
+    }
+}
@@ -0,0 +1,539 @@
+use super::{GGufTemplateError, lexer::Token, value::Value};
+
+#[derive(Debug)]
+pub(super) enum Node {
+    Text(String),
+    Expr(Expr),
+    If(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+    For {
+        vars: Vec<String>,
+        iter: Expr,
+        cond: Option<Expr>,
+        body: Vec<Node>,
+        else_: Vec<Node>,
+    },
+    Set(Target, Expr),
+    SetBlock(String, Vec<Node>),
+    Macro(std::rc::Rc<Macro>),
+    Break,
+    Continue,
+}
+
+#[derive(Debug)]
+pub(super) enum Target {
+    Var(String),
+    Attr(String, String),
+    Tuple(Vec<String>),
+}
+
+#[derive(Debug)]
+pub(super) struct Macro {
+    pub name: String,
+    pub params: Vec<(String, Option<Expr>)>,
+    pub body: Vec<Node>,
+}
+
+#[derive(Debug)]
+pub(super) enum Expr {
+    Lit(Value),
+    Var(String),
+    List(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    Attr(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, [Option<Box<Expr>>; 3]),
+    Call(Box<Expr>, Args),
+    Filter(Box<Expr>, String, Args),
+    Test(Box<Expr>, String, Args, bool),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+}
+
+/// Positional and keyword arguments.
+#[derive(Default, Debug)]
+pub(super) struct Args {
+    pub pos: Vec<Expr>,
+    pub kw: Vec<(String, Expr)>,
+}
+
+const KEYWORDS: &[&str] = &["and", "or", "not", "in", "is", "if", "else"];
+
+pub(super) struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    pub fn parse(mut self) -> Result<Vec<Node>, GGufTemplateError> {
+        let (nodes, end) = self.parse_nodes(&[])?;
+        match end {
+            None => Ok(nodes),
+            Some(end) => Err(syntax(format!("unexpected `{end}`"))),
+        }
+    }
+
+    /// Parses nodes until one of the `ends` statements, which is consumed and returned.
+    fn parse_nodes(
+        &mut self,
+        ends: &[&str],
+    ) -> Result<(Vec<Node>, Option<String>), GGufTemplateError> {
+        let mut nodes = Vec::new();
+        while let Some(token) = self.next() {
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::ExprStart => {
+                    nodes.push(Node::Expr(self.parse_expr()?));
+                    self.expect(&Token::ExprEnd)?
+                }
+                Token::StmtStart => {
+                    let keyword = self.name()?;
+                    if ends.contains(&&*keyword) {
+                        return Ok((nodes, Some(keyword)));
+                    }
+                    nodes.extend(self.parse_stmt(&keyword)?)
+                }
+                t => return Err(syntax(format!("unexpected {t:?}"))),
+            }
+        }
+        if ends.is_empty() {
+            Ok((nodes, None))
+        } else {
+            Err(syntax(format!("missing `{}`", ends.last().unwrap())))
+        }
+    }
+
+    fn parse_stmt(&mut self, keyword: &str) -> Result<Option<Node>, GGufTemplateError> {
+        let node = match keyword {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut cond = self.parse_expr()?;
+                loop {
+                    self.expect(&Token::StmtEnd)?;
+                    let (body, end) = self.parse_nodes(&["elif", "else", "endif"])?;
+                    branches.push((cond, body));
+                    match end.as_deref() {
+                        Some("elif") => cond = self.parse_expr()?,
+                        Some("else") => {
+                            self.expect(&Token::StmtEnd)?;
+                            let (else_, _) = self.parse_nodes(&["endif"])?;
+                            break Node::If(branches, else_);
+                        }
+                        _ => break Node::If(branches, vec![]),
+                    }
+                }
+            }
+            "for" => {
+                let mut vars = vec![self.name()?];
+                while self.eat_punct(",") {
+                    vars.push(self.name()?)
+                }
+                self.expect_name("in")?;
+                let iter = self.parse_or()?;
+                let cond = if self.eat_name("if") {
+                    Some(self.parse_or()?)
+                } else {
+                    None
+                };
+                self.expect(&Token::StmtEnd)?;
+                let (body, end) = self.parse_nodes(&["else", "endfor"])?;
+                let else_ = if end.as_deref() == Some("else") {
+                    self.expect(&Token::StmtEnd)?;
+                    self.parse_nodes(&["endfor"])?.0
+                } else {
+                    vec![]
+                };
+                Node::For {
+                    vars,
+                    iter,
+                    cond,
+                    body,
+                    else_,
+                }
+            }
+            "set" => {
+                let name = self.name()?;
+                let target = if self.eat_punct(".") {
+                    Target::Attr(name, self.name()?)
+                } else if self.peek() == Some(&Token::Punct(",")) {
+                    let mut names = vec![name];
+                    while self.eat_punct(",") {
+                        names.push(self.name()?)
+                    }
+                    Target::Tuple(names)
+                } else {
+                    Target::Var(name)
+                };
+                if self.eat_punct("=") {
+                    Node::Set(target, self.parse_expr()?)
+                } else {
+                    let Target::Var(name) = target else {
+                        return Err(syntax("block set only supports a variable"));
+                    };
+                    self.expect(&Token::StmtEnd)?;
+                    let (body, _) = self.parse_nodes(&["endset"])?;
+                    Node::SetBlock(name, body)
+                }
+            }
+            "macro" => {
+                let name = self.name()?;
+                self.expect(&Token::Punct("("))?;
+                let mut params = Vec::new();
+                while !self.eat_punct(")") {
+                    if !params.is_empty() {
+                        self.expect(&Token::Punct(","))?
+                    }
+                    let param = self.name()?;
+                    let default = if self.eat_punct("=") {
+                        Some(self.parse_expr()?)
+                    } else {
+                        None
+                    };
+                    params.push((param, default))
+                }
+                self.expect(&Token::StmtEnd)?;
+                let (body, _) = self.parse_nodes(&["endmacro"])?;
+                Node::Macro(std::rc::Rc::new(Macro { name, params, body }))
+            }
+            "break" => Node::Break,
+            "continue" => Node::Continue,
+            // HF 的 AssistantTracker 扩展，原样渲染内容
+            "generation" | "endgeneration" => {
+                self.expect(&Token::StmtEnd)?;
+                return Ok(None);
+            }
+            _ => return Err(syntax(format!("unknown statement `{keyword}`"))),
+        };
+        self.expect(&Token::StmtEnd)?;
+        Ok(Some(node))
+    }
+
+    pub fn parse_expr(&mut self) -> Result<Expr, GGufTemplateError> {
+        let expr = self.parse_or()?;
+        if self.eat_name("if") {
+            let cond = self.parse_or()?;
+            let else_ = if self.eat_name("else") {
+                Some(Box::new(self.parse_expr()?))
+            } else {
+                None
+            };
+            Ok(Expr::Cond(Box::new(cond), Box::new(expr), else_))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_and()?;
+        while self.eat_name("or") {
+            lhs = Expr::Binary("or", Box::new(lhs), Box::new(self.parse_and()?))
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_not()?;
+        while self.eat_name("and") {
+            lhs = Expr::Binary("and", Box::new(lhs), Box::new(self.parse_not()?))
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, GGufTemplateError> {
+        if self.eat_name("not") {
+            Ok(Expr::Not(Box::new(self.parse_not()?)))
+        } else {
+            self.parse_compare()
+        }
+    }
+
+    fn parse_compare(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_concat()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct(op @ ("==" | "!=" | "<" | ">" | "<=" | ">="))) => *op,
+                Some(Token::Name(n)) if n == "in" => "in",
+                Some(Token::Name(n))
+                    if n == "not"
+                        && matches!(self.tokens.get(self.pos + 1), Some(Token::Name(n)) if n == "in") =>
+                {
+                    self.pos += 1;
+                    "not in"
+                }
+                _ => break Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.parse_concat()?))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_sum()?;
+        while self.eat_punct("~") {
+            lhs = Expr::Binary("~", Box::new(lhs), Box::new(self.parse_sum()?))
+        }
+        Ok(lhs)
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct(op @ ("+" | "-"))) => *op,
+                _ => break Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.parse_product()?))
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, GGufTemplateError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct(op @ ("*" | "/" | "//" | "%"))) => *op,
+                _ => break Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.parse_unary()?))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, GGufTemplateError> {
+        if self.eat_punct("-") {
+            Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+        } else if self.eat_punct("+") {
+            self.parse_unary()
+        } else {
+            let primary = self.parse_primary()?;
+            let postfix = self.parse_postfix(primary)?;
+            self.parse_filters(postfix)
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, GGufTemplateError> {
+        let Some(token) = self.next() else {
+            return Err(syntax("unexpected end of template"));
+        };
+        Ok(match token {
+            Token::Str(mut s) => {
+                // 相邻的字符串字面量自动拼接
+                while let Some(Token::Str(next)) = self.peek() {
+                    s.push_str(next);
+                    self.pos += 1
+                }
+                Expr::Lit(s.into())
+            }
+            Token::Int(n) => Expr::Lit(Value::Int(n)),
+            Token::Float(f) => Expr::Lit(Value::Float(f)),
+            Token::Name(name) => match &*name {
+                "true" | "True" => Expr::Lit(Value::Bool(true)),
+                "false" | "False" => Expr::Lit(Value::Bool(false)),
+                "none" | "None" => Expr::Lit(Value::None),
+                _ => Expr::Var(name),
+            },
+            Token::Punct("(") => {
+                let expr = self.parse_expr()?;
+                if self.peek() == Some(&Token::Punct(",")) {
+                    // 元组按列表处理
+                    let mut items = vec![expr];
+                    while self.eat_punct(",") && self.peek() != Some(&Token::Punct(")")) {
+                        items.push(self.parse_expr()?)
+                    }
+                    self.expect(&Token::Punct(")"))?;
+                    Expr::List(items)
+                } else {
+                    self.expect(&Token::Punct(")"))?;
+                    expr
+                }
+            }
+            Token::Punct("[") => {
+                let mut items = Vec::new();
+                while !self.eat_punct("]") {
+                    if !items.is_empty() {
+                        self.expect(&Token::Punct(","))?;
+                        if self.eat_punct("]") {
+                            break;
+                        }
+                    }
+                    items.push(self.parse_expr()?)
+                }
+                Expr::List(items)
+            }
+            Token::Punct("{") => {
+                let mut items = Vec::new();
+                while !self.eat_punct("}") {
+                    if !items.is_empty() {
+                        self.expect(&Token::Punct(","))?;
+                        if self.eat_punct("}") {
+                            break;
+                        }
+                    }
+                    let k = self.parse_expr()?;
+                    self.expect(&Token::Punct(":"))?;
+                    items.push((k, self.parse_expr()?))
+                }
+                Expr::Dict(items)
+            }
+            t => return Err(syntax(format!("unexpected {t:?}"))),
+        })
+    }
+
+    fn parse_postfix(&mut self, mut expr: Expr) -> Result<Expr, GGufTemplateError> {
+        loop {
+            expr = if self.eat_punct(".") {
+                Expr::Attr(Box::new(expr), self.name()?)
+            } else if self.eat_punct("[") {
+                let mut parts = [None, None, None];
+                let mut i = 0;
+                loop {
+                    match self.peek() {
+                        Some(Token::Punct(":")) if i < 2 => {
+                            self.pos += 1;
+                            i += 1
+                        }
+                        Some(Token::Punct("]")) => {
+                            self.pos += 1;
+                            break;
+                        }
+                        _ if parts[i].is_none() => parts[i] = Some(Box::new(self.parse_expr()?)),
+                        _ => return Err(syntax("invalid subscript")),
+                    }
+                }
+                if i == 0 {
+                    let Some(index) = parts[0].take() else {
+                        return Err(syntax("empty subscript"));
+                    };
+                    Expr::Index(Box::new(expr), index)
+                } else {
+                    Expr::Slice(Box::new(expr), parts)
+                }
+            } else if self.eat_punct("(") {
+                Expr::Call(Box::new(expr), self.parse_args()?)
+            } else {
+                break Ok(expr);
+            }
+        }
+    }
+
+    fn parse_filters(&mut self, mut expr: Expr) -> Result<Expr, GGufTemplateError> {
+        loop {
+            expr = if self.eat_punct("|") {
+                let name = self.name()?;
+                let args = if self.eat_punct("(") {
+                    self.parse_args()?
+                } else {
+                    Args::default()
+                };
+                Expr::Filter(Box::new(expr), name, args)
+            } else if self.eat_name("is") {
+                let negate = self.eat_name("not");
+                let name = self.name()?;
+                let args = if self.eat_punct("(") {
+                    self.parse_args()?
+                } else {
+                    // 单个参数的测试可以省略括号，如 `is sameas false`
+                    match self.peek() {
+                        Some(Token::Str(_) | Token::Int(_) | Token::Float(_)) => Args {
+                            pos: vec![self.parse_primary()?],
+                            kw: vec![],
+                        },
+                        Some(Token::Name(n)) if !KEYWORDS.contains(&&**n) => Args {
+                            pos: vec![self.parse_primary()?],
+                            kw: vec![],
+                        },
+                        _ => Args::default(),
+                    }
+                };
+                Expr::Test(Box::new(expr), name, args, negate)
+            } else {
+                break Ok(expr);
+            }
+        }
+    }
+
+    /// Parses arguments after `(`.
+    fn parse_args(&mut self) -> Result<Args, GGufTemplateError> {
+        let mut args = Args::default();
+        let mut first = true;
+        while !self.eat_punct(")") {
+            if !first {
+                self.expect(&Token::Punct(","))?;
+                if self.eat_punct(")") {
+                    break;
+                }
+            }
+            first = false;
+            match (self.peek(), self.tokens.get(self.pos + 1)) {
+                (Some(Token::Name(name)), Some(Token::Punct("="))) => {
+                    let name = name.clone();
+                    self.pos += 2;
+                    args.kw.push((name, self.parse_expr()?))
+                }
+                _ => args.pos.push(self.parse_expr()?),
+            }
+        }
+        Ok(args)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn name(&mut self) -> Result<String, GGufTemplateError> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            t => Err(syntax(format!("expected a name, found {t:?}"))),
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), GGufTemplateError> {
+        match self.next() {
+            Some(t) if t == *expected => Ok(()),
+            t => Err(syntax(format!("expected {expected:?}, found {t:?}"))),
+        }
+    }
+
+    fn expect_name(&mut self, name: &str) -> Result<(), GGufTemplateError> {
+        if self.eat_name(name) {
+            Ok(())
+        } else {
+            Err(syntax(format!("expected `{name}`")))
+        }
+    }
+
+    fn eat_name(&mut self, name: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Name(n)) if n == name) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[inline]
+fn syntax(msg: impl Into<String>) -> GGufTemplateError {
+    GGufTemplateError::Syntax(msg.into())
+}
@@ -0,0 +1,836 @@
+use super::{
+    GGufTemplateError,
+    parser::{Args, Expr, Macro, Node, Target},
+    value::Value,
+};
+use indexmap::IndexMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Evaluated keyword arguments.
+type Kwargs = Vec<(String, Value)>;
+
+/// Max length of lists and strings built by `range` and `*`, templates are untrusted.
+const MAX_LEN: usize = 1 << 20;
+
+/// Control flow after rendering nodes.
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+pub(super) struct Context {
+    scopes: Vec<HashMap<String, Value>>,
+}
+
+impl Context {
+    pub fn new(globals: HashMap<String, Value>) -> Self {
+        Self {
+            scopes: vec![globals],
+        }
+    }
+
+    fn get(&self, name: &str) -> Value {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.get(name))
+            .cloned()
+            .unwrap_or(Value::Undefined)
+    }
+
+    fn set(&mut self, name: String, value: Value) {
+        self.scopes.last_mut().unwrap().insert(name, value);
+    }
+
+    pub fn render(&mut self, nodes: &[Node], out: &mut String) -> Result<(), GGufTemplateError> {
+        match self.render_nodes(nodes, out)? {
+            Flow::Normal => Ok(()),
+            _ => Err(render("`break` or `continue` outside of a loop")),
+        }
+    }
+
+    fn render_nodes(
+        &mut self,
+        nodes: &[Node],
+        out: &mut String,
+    ) -> Result<Flow, GGufTemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Expr(expr) => out.push_str(&self.eval(expr)?.to_string()),
+                Node::If(branches, else_) => {
+                    let mut body = else_;
+                    for (cond, branch) in branches {
+                        if self.eval(cond)?.is_true() {
+                            body = branch;
+                            break;
+                        }
+                    }
+                    match self.render_nodes(body, out)? {
+                        Flow::Normal => {}
+                        flow => return Ok(flow),
+                    }
+                }
+                Node::For {
+                    vars,
+                    iter,
+                    cond,
+                    body,
+                    else_,
+                } => self.render_for(vars, iter, cond.as_ref(), body, else_, out)?,
+                Node::Set(target, expr) => {
+                    let value = self.eval(expr)?;
+                    self.assign(target, value)?
+                }
+                Node::SetBlock(name, body) => {
+                    let mut buf = String::new();
+                    self.render_nodes(body, &mut buf)?;
+                    self.set(name.clone(), buf.into())
+                }
+                Node::Macro(m) => self.set(m.name.clone(), Value::Macro(m.clone())),
+                Node::Break => return Ok(Flow::Break),
+                Node::Continue => return Ok(Flow::Continue),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn render_for(
+        &mut self,
+        vars: &[String],
+        iter: &Expr,
+        cond: Option<&Expr>,
+        body: &[Node],
+        else_: &[Node],
+        out: &mut String,
+    ) -> Result<(), GGufTemplateError> {
+        let iter = self.eval(iter)?;
+        let Some(items) = iter.iter() else {
+            return Err(render(format!("{} is not iterable", iter.type_name())));
+        };
+        // 遍历字典时产生键值对
+        let items = match &iter {
+            Value::Map(m) if vars.len() > 1 => m
+                .iter()
+                .map(|(k, v)| Value::from(vec![Value::from(&**k), v.clone()]))
+                .collect(),
+            _ => items,
+        };
+
+        self.scopes.push(HashMap::new());
+        let items = match cond {
+            Some(cond) => {
+                let mut filtered = Vec::new();
+                for item in items {
+                    self.bind(vars, item.clone())?;
+                    if self.eval(cond)?.is_true() {
+                        filtered.push(item)
+                    }
+                }
+                filtered
+            }
+            None => items,
+        };
+
+        let len = items.len();
+        let mut ans = Ok(());
+        for (i, item) in items.iter().enumerate() {
+            let mut info = IndexMap::new();
+            info.insert("index".into(), Value::Int(i as i64 + 1));
+            info.insert("index0".into(), Value::Int(i as _));
+            info.insert("revindex".into(), Value::Int((len - i) as _));
+            info.insert("revindex0".into(), Value::Int((len - i - 1) as _));
+            info.insert("first".into(), Value::Bool(i == 0));
+            info.insert("last".into(), Value::Bool(i == len - 1));
+            info.insert("length".into(), Value::Int(len as _));
+            let prev = i
+                .checked_sub(1)
+                .map_or(Value::Undefined, |j| items[j].clone());
+            info.insert("previtem".into(), prev);
+            let next = items.get(i + 1).cloned().unwrap_or(Value::Undefined);
+            info.insert("nextitem".into(), next);
+            self.set("loop".into(), info.into());
+
+            if let Err(e) = self.bind(vars, item.clone()) {
+                ans = Err(e);
+                break;
+            }
+            match self.render_nodes(body, out) {
+                Ok(Flow::Break) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    ans = Err(e);
+                    break;
+                }
+            }
+        }
+        self.scopes.pop();
+        ans?;
+
+        if len == 0 {
+            self.render_nodes(else_, out)?;
+        }
+        Ok(())
+    }
+
+    fn bind(&mut self, vars: &[String], item: Value) -> Result<(), GGufTemplateError> {
+        if let [var] = vars {
+            self.set(var.clone(), item);
+            return Ok(());
+        }
+        let values = item.iter().unwrap_or_default();
+        if values.len() != vars.len() {
+            return Err(render(format!(
+                "cannot unpack {} values into {}",
+                values.len(),
+                vars.len()
+            )));
+        }
+        for (var, value) in vars.iter().zip(values) {
+            self.set(var.clone(), value)
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, target: &Target, value: Value) -> Result<(), GGufTemplateError> {
+        match target {
+            Target::Var(name) => self.set(name.clone(), value),
+            Target::Tuple(names) => self.bind(names, value)?,
+            Target::Attr(name, attr) => match self.get(name) {
+                Value::Namespace(ns) => {
+                    ns.borrow_mut().insert(attr.clone(), value);
+                }
+                v => {
+                    return Err(render(format!("cannot set attribute of {}", v.type_name())));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, GGufTemplateError> {
+        Ok(match expr {
+            Expr::Lit(v) => v.clone(),
+            Expr::Var(name) => self.get(name),
+            Expr::List(items) => items
+                .iter()
+                .map(|e| self.eval(e))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            Expr::Dict(items) => {
+                let mut map = IndexMap::new();
+                for (k, v) in items {
+                    let k = self.eval(k)?.to_string();
+                    map.insert(k, self.eval(v)?);
+                }
+                map.into()
+            }
+            Expr::Attr(obj, name) => self.eval(obj)?.attr(name),
+            Expr::Index(obj, index) => {
+                let obj = self.eval(obj)?;
+                let index = self.eval(index)?;
+                match (&obj, &index) {
+                    (Value::List(l), Value::Int(i)) => {
+                        let i = if *i < 0 { l.len() as i64 + i } else { *i };
+                        l.get(i as usize).cloned().unwrap_or(Value::Undefined)
+                    }
+                    (Value::Str(s), Value::Int(i)) => {
+                        let chars = s.chars().collect::<Vec<_>>();
+                        let i = if *i < 0 { chars.len() as i64 + i } else { *i };
+                        chars
+                            .get(i as usize)
+                            .map_or(Value::Undefined, |c| c.to_string().into())
+                    }
+                    (_, Value::Str(key)) => obj.attr(key),
+                    _ => Value::Undefined,
+                }
+            }
+            Expr::Slice(obj, parts) => {
+                let obj = self.eval(obj)?;
+                let mut nums = [None; 3];
+                for (n, part) in nums.iter_mut().zip(parts) {
+                    if let Some(part) = part {
+                        match self.eval(part)? {
+                            Value::Int(i) => *n = Some(i),
+                            Value::None => {}
+                            v => return Err(render(format!("invalid slice {}", v.type_name()))),
+                        }
+                    }
+                }
+                slice(&obj, nums)?
+            }
+            Expr::Call(callee, args) => self.call(callee, args)?,
+            Expr::Filter(obj, name, args) => {
+                let obj = self.eval(obj)?;
+                self.filter(obj, name, args)?
+            }
+            Expr::Test(obj, name, args, negate) => {
+                let obj = self.eval(obj)?;
+                let args = self.eval_args(args)?;
+                Value::Bool(test(&obj, name, &args.0)? != *negate)
+            }
+            Expr::Not(e) => Value::Bool(!self.eval(e)?.is_true()),
+            Expr::Neg(e) => match self.eval(e)? {
+                Value::Int(n) => Value::Int(-n),
+                Value::Float(f) => Value::Float(-f),
+                v => return Err(render(format!("cannot negate {}", v.type_name()))),
+            },
+            Expr::Binary(op, lhs, rhs) => self.binary(op, lhs, rhs)?,
+            Expr::Cond(cond, then, else_) => {
+                if self.eval(cond)?.is_true() {
+                    self.eval(then)?
+                } else if let Some(else_) = else_ {
+                    self.eval(else_)?
+                } else {
+                    Value::Undefined
+                }
+            }
+        })
+    }
+
+    fn eval_args(&mut self, args: &Args) -> Result<(Vec<Value>, Kwargs), GGufTemplateError> {
+        let pos = args
+            .pos
+            .iter()
+            .map(|e| self.eval(e))
+            .collect::<Result<_, _>>()?;
+        let kw = args
+            .kw
+            .iter()
+            .map(|(k, e)| Ok((k.clone(), self.eval(e)?)))
+            .collect::<Result<_, GGufTemplateError>>()?;
+        Ok((pos, kw))
+    }
+
+    fn binary(&mut self, op: &str, lhs: &Expr, rhs: &Expr) -> Result<Value, GGufTemplateError> {
+        // 短路求值
+        match op {
+            "and" => {
+                let lhs = self.eval(lhs)?;
+                return if lhs.is_true() {
+                    self.eval(rhs)
+                } else {
+                    Ok(lhs)
+                };
+            }
+            "or" => {
+                let lhs = self.eval(lhs)?;
+                return if lhs.is_true() {
+                    Ok(lhs)
+                } else {
+                    self.eval(rhs)
+                };
+            }
+            _ => {}
+        }
+
+        let lhs = self.eval(lhs)?;
+        let rhs = self.eval(rhs)?;
+        let mismatch = || {
+            render(format!(
+                "unsupported operand types for {op}: {} and {}",
+                lhs.type_name(),
+                rhs.type_name()
+            ))
+        };
+        Ok(match op {
+            "==" => Value::Bool(lhs == rhs),
+            "!=" => Value::Bool(lhs != rhs),
+            "<" => Value::Bool(lhs.partial_cmp(&rhs).ok_or_else(mismatch)?.is_lt()),
+            ">" => Value::Bool(lhs.partial_cmp(&rhs).ok_or_else(mismatch)?.is_gt()),
+            "<=" => Value::Bool(lhs.partial_cmp(&rhs).ok_or_else(mismatch)?.is_le()),
+            ">=" => Value::Bool(lhs.partial_cmp(&rhs).ok_or_else(mismatch)?.is_ge()),
+            "in" => Value::Bool(rhs.contains(&lhs).ok_or_else(mismatch)?),
+            "not in" => Value::Bool(!rhs.contains(&lhs).ok_or_else(mismatch)?),
+            "~" => format!("{lhs}{rhs}").into(),
+            "+" => match (&lhs, &rhs) {
+                (Value::Str(a), Value::Str(b)) => format!("{a}{b}").into(),
+                (Value::List(a), Value::List(b)) => [&a[..], &b[..]].concat().into(),
+                _ => arith(&lhs, &rhs, i64::checked_add, |a, b| a + b).ok_or_else(mismatch)?,
+            },
+            "-" => arith(&lhs, &rhs, i64::checked_sub, |a, b| a - b).ok_or_else(mismatch)?,
+            "*" => match (&lhs, &rhs) {
+                (Value::Str(s), Value::Int(n)) | (Value::Int(n), Value::Str(s)) => {
+                    let n = (*n).max(0) as usize;
+                    match s.len().checked_mul(n) {
+                        Some(len) if len <= MAX_LEN => s.repeat(n).into(),
+                        _ => return Err(render("repeated string is too long")),
+                    }
+                }
+                _ => arith(&lhs, &rhs, i64::checked_mul, |a, b| a * b).ok_or_else(mismatch)?,
+            },
+            "/" => match (lhs.as_f64(), rhs.as_f64()) {
+                (Some(_), Some(0.)) => return Err(render("division by zero")),
+                (Some(a), Some(b)) => Value::Float(a / b),
+                _ => return Err(mismatch()),
+            },
+            "//" => match (&lhs, &rhs) {
+                (_, Value::Int(0)) => return Err(render("division by zero")),
+                (Value::Int(a), Value::Int(b)) => Value::Int(a.div_euclid(*b)),
+                _ => arith(&lhs, &rhs, |_, _| None, |a, b| (a / b).floor()).ok_or_else(mismatch)?,
+            },
+            "%" => match (&lhs, &rhs) {
+                (_, Value::Int(0)) => return Err(render("division by zero")),
+                (Value::Int(a), Value::Int(b)) => Value::Int(a.rem_euclid(*b)),
+                _ => arith(&lhs, &rhs, |_, _| None, |a, b| a.rem_euclid(b)).ok_or_else(mismatch)?,
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    fn call(&mut self, callee: &Expr, args: &Args) -> Result<Value, GGufTemplateError> {
+        // 方法调用
+        if let Expr::Attr(obj, name) = callee {
+            let obj = self.eval(obj)?;
+            if !matches!(obj, Value::Map(_) | Value::Namespace(_)) || obj.attr(name).is_undefined()
+            {
+                let (args, _) = self.eval_args(args)?;
+                return method(&obj, name, &args);
+            }
+        }
+
+        let (pos, kw) = self.eval_args(args)?;
+        if let Expr::Var(name) = callee {
+            match &**name {
+                "raise_exception" => {
+                    let msg = pos.first().map(Value::to_string).unwrap_or_default();
+                    return Err(GGufTemplateError::Raised(msg));
+                }
+                "namespace" => {
+                    let mut map = IndexMap::new();
+                    if let Some(Value::Map(m)) = pos.first() {
+                        map.extend(m.iter().map(|(k, v)| (k.clone(), v.clone())))
+                    }
+                    map.extend(kw);
+                    return Ok(Value::Namespace(Rc::new(RefCell::new(map))));
+                }
+                "dict" => return Ok(kw.into_iter().collect::<IndexMap<_, _>>().into()),
+                "range" => {
+                    let nums = pos
+                        .iter()
+                        .map(|v| match v {
+                            Value::Int(n) => Ok(*n),
+                            v => Err(render(format!("range of {}", v.type_name()))),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let (start, stop, step) = match *nums {
+                        [stop] => (0, stop, 1),
+                        [start, stop] => (start, stop, 1),
+                        [start, stop, step] if step != 0 => (start, stop, step),
+                        _ => return Err(render("invalid arguments of range")),
+                    };
+                    let mut ans = Vec::new();
+                    let mut i = start;
+                    while (step > 0 && i < stop) || (step < 0 && i > stop) {
+                        if ans.len() == MAX_LEN {
+                            return Err(render("range is too long"));
+                        }
+                        ans.push(Value::Int(i));
+                        let Some(next) = i.checked_add(step) else {
+                            break;
+                        };
+                        i = next
+                    }
+                    return Ok(ans.into());
+                }
+                _ => {}
+            }
+        }
+
+        match self.eval(callee)? {
+            Value::Macro(m) => self.call_macro(&m, pos, kw),
+            Value::Undefined => Err(render(format!("undefined function {callee:?}"))),
+            v => Err(render(format!("{} is not callable", v.type_name()))),
+        }
+    }
+
+    fn call_macro(
+        &mut self,
+        m: &Macro,
+        pos: Vec<Value>,
+        mut kw: Kwargs,
+    ) -> Result<Value, GGufTemplateError> {
+        let mut scope = HashMap::new();
+        let mut pos = pos.into_iter();
+        for (param, default) in &m.params {
+            let value = match pos.next() {
+                Some(v) => v,
+                None => match kw.iter().position(|(k, _)| k == param) {
+                    Some(i) => kw.swap_remove(i).1,
+                    None => match default {
+                        Some(e) => self.eval(e)?,
+                        None => Value::Undefined,
+                    },
+                },
+            };
+            scope.insert(param.clone(), value);
+        }
+        self.scopes.push(scope);
+        let mut out = String::new();
+        let ans = self.render_nodes(&m.body, &mut out);
+        self.scopes.pop();
+        ans?;
+        Ok(out.into())
+    }
+
+    fn filter(&mut self, obj: Value, name: &str, args: &Args) -> Result<Value, GGufTemplateError> {
+        let (pos, kw) = self.eval_args(args)?;
+        let arg = |i: usize, name: &str| {
+            kw.iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+                .or_else(|| pos.get(i).cloned())
+        };
+        let items = || {
+            obj.iter()
+                .ok_or_else(|| render(format!("{} is not iterable", obj.type_name())))
+        };
+        Ok(match name {
+            "safe" | "string" => match obj {
+                Value::Str(_) => obj,
+                _ => obj.to_string().into(),
+            },
+            "trim" => obj.to_string().trim().into(),
+            "upper" => obj.to_string().to_uppercase().into(),
+            "lower" => obj.to_string().to_lowercase().into(),
+            "title" => title(&obj.to_string()).into(),
+            "capitalize" => capitalize(&obj.to_string()).into(),
+            "length" | "count" => match obj.len() {
+                Some(n) => Value::Int(n as _),
+                None => return Err(render(format!("{} has no length", obj.type_name()))),
+            },
+            "tojson" => {
+                let indent = match arg(0, "indent") {
+                    Some(Value::Int(n)) => Some(n.max(0) as _),
+                    _ => None,
+                };
+                obj.to_json(indent).into()
+            }
+            "int" => match &obj {
+                Value::Int(_) => obj,
+                Value::Float(f) => Value::Int(*f as _),
+                Value::Bool(b) => Value::Int(*b as _),
+                Value::Str(s) => Value::Int(s.trim().parse().unwrap_or(0)),
+                _ => Value::Int(0),
+            },
+            "float" => match &obj {
+                Value::Str(s) => Value::Float(s.trim().parse().unwrap_or(0.)),
+                v => Value::Float(v.as_f64().unwrap_or(0.)),
+            },
+            "first" => items()?.into_iter().next().unwrap_or(Value::Undefined),
+            "last" => items()?.into_iter().next_back().unwrap_or(Value::Undefined),
+            "list" => items()?.into(),
+            "reverse" => match &obj {
+                Value::Str(s) => s.chars().rev().collect::<String>().into(),
+                _ => items()?.into_iter().rev().collect::<Vec<_>>().into(),
+            },
+            "join" => {
+                let sep = arg(0, "d").map(|v| v.to_string()).unwrap_or_default();
+                let attr = arg(1, "attribute");
+                items()?
+                    .iter()
+                    .map(|v| match &attr {
+                        Some(attr) => v.attr(&attr.to_string()).to_string(),
+                        None => v.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(&sep)
+                    .into()
+            }
+            "default" | "d" => {
+                let default = arg(0, "default_value").unwrap_or_else(|| "".into());
+                let boolean = arg(1, "boolean").is_some_and(|v| v.is_true());
+                if obj.is_undefined() || (boolean && !obj.is_true()) {
+                    default
+                } else {
+                    obj
+                }
+            }
+            "items" => match &obj {
+                Value::Map(m) => m
+                    .iter()
+                    .map(|(k, v)| Value::from(vec![Value::from(&**k), v.clone()]))
+                    .collect::<Vec<_>>()
+                    .into(),
+                Value::Undefined => Vec::new().into(),
+                v => return Err(render(format!("{} has no items", v.type_name()))),
+            },
+            "replace" => {
+                let (Some(from), Some(to)) = (pos.first(), pos.get(1)) else {
+                    return Err(render("replace requires 2 arguments"));
+                };
+                obj.to_string()
+                    .replace(&from.to_string(), &to.to_string())
+                    .into()
+            }
+            "indent" => {
+                let width = match arg(0, "width") {
+                    Some(Value::Int(n)) => n.max(0) as usize,
+                    _ => 4,
+                };
+                let first = arg(1, "first").is_some_and(|v| v.is_true());
+                let pad = " ".repeat(width);
+                let mut ans = String::new();
+                for (i, line) in obj.to_string().split('\n').enumerate() {
+                    if i > 0 {
+                        ans.push('\n')
+                    }
+                    if (i > 0 || first) && !line.is_empty() {
+                        ans.push_str(&pad)
+                    }
+                    ans.push_str(line)
+                }
+                ans.into()
+            }
+            "selectattr" | "rejectattr" => {
+                let Some(attr) = pos.first() else {
+                    return Err(render(format!("{name} requires an attribute")));
+                };
+                let attr = attr.to_string();
+                let (test_name, test_args) = match pos.get(1) {
+                    Some(t) => (t.to_string(), &pos[2..]),
+                    None => ("truthy".into(), &[][..]),
+                };
+                let mut ans = Vec::new();
+                for item in items()? {
+                    let value = item.attr(&attr);
+                    let pass = if test_name == "truthy" {
+                        value.is_true()
+                    } else {
+                        test(&value, &test_name, test_args)?
+                    };
+                    if pass == (name == "selectattr") {
+                        ans.push(item)
+                    }
+                }
+                ans.into()
+            }
+            "map" => {
+                let items = items()?;
+                match arg(usize::MAX, "attribute") {
+                    Some(attr) => items
+                        .iter()
+                        .map(|v| v.attr(&attr.to_string()))
+                        .collect::<Vec<_>>()
+                        .into(),
+                    None => {
+                        let Some(filter) = pos.first() else {
+                            return Err(render("map requires a filter or an attribute"));
+                        };
+                        let filter = filter.to_string();
+                        items
+                            .into_iter()
+                            .map(|v| self.filter(v, &filter, &Args::default()))
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into()
+                    }
+                }
+            }
+            "unique" => {
+                let mut ans = Vec::<Value>::new();
+                for item in items()? {
+                    if !ans.contains(&item) {
+                        ans.push(item)
+                    }
+                }
+                ans.into()
+            }
+            "abs" => match obj {
+                Value::Int(n) => Value::Int(n.abs()),
+                Value::Float(f) => Value::Float(f.abs()),
+                v => return Err(render(format!("abs of {}", v.type_name()))),
+            },
+            _ => return Err(render(format!("unknown filter `{name}`"))),
+        })
+    }
+}
+
+fn test(obj: &Value, name: &str, args: &[Value]) -> Result<bool, GGufTemplateError> {
+    Ok(match name {
+        "defined" => !obj.is_undefined(),
+        "undefined" => obj.is_undefined(),
+        "none" => matches!(obj, Value::None),
+        "boolean" => matches!(obj, Value::Bool(_)),
+        "true" => matches!(obj, Value::Bool(true)),
+        "false" => matches!(obj, Value::Bool(false)),
+        "string" => matches!(obj, Value::Str(_)),
+        "number" => matches!(obj, Value::Int(_) | Value::Float(_)),
+        "integer" => matches!(obj, Value::Int(_)),
+        "float" => matches!(obj, Value::Float(_)),
+        "mapping" => matches!(obj, Value::Map(_) | Value::Namespace(_)),
+        "sequence" => matches!(obj, Value::List(_) | Value::Str(_)),
+        "iterable" => matches!(obj, Value::List(_) | Value::Str(_) | Value::Map(_)),
+        "callable" => matches!(obj, Value::Macro(_)),
+        "odd" | "even" => match obj {
+            Value::Int(n) => (n % 2 != 0) == (name == "odd"),
+            v => return Err(render(format!("{name} of {}", v.type_name()))),
+        },
+        "eq" | "equalto" | "==" | "sameas" => args.first().is_some_and(|v| v == obj),
+        "ne" | "!=" => args.first().is_none_or(|v| v != obj),
+        "in" => args.first().and_then(|v| v.contains(obj)).unwrap_or(false),
+        _ => return Err(render(format!("unknown test `{name}`"))),
+    })
+}
+
+fn method(obj: &Value, name: &str, args: &[Value]) -> Result<Value, GGufTemplateError> {
+    let str_arg = |i: usize| args.get(i).map(Value::to_string);
+    let unknown = || render(format!("{} has no method `{name}`", obj.type_name()));
+    Ok(match obj {
+        Value::Str(s) => {
+            let chars = str_arg(0).map(|c| c.chars().collect::<Vec<_>>());
+            let pat = |c: char| match &chars {
+                Some(chars) => chars.contains(&c),
+                None => c.is_whitespace(),
+            };
+            match name {
+                "strip" => s.trim_matches(pat).into(),
+                "lstrip" => s.trim_start_matches(pat).into(),
+                "rstrip" => s.trim_end_matches(pat).into(),
+                "upper" => s.to_uppercase().into(),
+                "lower" => s.to_lowercase().into(),
+                "title" => title(s).into(),
+                "capitalize" => capitalize(s).into(),
+                "startswith" => Value::Bool(args.iter().any(|p| s.starts_with(&*p.to_string()))),
+                "endswith" => Value::Bool(args.iter().any(|p| s.ends_with(&*p.to_string()))),
+                "replace" => match (str_arg(0), str_arg(1)) {
+                    (Some(from), Some(to)) => s.replace(&from, &to).into(),
+                    _ => return Err(render("replace requires 2 arguments")),
+                },
+                "find" => match str_arg(0) {
+                    Some(sub) => {
+                        Value::Int(s.find(&sub).map_or(-1, |i| s[..i].chars().count() as i64))
+                    }
+                    None => return Err(render("find requires an argument")),
+                },
+                "split" => {
+                    let max = match args.get(1) {
+                        Some(Value::Int(n)) if *n >= 0 => *n as usize + 1,
+                        _ => usize::MAX,
+                    };
+                    let parts = match args.first() {
+                        Some(Value::Str(sep)) => s.splitn(max, &**sep).map(Value::from).collect(),
+                        _ => s.split_whitespace().map(Value::from).collect::<Vec<_>>(),
+                    };
+                    parts.into()
+                }
+                _ => return Err(unknown()),
+            }
+        }
+        Value::Map(m) => match name {
+            "items" => m
+                .iter()
+                .map(|(k, v)| Value::from(vec![Value::from(&**k), v.clone()]))
+                .collect::<Vec<_>>()
+                .into(),
+            "keys" => m
+                .keys()
+                .map(|k| Value::from(&**k))
+                .collect::<Vec<_>>()
+                .into(),
+            "values" => m.values().cloned().collect::<Vec<_>>().into(),
+            "get" => match args.first() {
+                Some(k) => m
+                    .get(&*k.to_string())
+                    .cloned()
+                    .unwrap_or_else(|| args.get(1).cloned().unwrap_or(Value::None)),
+                None => return Err(render("get requires a key")),
+            },
+            _ => return Err(unknown()),
+        },
+        _ => return Err(unknown()),
+    })
+}
+
+fn arith(
+    lhs: &Value,
+    rhs: &Value,
+    int: impl Fn(i64, i64) -> Option<i64>,
+    float: impl Fn(f64, f64) -> f64,
+) -> Option<Value> {
+    if let (Value::Int(a), Value::Int(b)) = (lhs, rhs)
+        && let Some(n) = int(*a, *b)
+    {
+        return Some(Value::Int(n));
+    }
+    match (lhs, rhs) {
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+            Some(Value::Float(float(lhs.as_f64()?, rhs.as_f64()?)))
+        }
+        _ => None,
+    }
+}
+
+fn slice(obj: &Value, [start, stop, step]: [Option<i64>; 3]) -> Result<Value, GGufTemplateError> {
+    let items = match obj {
+        Value::List(l) => l.to_vec(),
+        Value::Str(s) => s.chars().map(|c| Value::from(c.to_string())).collect(),
+        v => return Err(render(format!("cannot slice {}", v.type_name()))),
+    };
+    let len = items.len() as i64;
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err(render("slice step cannot be zero"));
+    }
+    // 按 python 的规则计算边界
+    let clamp = |i: i64, lo: i64, hi: i64| {
+        let i = if i < 0 { i + len } else { i };
+        i.clamp(lo, hi)
+    };
+    let mut ans = Vec::new();
+    if step > 0 {
+        let mut i = start.map_or(0, |i| clamp(i, 0, len));
+        let end = stop.map_or(len, |i| clamp(i, 0, len));
+        while i < end {
+            ans.push(items[i as usize].clone());
+            let Some(next) = i.checked_add(step) else {
+                break;
+            };
+            i = next
+        }
+    } else {
+        let mut i = start.map_or(len - 1, |i| clamp(i, -1, len - 1));
+        let end = stop.map_or(-1, |i| clamp(i, -1, len - 1));
+        while i > end {
+            ans.push(items[i as usize].clone());
+            let Some(next) = i.checked_add(step) else {
+                break;
+            };
+            i = next
+        }
+    }
+    Ok(match obj {
+        Value::Str(_) => ans.iter().map(Value::to_string).collect::<String>().into(),
+        _ => ans.into(),
+    })
+}
+
+fn title(s: &str) -> String {
+    let mut ans = String::with_capacity(s.len());
+    let mut prev_alpha = false;
+    for c in s.chars() {
+        if prev_alpha {
+            ans.extend(c.to_lowercase())
+        } else {
+            ans.extend(c.to_uppercase())
+        }
+        prev_alpha = c.is_alphabetic()
+    }
+    ans
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+#[inline]
+fn render(msg: impl Into<String>) -> GGufTemplateError {
+    GGufTemplateError::Render(msg.into())
+}
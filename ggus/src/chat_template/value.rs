@@ -0,0 +1,333 @@
+use super::parser::Macro;
+use indexmap::IndexMap;
+use std::{cell::RefCell, cmp::Ordering, fmt, rc::Rc};
+
+/// A value during rendering, with python semantics where jinja has them.
+#[derive(Clone, Debug)]
+pub(super) enum Value {
+    Undefined,
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    List(Rc<Vec<Value>>),
+    Map(Rc<IndexMap<String, Value>>),
+    Namespace(Rc<RefCell<IndexMap<String, Value>>>),
+    Macro(Rc<Macro>),
+}
+
+impl From<&str> for Value {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<String> for Value {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<bool> for Value {
+    #[inline]
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    #[inline]
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(Rc::new(value))
+    }
+}
+
+impl From<IndexMap<String, Value>> for Value {
+    #[inline]
+    fn from(value: IndexMap<String, Value>) -> Self {
+        Self::Map(Rc::new(value))
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Undefined => "undefined",
+            Self::None => "none",
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Str(_) => "string",
+            Self::List(_) => "list",
+            Self::Map(_) => "dict",
+            Self::Namespace(_) => "namespace",
+            Self::Macro(_) => "macro",
+        }
+    }
+
+    pub fn is_true(&self) -> bool {
+        match self {
+            Self::Undefined | Self::None => false,
+            Self::Bool(b) => *b,
+            Self::Int(n) => *n != 0,
+            Self::Float(f) => *f != 0.,
+            Self::Str(s) => !s.is_empty(),
+            Self::List(l) => !l.is_empty(),
+            Self::Map(m) => !m.is_empty(),
+            Self::Namespace(_) | Self::Macro(_) => true,
+        }
+    }
+
+    #[inline]
+    pub const fn is_undefined(&self) -> bool {
+        matches!(self, Self::Undefined)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Bool(b) => Some(*b as u8 as _),
+            Self::Int(n) => Some(*n as _),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Gets an attribute or a key, [`Value::Undefined`] if it does not exist.
+    pub fn attr(&self, name: &str) -> Value {
+        match self {
+            Self::Map(m) => m.get(name).cloned().unwrap_or(Self::Undefined),
+            Self::Namespace(ns) => ns.borrow().get(name).cloned().unwrap_or(Self::Undefined),
+            _ => Self::Undefined,
+        }
+    }
+
+    /// Iterates lists, the keys of maps or the characters of strings.
+    pub fn iter(&self) -> Option<Vec<Value>> {
+        match self {
+            Self::List(l) => Some(l.to_vec()),
+            Self::Map(m) => Some(m.keys().map(|k| Self::from(&**k)).collect()),
+            Self::Str(s) => Some(s.chars().map(|c| Self::from(c.to_string())).collect()),
+            Self::Undefined | Self::None => Some(vec![]),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Self::List(l) => Some(l.len()),
+            Self::Map(m) => Some(m.len()),
+            Self::Str(s) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, item: &Value) -> Option<bool> {
+        match (self, item) {
+            (Self::Str(s), Self::Str(sub)) => Some(s.contains(&**sub)),
+            (Self::List(l), _) => Some(l.iter().any(|v| v == item)),
+            (Self::Map(m), Self::Str(k)) => Some(m.contains_key(&**k)),
+            (Self::Map(_), _) => Some(false),
+            (Self::Undefined | Self::None, _) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Formats the value as python `repr` does, used inside containers.
+    fn fmt_repr(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => {
+                let quote = if s.contains('\'') && !s.contains('"') {
+                    '"'
+                } else {
+                    '\''
+                };
+                write!(f, "{quote}")?;
+                for c in s.chars() {
+                    match c {
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c == quote => write!(f, "\\{c}")?,
+                        c => write!(f, "{c}")?,
+                    }
+                }
+                write!(f, "{quote}")
+            }
+            _ => fmt::Display::fmt(self, f),
+        }
+    }
+
+    /// Serializes the value as JSON, non-ascii characters are kept as they are.
+    pub fn to_json(&self, indent: Option<usize>) -> String {
+        let mut buf = String::new();
+        self.write_json(&mut buf, indent, 0);
+        buf
+    }
+
+    fn write_json(&self, buf: &mut String, indent: Option<usize>, depth: usize) {
+        fn newline(buf: &mut String, indent: Option<usize>, depth: usize) {
+            if let Some(n) = indent {
+                buf.push('\n');
+                buf.extend(std::iter::repeat_n(' ', n * depth))
+            }
+        }
+        let sep = if indent.is_some() { "," } else { ", " };
+        match self {
+            Self::Undefined | Self::None | Self::Macro(_) => buf.push_str("null"),
+            Self::Bool(b) => buf.push_str(if *b { "true" } else { "false" }),
+            Self::Int(n) => buf.push_str(&n.to_string()),
+            Self::Float(_) => buf.push_str(&self.to_string()),
+            Self::Str(s) => write_json_str(buf, s),
+            Self::List(l) => {
+                buf.push('[');
+                for (i, v) in l.iter().enumerate() {
+                    if i > 0 {
+                        buf.push_str(sep)
+                    }
+                    newline(buf, indent, depth + 1);
+                    v.write_json(buf, indent, depth + 1)
+                }
+                if !l.is_empty() {
+                    newline(buf, indent, depth)
+                }
+                buf.push(']')
+            }
+            Self::Map(_) | Self::Namespace(_) => {
+                let map = match self {
+                    Self::Map(m) => (**m).clone(),
+                    Self::Namespace(ns) => ns.borrow().clone(),
+                    _ => unreachable!(),
+                };
+                buf.push('{');
+                for (i, (k, v)) in map.iter().enumerate() {
+                    if i > 0 {
+                        buf.push_str(sep)
+                    }
+                    newline(buf, indent, depth + 1);
+                    write_json_str(buf, k);
+                    buf.push_str(": ");
+                    v.write_json(buf, indent, depth + 1)
+                }
+                if !map.is_empty() {
+                    newline(buf, indent, depth)
+                }
+                buf.push('}')
+            }
+        }
+    }
+}
+
+fn write_json_str(buf: &mut String, s: &str) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            '\u{8}' => buf.push_str("\\b"),
+            '\u{c}' => buf.push_str("\\f"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"')
+}
+
+impl fmt::Display for Value {
+    /// Formats the value as python `str` does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined => Ok(()),
+            Self::None => f.write_str("None"),
+            Self::Bool(true) => f.write_str("True"),
+            Self::Bool(false) => f.write_str("False"),
+            Self::Int(n) => write!(f, "{n}"),
+            Self::Float(x) if x.is_finite() && x.fract() == 0. && x.abs() < 1e16 => {
+                write!(f, "{x:.1}")
+            }
+            Self::Float(x) => write!(f, "{x}"),
+            Self::Str(s) => f.write_str(s),
+            Self::List(l) => {
+                f.write_str("[")?;
+                for (i, v) in l.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?
+                    }
+                    v.fmt_repr(f)?
+                }
+                f.write_str("]")
+            }
+            Self::Map(m) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?
+                    }
+                    Self::from(&**k).fmt_repr(f)?;
+                    f.write_str(": ")?;
+                    v.fmt_repr(f)?
+                }
+                f.write_str("}")
+            }
+            Self::Namespace(_) => f.write_str("<Namespace>"),
+            Self::Macro(m) => write!(f, "<Macro {}>", m.name),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Undefined, Self::Undefined) | (Self::None, Self::None) => true,
+            (Self::Str(a), Self::Str(b)) => a == b,
+            (Self::List(a), Self::List(b)) => a == b,
+            (Self::Map(a), Self::Map(b)) => a == b,
+            (Self::Namespace(a), Self::Namespace(b)) => Rc::ptr_eq(a, b),
+            (Self::Macro(a), Self::Macro(b)) => Rc::ptr_eq(a, b),
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Str(a), Self::Str(b)) => a.partial_cmp(b),
+            (Self::List(a), Self::List(b)) => a.partial_cmp(b),
+            (a, b) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        }
+    }
+}
+
+#[test]
+fn test_value() {
+    let list = Value::from(vec![
+        Value::from("it's"),
+        Value::Int(1),
+        Value::Float(2.),
+        Value::None,
+    ]);
+    assert_eq!(list.to_string(), r#"["it's", 1, 2.0, None]"#);
+    assert_eq!(list.to_json(None), r#"["it's", 1, 2.0, null]"#);
+
+    let mut map = IndexMap::new();
+    map.insert("b".to_string(), Value::from("\"中\"\n"));
+    map.insert("a".to_string(), Value::Bool(true));
+    let map = Value::from(map);
+    assert_eq!(map.to_json(None), r#"{"b": "\"中\"\n", "a": true}"#);
+    assert_eq!(
+        map.to_json(Some(2)),
+        "{\n  \"b\": \"\\\"中\\\"\\n\",\n  \"a\": true\n}"
+    );
+    assert_eq!(Value::Int(1), Value::Float(1.));
+    assert!(Value::Int(1) < Value::Float(1.5));
+}
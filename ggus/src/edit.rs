@@ -0,0 +1,264 @@
+use crate::{
+    GGUS_PADDING, GGufError, GGufFileHeader, GGufIndex, GGufMetaBuf, GGufMetaDataValueType,
+    GGufMetaMap, GGufMetaMapExt, GGufStreamReader, GGufWriter,
+};
+use std::{
+    borrow::Cow,
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Edits the metadata of a gguf file in place.
+///
+/// Only the head of the file is rewritten, tensor data is moved only when the new head
+/// does not fit before the tensor data section. A shorter head is filled up to the old
+/// data offset with a [`GGUS_PADDING`] string value, which is dropped again on the next commit.
+pub struct GGufMetaEditor {
+    file: File,
+    index: GGufIndex,
+}
+
+/// Size of the buffer used to move tensor data.
+const MOVE_CHUNK: usize = 8 << 20;
+
+impl GGufMetaMap for GGufMetaEditor {
+    #[inline]
+    fn get(&self, key: &str) -> Option<(GGufMetaDataValueType, &[u8])> {
+        self.index.get(key)
+    }
+}
+
+impl GGufMetaEditor {
+    /// Opens a gguf file for reading and writing.
+    pub fn open(path: impl AsRef<Path>) -> std::result::Result<Self, GGufError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(GGufError::Io)?;
+        Self::new(file)
+    }
+
+    /// Parses the head of a gguf file, the file must start with the gguf header.
+    pub fn new(mut file: File) -> std::result::Result<Self, GGufError> {
+        file.rewind().map_err(GGufError::Io)?;
+        let reader = GGufStreamReader::new(file)?;
+        let index = reader.index().clone();
+        let file = reader.into_inner();
+        Ok(Self { file, index })
+    }
+
+    #[inline]
+    pub fn index(&self) -> &GGufIndex {
+        &self.index
+    }
+
+    #[inline]
+    pub fn insert_meta(
+        &mut self,
+        key: impl Into<String>,
+        ty: GGufMetaDataValueType,
+        value: impl Into<Cow<'static, [u8]>>,
+    ) -> Option<GGufMetaBuf<'static>> {
+        let value = GGufMetaBuf {
+            ty,
+            value: value.into(),
+        };
+        self.index.meta_kvs.insert(key.into(), value)
+    }
+
+    #[inline]
+    pub fn remove_meta(&mut self, key: &str) -> Option<GGufMetaBuf<'static>> {
+        self.index.meta_kvs.shift_remove(key)
+    }
+
+    /// Writes the edited head back to the file, returns the new offset of the tensor data section.
+    ///
+    /// Changing `general.alignment` is rejected, since tensors would have to be realigned.
+    pub fn commit(self) -> Result<u64> {
+        let Self {
+            mut file,
+            mut index,
+        } = self;
+        index.meta_kvs.shift_remove(GGUS_PADDING);
+
+        let alignment = index
+            .general_alignment()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{e:?}")))?;
+        if alignment != index.alignment {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "alignment changed from {} to {alignment}, tensor data must be rewritten",
+                    index.alignment,
+                ),
+            ));
+        }
+
+        // 生成新的文件头
+
+        let old = index.data_offset;
+        let len = file.seek(SeekFrom::End(0))? - old;
+        let mut head = write_head(&index, None)?;
+        if !index.tensors.is_empty() {
+            // 文件头变短时用填充键占满空隙，保持张量数据不动
+            let padding = (old as usize)
+                .checked_sub(head.len() + GGUS_PADDING.len() + PADDING_OVERHEAD)
+                .filter(|_| head.len().next_multiple_of(alignment) < old as usize);
+            if let Some(padding) = padding {
+                head = write_head(&index, Some(padding))?
+            }
+            let padded = head.len().next_multiple_of(alignment);
+            head.resize(padded, 0)
+        }
+
+        // 按需移动张量数据
+
+        let new = head.len() as u64;
+        if new > old {
+            move_data(&mut file, old, new, len, true)?;
+        } else if new < old {
+            move_data(&mut file, old, new, len, false)?;
+            file.set_len(new + len)?
+        }
+
+        file.rewind()?;
+        file.write_all(&head)?;
+        file.flush()?;
+        Ok(new)
+    }
+}
+
+/// Bytes of a string meta kv besides its key and value: key length, type and value length.
+const PADDING_OVERHEAD: usize = size_of::<u64>() + size_of::<u32>() + size_of::<u64>();
+
+/// Writes the head without the final padding, with a padding kv of `padding` bytes if given.
+fn write_head(index: &GGufIndex, padding: Option<usize>) -> Result<Vec<u8>> {
+    let header = GGufFileHeader::new(
+        index.header.version,
+        index.tensors.len() as _,
+        (index.meta_kvs.len() + padding.is_some() as usize) as _,
+    );
+    let mut writer = GGufWriter::new(Vec::new());
+    writer.write_header(header)?;
+    for (k, v) in &index.meta_kvs {
+        writer.write_str(k)?;
+        writer.write(&[v.ty])?;
+        writer.write(&v.value)?;
+    }
+    if let Some(padding) = padding {
+        writer.write_str(GGUS_PADDING)?;
+        writer.write(&[GGufMetaDataValueType::String])?;
+        writer.write_str(" ".repeat(padding))?;
+    }
+    for (name, tensor) in &index.tensors {
+        writer.write_tensor_info(name, &tensor.shape, tensor.ty, tensor.offset)?;
+    }
+    writer.into_inner()
+}
+
+/// Moves `len` bytes from `src` to `dst`, copying from the end if `backward`.
+fn move_data(file: &mut File, src: u64, dst: u64, len: u64, backward: bool) -> Result<()> {
+    let mut buf = vec![0; MOVE_CHUNK.min(len as _)];
+    let mut done = 0;
+    while done < len {
+        let n = (len - done).min(buf.len() as _);
+        let pos = if backward { len - done - n } else { done };
+        let buf = &mut buf[..n as usize];
+        file.seek(SeekFrom::Start(src + pos))?;
+        file.read_exact(buf)?;
+        file.seek(SeekFrom::Start(dst + pos))?;
+        file.write_all(buf)?;
+        done += n
+    }
+    Ok(())
+}
+
+#[test]
+fn test_editor() {
+    use crate::{GGmlType, GGuf, GGufModel};
+
+    let path = std::env::temp_dir().join(format!("ggus-edit-{}.gguf", std::process::id()));
+    let mut model = GGufModel::default();
+    model.insert_meta("general.name", GGufMetaDataValueType::String, meta_str("a"));
+    model.insert_tensor("x", GGmlType::F32, [4], vec![1; 16]);
+    model.insert_tensor("y", GGmlType::I8, [3], vec![2; 3]);
+    model.write(File::create(&path).unwrap(), true).unwrap();
+    let data_offset = GGufMetaEditor::open(&path).unwrap().index().data_offset;
+
+    let check = |name: &str, data_offset_expected: Option<u64>| {
+        let buf = std::fs::read(&path).unwrap();
+        let gguf = GGuf::new(&buf).unwrap();
+        assert_eq!(gguf.general_name().unwrap(), name);
+        assert_eq!(gguf.tensor_data("x"), Some(&[1; 16][..]));
+        assert_eq!(gguf.tensor_data("y"), Some(&[2; 3][..]));
+        if let Some(offset) = data_offset_expected {
+            assert_eq!(
+                gguf.data.as_ptr() as usize - buf.as_ptr() as usize,
+                offset as usize
+            )
+        }
+    };
+
+    // 仍能放进原有的空间
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    editor.insert_meta("general.name", GGufMetaDataValueType::String, meta_str("b"));
+    assert_eq!(editor.commit().unwrap(), data_offset);
+    check("b", Some(data_offset));
+
+    // 需要后移张量数据
+    let long = "c".repeat(MOVE_CHUNK + 100);
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    editor.insert_meta(
+        "general.name",
+        GGufMetaDataValueType::String,
+        meta_str(&long),
+    );
+    let offset = editor.commit().unwrap();
+    assert!(offset > data_offset);
+    check(&long, Some(offset));
+
+    // 文件头变短时填充空隙，不移动张量数据
+    let file_len = std::fs::metadata(&path).unwrap().len();
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    editor.insert_meta("general.name", GGufMetaDataValueType::String, meta_str("d"));
+    assert_eq!(editor.commit().unwrap(), offset);
+    check("d", Some(offset));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), file_len);
+    let buf = std::fs::read(&path).unwrap();
+    let mut merged = GGufModel::default();
+    merged.merge(GGuf::new(&buf).unwrap()).unwrap();
+    assert!(merged.get(GGUS_PADDING).is_none());
+
+    // 填充键被重新计算，仍不移动张量数据
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    assert!(editor.get(GGUS_PADDING).is_some());
+    editor.insert_meta(
+        "general.name",
+        GGufMetaDataValueType::String,
+        meta_str(&long),
+    );
+    assert_eq!(editor.commit().unwrap(), offset);
+    check(&long, Some(offset));
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    assert!(editor.get(GGUS_PADDING).is_none());
+    editor.insert_meta("general.name", GGufMetaDataValueType::String, meta_str("d"));
+    assert_eq!(editor.commit().unwrap(), offset);
+    check("d", Some(offset));
+
+    let mut editor = GGufMetaEditor::open(&path).unwrap();
+    editor.insert_meta(
+        "general.alignment",
+        GGufMetaDataValueType::U32,
+        128u32.to_le_bytes().to_vec(),
+    );
+    assert_eq!(editor.commit().unwrap_err().kind(), ErrorKind::InvalidInput);
+    check("d", None);
+
+    std::fs::remove_file(&path).unwrap();
+
+    fn meta_str(s: &str) -> Cow<'static, [u8]> {
+        GGufMetaBuf::string(s).value
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
 };
 use indexmap::IndexMap;
 use log::{info, warn};
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, io};
 
 pub struct GGuf<'a> {
     pub header: GGufFileHeader,
@@ -21,8 +21,11 @@ pub enum GGufError {
     EndianNotSupport,
     VersionNotSupport,
     AlignmentTypeMismatch(GGufMetaDataValueType),
+    /// `general.alignment` is not a non-zero power of two.
+    InvalidAlignment(u64),
     DuplicateMetaKey(String),
     DuplicateTensorName(String),
+    Io(io::Error),
 }
 
 impl fmt::Display for GGufError {
@@ -33,8 +36,10 @@ impl fmt::Display for GGufError {
             Self::EndianNotSupport => f.write_str("endian not support"),
             Self::VersionNotSupport => f.write_str("version not support"),
             Self::AlignmentTypeMismatch(ty) => write!(f, "alignment type mismatch: {ty:?}"),
+            Self::InvalidAlignment(n) => write!(f, "invalid alignment: {n}"),
             Self::DuplicateMetaKey(key) => write!(f, "duplicate meta key: {key}"),
             Self::DuplicateTensorName(name) => write!(f, "duplicate tensor name: {name}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
         }
     }
 }
@@ -49,6 +54,118 @@ impl GGufMetaMap for GGuf<'_> {
 
 impl<'a> GGuf<'a> {
     pub fn new(data: &'a [u8]) -> Result<Self, GGufError> {
+        Self::with_head(data, GGufHead::new(data)?)
+    }
+
+    /// Parses a gguf file without validating strings and bools in meta values.
+    ///
+    /// Only keys, value types and array lengths are checked, so large arrays
+    /// such as vocabularies are skipped in place. Values are validated when read.
+    pub fn new_lazy(data: &'a [u8]) -> Result<Self, GGufError> {
+        Self::with_head(data, GGufHead::new_lazy(data)?)
+    }
+
+    fn with_head(data: &'a [u8], head: GGufHead<'a>) -> Result<Self, GGufError> {
+        let GGufHead {
+            header,
+            alignment,
+            meta_kvs,
+            tensors,
+            data_offset,
+        } = head;
+
+        // 张量可能共享数据，数据段长度取最远的结束位置
+        let mut data_len = 0;
+        for tensor in tensors.values() {
+            let info = tensor.to_info();
+            let end = info.offset() + info.nbytes();
+            if end > data_len {
+                data_len = end;
+            }
+        }
+
+        // 骨架文件可能连数据段前的填充都没有
+        let data = data.get(data_offset..).unwrap_or_default();
+        let data = if data.len() == data_len {
+            data
+        } else if data.is_empty() {
+            info!("no tensor data, treated as skeleton file");
+            data
+        } else if data.len() < data_len {
+            warn!(
+                "{} bytes of tensor data missing, file may be truncated",
+                data_len - data.len()
+            );
+            data
+        } else {
+            let padding = pad(data_len, alignment);
+            if data.len() == data_len + padding {
+                info!("unnecessary padding detected")
+            } else {
+                warn!(
+                    "extra {} bytes detected after tensor data",
+                    data.len() - data_len
+                )
+            }
+            &data[..data_len]
+        };
+
+        Ok(Self {
+            header,
+            alignment,
+            meta_kvs,
+            tensors,
+            data,
+        })
+    }
+
+    /// Returns the data of a tensor, or `None` if the tensor does not exist
+    /// or its data is not fully present in the file.
+    pub fn tensor_data(&self, name: &str) -> Option<&'a [u8]> {
+        let info = self.tensors.get(name)?.to_info();
+        self.data.get(info.offset()..)?.get(..info.nbytes())
+    }
+
+    /// Returns the number of bytes of a tensor missing from the file,
+    /// or `None` if the tensor does not exist.
+    pub fn missing_bytes(&self, name: &str) -> Option<usize> {
+        let info = self.tensors.get(name)?.to_info();
+        let end = info.offset() + info.nbytes();
+        Some(end - self.data.len().clamp(info.offset(), end))
+    }
+
+    /// Returns `true` if the data of any tensor is not fully present,
+    /// such as a file written without tensor data.
+    pub fn is_skeleton(&self) -> bool {
+        self.tensors.values().any(|t| {
+            let info = t.to_info();
+            info.offset() + info.nbytes() > self.data.len()
+        })
+    }
+}
+
+/// Everything in a gguf file before the tensor data.
+pub(crate) struct GGufHead<'a> {
+    pub header: GGufFileHeader,
+    pub alignment: usize,
+    pub meta_kvs: IndexMap<&'a str, GGufMetaKV<'a>>,
+    pub tensors: IndexMap<&'a str, GGufTensorMeta<'a>>,
+    /// Offset of the tensor data section from the start of the file.
+    pub data_offset: usize,
+}
+
+impl<'a> GGufHead<'a> {
+    #[inline]
+    pub fn new(data: &'a [u8]) -> Result<Self, GGufError> {
+        Self::parse(data, false)
+    }
+
+    #[inline]
+    pub fn new_lazy(data: &'a [u8]) -> Result<Self, GGufError> {
+        Self::parse(data, true)
+    }
+
+    fn parse(data: &'a [u8], lazy: bool) -> Result<Self, GGufError> {
         use GGufError::*;
 
         let mut reader = GGufReader::new(data);
@@ -67,57 +184,44 @@ impl<'a> GGuf<'a> {
         let mut alignment = DEFAULT_ALIGNMENT;
         let mut meta_kvs = IndexMap::with_capacity(header.metadata_kv_count as _);
         for _ in 0..header.metadata_kv_count {
-            let kv = reader.read_meta_kv().map_err(Reading)?;
+            let kv = if lazy {
+                reader.read_meta_kv_lazy()
+            } else {
+                reader.read_meta_kv()
+            }
+            .map_err(Reading)?;
             let k = kv.key();
             if k == GENERAL_ALIGNMENT {
                 type Ty = GGufMetaDataValueType;
-                alignment = match kv.ty() {
+                let n = match kv.ty() {
                     Ty::U32 => kv.value_reader().read::<u32>().map_err(Reading)? as _,
-                    Ty::U64 => kv.value_reader().read::<u64>().map_err(Reading)? as _,
+                    Ty::U64 => kv.value_reader().read::<u64>().map_err(Reading)?,
                     ty => return Err(AlignmentTypeMismatch(ty)),
-                }
+                };
+                alignment = usize::try_from(n)
+                    .ok()
+                    .filter(|n| n.is_power_of_two())
+                    .ok_or(InvalidAlignment(n))?
             }
             if meta_kvs.insert(k, kv).is_some() {
                 return Err(DuplicateMetaKey(k.into()));
             }
         }
 
-        let mut data_len = 0;
         let mut tensors = IndexMap::with_capacity(header.tensor_count as _);
         for _ in 0..header.tensor_count {
             let tensor = reader.read_tensor_meta().map_err(Reading)?;
             let name = tensor.name();
-            let info = tensor.to_info();
-            let end = info.offset() + info.nbytes();
-            if end > data_len {
-                data_len = end;
-            }
             if tensors.insert(name, tensor).is_some() {
                 return Err(DuplicateTensorName(name.into()));
             }
         }
 
         let cursor = data.len() - reader.remaining().len();
-        let padding = if tensors.is_empty() {
-            0
+        let data_offset = if tensors.is_empty() {
+            cursor
         } else {
-            pad(cursor, alignment)
-        };
-        reader.skip::<u8>(padding).map_err(Reading)?;
-        let data = reader.remaining();
-        let data = if data.len() == data_len {
-            data
-        } else {
-            let padding = pad(data_len, alignment);
-            if data.len() == data_len + padding {
-                info!("unnecessary padding detected")
-            } else {
-                warn!(
-                    "extra {} bytes detected after tensor data",
-                    data.len() - data_len
-                )
-            }
-            &data[..data_len]
+            cursor + pad(cursor, alignment)
         };
 
         Ok(Self {
@@ -125,7 +229,7 @@ impl<'a> GGuf<'a> {
             alignment,
             meta_kvs,
             tensors,
-            data,
+            data_offset,
         })
     }
 }
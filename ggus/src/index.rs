@@ -0,0 +1,86 @@
+use crate::{
+    GGmlType, GGufError, GGufFileHeader, GGufMetaBuf, GGufMetaDataValueType, GGufMetaMap,
+    file::GGufHead,
+};
+use indexmap::IndexMap;
+
+/// Owned header, metadata and tensor infos of a gguf file, without the tensor data.
+#[derive(Clone, Debug)]
+pub struct GGufIndex {
+    pub header: GGufFileHeader,
+    pub alignment: usize,
+    pub meta_kvs: IndexMap<String, GGufMetaBuf<'static>>,
+    pub tensors: IndexMap<String, GGufTensorEntry>,
+    /// Offset of the tensor data section from the start of the file.
+    pub data_offset: u64,
+}
+
+/// Owned info of a tensor, with the offset relative to the tensor data section.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GGufTensorEntry {
+    pub ty: GGmlType,
+    pub shape: Vec<u64>,
+    pub offset: u64,
+}
+
+impl GGufTensorEntry {
+    #[inline]
+    pub fn nbytes(&self) -> usize {
+        self.ty.size().elements_to_bytes(&self.shape)
+    }
+}
+
+impl GGufMetaMap for GGufIndex {
+    fn get(&self, key: &str) -> Option<(GGufMetaDataValueType, &[u8])> {
+        self.meta_kvs.get(key).map(|v| (v.ty, &*v.value))
+    }
+}
+
+impl GGufIndex {
+    /// Parses the head of a gguf file.
+    ///
+    /// `data` only needs to cover the file up to the tensor data section,
+    /// [`GGufError::Reading`] with [`crate::GGufReadError::Eos`] is returned if it is too short.
+    pub fn new(data: &[u8]) -> Result<Self, GGufError> {
+        GGufHead::new(data).map(Self::from)
+    }
+}
+
+impl From<GGufHead<'_>> for GGufIndex {
+    fn from(head: GGufHead) -> Self {
+        let GGufHead {
+            header,
+            alignment,
+            meta_kvs,
+            tensors,
+            data_offset,
+        } = head;
+        Self {
+            header,
+            alignment,
+            meta_kvs: meta_kvs
+                .into_iter()
+                .map(|(k, kv)| {
+                    let value = GGufMetaBuf {
+                        ty: kv.ty(),
+                        value: kv.value_bytes().to_vec().into(),
+                    };
+                    (k.into(), value)
+                })
+                .collect(),
+            tensors: tensors
+                .into_iter()
+                .map(|(name, tensor)| {
+                    let info = tensor.to_info();
+                    let entry = GGufTensorEntry {
+                        ty: info.ty(),
+                        shape: info.shape().to_vec(),
+                        offset: info.offset() as _,
+                    };
+                    (name.into(), entry)
+                })
+                .collect(),
+            data_offset: data_offset as _,
+        }
+    }
+}
@@ -3,26 +3,53 @@
 
 pub extern crate ggml_quants;
 
+mod builder;
+#[cfg(feature = "chat-template")]
+mod chat_template;
+mod edit;
 mod file;
 mod header;
+mod index;
 mod metadata;
+mod model;
 mod name;
 mod read;
+mod stream;
 mod tensor;
+mod tensor_name;
+#[cfg(feature = "tokenizer")]
+mod tokenizer;
+mod view;
 mod write;
 
+pub use builder::{GGufBuildError, GGufBuilder};
+#[cfg(feature = "chat-template")]
+pub use chat_template::{GGufChatMessage, GGufChatTemplate, GGufTemplateError};
+pub use edit::GGufMetaEditor;
 pub use file::{GGuf, GGufError};
 pub use header::GGufFileHeader;
+pub use index::{GGufIndex, GGufTensorEntry};
 pub use metadata::{
-    DEFAULT_ALIGNMENT, GENERAL_ALIGNMENT, GGmlTokenType, GGufFileType, GGufMetaDataValueType,
-    GGufMetaError, GGufMetaKV, GGufMetaMap, GGufMetaMapExt, GGufMetaValueArray,
+    DEFAULT_ALIGNMENT, GENERAL_ALIGNMENT, GGUF_META_KEYS, GGUS_PADDING, GGmlTokenType,
+    GGufFileType, GGufMetaDataValueType, GGufMetaError, GGufMetaKV, GGufMetaKey, GGufMetaMap,
+    GGufMetaMapExt, GGufMetaScalar, GGufMetaValue, GGufMetaValueArray, GGufTokenTypes,
+    LlmHyperParams,
 };
+pub use model::{GGufDataError, GGufMetaBuf, GGufModel, GGufTensorBuf, GGufTensorData};
 pub use name::{GGufExtNotMatch, GGufFileName};
 pub use read::{GGufReadError, GGufReader};
-pub use tensor::{GGmlType, GGmlTypeSize, GGufTensorInfo, GGufTensorMeta};
+pub use stream::GGufStreamReader;
+
+#[cfg(feature = "tokio")]
+pub use stream::GGufAsyncStreamReader;
+pub use tensor::{GGmlType, GGmlTypeSize, GGufTensorInfo, GGufTensorMeta, GGufTensorShape};
+pub use tensor_name::GGufTensorName;
+#[cfg(feature = "tokenizer")]
+pub use tokenizer::{GGufTokenizer, GGufTokenizerError};
+pub use view::{GGufTensorError, GGufTensorView};
 pub use write::{
     DataFuture, GGufFileSimulator, GGufFileWriter, GGufTensorSimulator, GGufTensorWriter,
-    GGufWriter,
+    GGufWriteObserver, GGufWriteProgress, GGufWriter,
 };
 
 #[inline(always)]
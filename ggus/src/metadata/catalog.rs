@@ -0,0 +1,306 @@
+use super::{GGufMetaDataValueType as Ty, GGufMetaError};
+use crate::GGufReader;
+
+/// The single table of standardized meta keys.
+///
+/// Each row is `[accessor]: scope "key" => kind`:
+///
+/// - `accessor` names the generated method of [`super::GGufMetaMapExt`],
+///   rows without it have a handwritten accessor or none at all;
+/// - `scope` is `gguf` for full keys and `arch` for keys under `{arch}.`;
+/// - `kind` is the value type expected by llama.cpp.
+///
+/// The table is passed to the macro named `$m`.
+#[rustfmt::skip]
+macro_rules! meta_keys {
+    ($m:ident) => {
+        $m! {
+            general_architecture                : gguf "general.architecture"                   => str    ,
+            general_quantization_version        : gguf "general.quantization_version"           => usize  ,
+                                                : gguf "general.alignment"                      => u32    ,
+            general_name                        : gguf "general.name"                           => str    ,
+            general_author                      : gguf "general.author"                         => str    ,
+            general_version                     : gguf "general.version"                        => str    ,
+            general_organization                : gguf "general.organization"                   => str    ,
+            general_basename                    : gguf "general.basename"                       => str    ,
+            general_finetune                    : gguf "general.finetune"                       => str    ,
+            general_description                 : gguf "general.description"                    => str    ,
+            general_quantized_by                : gguf "general.quantized_by"                   => str    ,
+            general_size_label                  : gguf "general.size_label"                     => str    ,
+            general_license                     : gguf "general.license"                        => str    ,
+            general_license_name                : gguf "general.license.name"                   => str    ,
+            general_license_link                : gguf "general.license.link"                   => str    ,
+            general_url                         : gguf "general.url"                            => str    ,
+            general_doi                         : gguf "general.doi"                            => str    ,
+            general_uuid                        : gguf "general.uuid"                           => str    ,
+            general_repo_url                    : gguf "general.repo_url"                       => str    ,
+            general_tags                        : gguf "general.tags"                           => str_arr,
+            general_languages                   : gguf "general.languages"                      => str_arr,
+            general_datasets                    : gguf "general.datasets"                       => str_arr,
+                                                : gguf "general.file_type"                      => u32    ,
+            general_source_url                  : gguf "general.source.url"                     => str    ,
+            general_source_doi                  : gguf "general.source.doi"                     => str    ,
+            general_source_uuid                 : gguf "general.source.uuid"                    => str    ,
+            general_source_repo_url             : gguf "general.source.repo_url"                => str    ,
+            general_base_model_count            : gguf "general.base_model.count"               => usize  ,
+                                                : gguf "general.base_model.{id}.name"           => str    ,
+                                                : gguf "general.base_model.{id}.author"         => str    ,
+                                                : gguf "general.base_model.{id}.version"        => str    ,
+                                                : gguf "general.base_model.{id}.organization"   => str    ,
+                                                : gguf "general.base_model.{id}.url"            => str    ,
+                                                : gguf "general.base_model.{id}.doi"            => str    ,
+                                                : gguf "general.base_model.{id}.uuid"           => str    ,
+                                                : gguf "general.base_model.{id}.repo_url"       => str    ,
+
+            llm_vocab_size                      : arch "vocab_size"                             => usize  ,
+            llm_context_length                  : arch "context_length"                         => usize  ,
+            llm_embedding_length                : arch "embedding_length"                       => usize  ,
+            llm_block_count                     : arch "block_count"                            => usize  ,
+            llm_leading_dense_block_count       : arch "leading_dense_block_count"              => usize  ,
+            llm_feed_forward_length             : arch "feed_forward_length"                    => usize  ,
+            llm_expert_feed_forward_length      : arch "expert_feed_forward_length"             => usize  ,
+            llm_expert_shared_feed_forward_length: arch "expert_shared_feed_forward_length"     => usize  ,
+            llm_use_parallel_residual           : arch "use_parallel_residual"                  => bool   ,
+            llm_tensor_data_layout              : arch "tensor_data_layout"                     => str    ,
+            llm_expert_count                    : arch "expert_count"                           => usize  ,
+            llm_expert_used_count               : arch "expert_used_count"                      => usize  ,
+            llm_expert_shared_count             : arch "expert_shared_count"                    => usize  ,
+            llm_expert_weights_scale            : arch "expert_weights_scale"                   => f32    ,
+            llm_expert_weights_norm             : arch "expert_weights_norm"                    => bool   ,
+            llm_expert_gating_func              : arch "expert_gating_func"                     => usize  ,
+            llm_pooling_type                    : arch "pooling_type"                           => usize  ,
+            llm_logit_scale                     : arch "logit_scale"                            => f32    ,
+            llm_decoder_start_token_id          : arch "decoder_start_token_id"                 => u32    ,
+            llm_attn_logit_softcapping          : arch "attn_logit_softcapping"                 => f32    ,
+            llm_final_logit_softcapping         : arch "final_logit_softcapping"                => f32    ,
+            llm_swin_norm                       : arch "swin_norm"                              => bool   ,
+            llm_rescale_every_n_layers          : arch "rescale_every_n_layers"                 => usize  ,
+            llm_residual_scale                  : arch "residual_scale"                         => f32    ,
+            llm_embedding_scale                 : arch "embedding_scale"                        => f32    ,
+            llm_attention_head_count            : arch "attention.head_count"                   => usize  ,
+                                                : arch "attention.head_count_kv"                => usize  ,
+            llm_attention_max_alibi_bias        : arch "attention.max_alibi_bias"               => f32    ,
+            llm_attention_clamp_kqv             : arch "attention.clamp_kqv"                    => f32    ,
+                                                : arch "attention.key_length"                   => usize  ,
+                                                : arch "attention.value_length"                 => usize  ,
+            llm_attention_layer_norm_epsilon    : arch "attention.layer_norm_epsilon"           => f32    ,
+            llm_attention_layer_norm_rms_epsilon: arch "attention.layer_norm_rms_epsilon"       => f32    ,
+            llm_attention_group_norm_epsilon    : arch "attention.group_norm_epsilon"           => f32    ,
+            llm_attention_group_norm_groups     : arch "attention.group_norm_groups"            => usize  ,
+            llm_attention_causal                : arch "attention.causal"                       => bool   ,
+            llm_attention_q_lora_rank           : arch "attention.q_lora_rank"                  => usize  ,
+            llm_attention_kv_lora_rank          : arch "attention.kv_lora_rank"                 => usize  ,
+            llm_attention_relative_buckets_count: arch "attention.relative_buckets_count"       => usize  ,
+            llm_attention_sliding_window        : arch "attention.sliding_window"               => usize  ,
+            llm_attention_scale                 : arch "attention.scale"                        => f32    ,
+            llm_rope_dimension_count            : arch "rope.dimension_count"                   => usize  ,
+            llm_rope_dimension_sections         : arch "rope.dimension_sections"                => i32_arr,
+            llm_rope_freq_base                  : arch "rope.freq_base"                         => f32    ,
+            llm_rope_scale_linear               : arch "rope.scale_linear"                      => f32    ,
+            llm_rope_scaling_type               : arch "rope.scaling.type"                      => str    ,
+            llm_rope_scaling_factor             : arch "rope.scaling.factor"                    => f32    ,
+            llm_rope_scaling_attn_factor        : arch "rope.scaling.attn_factor"               => f32    ,
+            llm_rope_scaling_original_context_length: arch "rope.scaling.original_context_length" => usize,
+            llm_rope_scaling_finetuned          : arch "rope.scaling.finetuned"                 => bool   ,
+            llm_rope_scaling_yarn_log_multiplier: arch "rope.scaling.yarn_log_multiplier"       => f32    ,
+            llm_rope_scaling_yarn_ext_factor    : arch "rope.scaling.yarn_ext_factor"           => f32    ,
+            llm_rope_scaling_yarn_attn_factor   : arch "rope.scaling.yarn_attn_factor"          => f32    ,
+            llm_rope_scaling_yarn_beta_fast     : arch "rope.scaling.yarn_beta_fast"            => f32    ,
+            llm_rope_scaling_yarn_beta_slow     : arch "rope.scaling.yarn_beta_slow"            => f32    ,
+            llm_ssm_conv_kernel                 : arch "ssm.conv_kernel"                        => usize  ,
+            llm_ssm_inner_size                  : arch "ssm.inner_size"                         => usize  ,
+            llm_ssm_state_size                  : arch "ssm.state_size"                         => usize  ,
+            llm_ssm_time_step_rank              : arch "ssm.time_step_rank"                     => usize  ,
+            llm_ssm_dt_b_c_rms                  : arch "ssm.dt_b_c_rms"                         => bool   ,
+            llm_wkv_head_size                   : arch "wkv.head_size"                          => usize  ,
+
+            tokenizer_ggml_model                : gguf "tokenizer.ggml.model"                   => str    ,
+            tokenizer_ggml_pre                  : gguf "tokenizer.ggml.pre"                     => str    ,
+            tokenizer_ggml_tokens               : gguf "tokenizer.ggml.tokens"                  => str_arr,
+            tokenizer_ggml_token_type           : gguf "tokenizer.ggml.token_type"              => tok_arr,
+            tokenizer_ggml_token_type_count     : gguf "tokenizer.ggml.token_type_count"        => u32    ,
+            tokenizer_ggml_scores               : gguf "tokenizer.ggml.scores"                  => f32_arr,
+            tokenizer_ggml_merges               : gguf "tokenizer.ggml.merges"                  => str_arr,
+            tokenizer_ggml_added_tokens         : gguf "tokenizer.ggml.added_tokens"            => str_arr,
+            tokenizer_ggml_bos_token_id         : gguf "tokenizer.ggml.bos_token_id"            => u32    ,
+            tokenizer_ggml_eos_token_id         : gguf "tokenizer.ggml.eos_token_id"            => u32    ,
+            tokenizer_ggml_eot_token_id         : gguf "tokenizer.ggml.eot_token_id"            => u32    ,
+            tokenizer_ggml_eom_token_id         : gguf "tokenizer.ggml.eom_token_id"            => u32    ,
+            tokenizer_ggml_unknown_token_id     : gguf "tokenizer.ggml.unknown_token_id"        => u32    ,
+            tokenizer_ggml_separator_token_id   : gguf "tokenizer.ggml.seperator_token_id"      => u32    ,
+            tokenizer_ggml_padding_token_id     : gguf "tokenizer.ggml.padding_token_id"        => u32    ,
+            tokenizer_ggml_cls_token_id         : gguf "tokenizer.ggml.cls_token_id"            => u32    ,
+            tokenizer_ggml_mask_token_id        : gguf "tokenizer.ggml.mask_token_id"           => u32    ,
+            tokenizer_ggml_fim_pre_token_id     : gguf "tokenizer.ggml.fim_pre_token_id"        => u32    ,
+            tokenizer_ggml_fim_suf_token_id     : gguf "tokenizer.ggml.fim_suf_token_id"        => u32    ,
+            tokenizer_ggml_fim_mid_token_id     : gguf "tokenizer.ggml.fim_mid_token_id"        => u32    ,
+            tokenizer_ggml_fim_pad_token_id     : gguf "tokenizer.ggml.fim_pad_token_id"        => u32    ,
+            tokenizer_ggml_fim_rep_token_id     : gguf "tokenizer.ggml.fim_rep_token_id"        => u32    ,
+            tokenizer_ggml_fim_sep_token_id     : gguf "tokenizer.ggml.fim_sep_token_id"        => u32    ,
+            tokenizer_ggml_add_bos_token        : gguf "tokenizer.ggml.add_bos_token"           => bool   ,
+            tokenizer_ggml_add_eos_token        : gguf "tokenizer.ggml.add_eos_token"           => bool   ,
+            tokenizer_ggml_add_space_prefix     : gguf "tokenizer.ggml.add_space_prefix"        => bool   ,
+            tokenizer_ggml_remove_extra_whitespaces: gguf "tokenizer.ggml.remove_extra_whitespaces" => bool,
+            tokenizer_ggml_precompiled_charsmap : gguf "tokenizer.ggml.precompiled_charsmap"    => u8_arr ,
+            tokenizer_huggingface_json          : gguf "tokenizer.huggingface.json"             => str    ,
+            tokenizer_rwkv_world                : gguf "tokenizer.rwkv.world"                   => str    ,
+            tokenizer_chat_template             : gguf "tokenizer.chat_template"                => str    ,
+                                                : gguf "tokenizer.chat_template.{name}"         => str    ,
+
+                                                : gguf "split.no"                               => u16    ,
+                                                : gguf "split.count"                            => u16    ,
+                                                : gguf "split.tensors.count"                    => i32    ,
+
+                                                : gguf "adapter.type"                           => str    ,
+                                                : gguf "adapter.lora.alpha"                     => f32    ,
+        }
+    };
+}
+
+pub(crate) use meta_keys;
+
+/// A standardized meta key and the value type expected for it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GGufMetaKey {
+    /// The key, `{arch}`, `{id}` and `{name}` stand for one segment of the actual key.
+    pub key: &'static str,
+    pub ty: Ty,
+    /// Element type of arrays.
+    pub elem: Option<Ty>,
+}
+
+#[rustfmt::skip]
+macro_rules! kind {
+    (str    ) => { (Ty::String, None::<Ty>     ) };
+    (bool   ) => { (Ty::Bool  , None::<Ty>     ) };
+    (u16    ) => { (Ty::U16   , None::<Ty>     ) };
+    (u32    ) => { (Ty::U32   , None::<Ty>     ) };
+    (i32    ) => { (Ty::I32   , None::<Ty>     ) };
+    (usize  ) => { (Ty::U32   , None::<Ty>     ) };
+    (f32    ) => { (Ty::F32   , None::<Ty>     ) };
+    (str_arr) => { (Ty::Array , Some(Ty::String)) };
+    (i32_arr) => { (Ty::Array , Some(Ty::I32   )) };
+    (f32_arr) => { (Ty::Array , Some(Ty::F32   )) };
+    (u8_arr ) => { (Ty::Array , Some(Ty::U8    )) };
+    (tok_arr) => { (Ty::Array , Some(Ty::I32   )) };
+}
+
+macro_rules! scoped {
+    (gguf, $key:literal) => {
+        $key
+    };
+    (arch, $key:literal) => {
+        concat!("{arch}.", $key)
+    };
+}
+
+macro_rules! table {
+    ($( $($name:ident)? : $scope:ident $key:literal => $kind:ident ,)*) => {
+        /// All standardized meta keys.
+        pub const GGUF_META_KEYS: &[GGufMetaKey] = &[$(
+            GGufMetaKey {
+                key: scoped!($scope, $key),
+                ty: kind!($kind).0,
+                elem: kind!($kind).1,
+            },
+        )*];
+    };
+}
+
+meta_keys!(table);
+
+/// Segments of actual keys that cannot be an architecture name.
+const NOT_ARCH: &[&str] = &["general", "tokenizer", "split", "adapter"];
+
+impl GGufMetaKey {
+    /// Finds the catalog entry of an actual key, such as `llama.context_length`.
+    pub fn find(key: &str) -> Option<&'static Self> {
+        GGUF_META_KEYS.iter().find(|k| k.matches(key))
+    }
+
+    /// Returns `true` if `key` is an instance of this entry.
+    pub fn matches(&self, key: &str) -> bool {
+        let mut pattern = self.key.split('.');
+        let mut key = key.split('.');
+        loop {
+            match (pattern.next(), key.next()) {
+                (None, None) => break true,
+                (Some("{arch}"), Some(seg)) if !seg.is_empty() && !NOT_ARCH.contains(&seg) => {}
+                (Some("{id}"), Some(seg))
+                    if !seg.is_empty() && seg.bytes().all(|b| b.is_ascii_digit()) => {}
+                (Some("{name}"), Some(seg)) if !seg.is_empty() => {}
+                (Some(p), Some(seg)) if p == seg => {}
+                _ => break false,
+            }
+        }
+    }
+
+    /// Checks a value against the expected type.
+    ///
+    /// Integers of any width are accepted where an integer is expected, as readers convert them.
+    pub fn check(&self, ty: Ty, value: &[u8]) -> Result<(), GGufMetaError> {
+        if !compatible(self.ty, ty) {
+            return Err(GGufMetaError::TypeMismatch(ty));
+        }
+        if let Some(elem) = self.elem {
+            let (ty, _) = GGufReader::new(value)
+                .read_arr_header()
+                .map_err(GGufMetaError::Read)?;
+            if !compatible(elem, ty) {
+                return Err(GGufMetaError::ArrTypeMismatch(ty));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compatible(expected: Ty, actual: Ty) -> bool {
+    expected == actual || (expected.is_integer() && actual.is_integer())
+}
+
+#[test]
+fn test_catalog() {
+    use crate::GGufMetaValue as V;
+
+    let key = GGufMetaKey::find("llama.context_length").unwrap();
+    assert_eq!(key.key, "{arch}.context_length");
+    assert_eq!(key.ty, Ty::U32);
+    assert!(
+        key.check(Ty::U64, &V::U64(4096).to_bytes().unwrap())
+            .is_ok()
+    );
+    assert!(matches!(
+        key.check(Ty::F32, &V::F32(4096.).to_bytes().unwrap()),
+        Err(GGufMetaError::TypeMismatch(Ty::F32))
+    ));
+
+    assert!(GGufMetaKey::find("general.context_length").is_none());
+    assert!(GGufMetaKey::find("llama.unknown").is_none());
+    assert_eq!(
+        GGufMetaKey::find("general.base_model.0.name").unwrap().ty,
+        Ty::String
+    );
+    assert!(GGufMetaKey::find("general.base_model.x.name").is_none());
+    assert_eq!(
+        GGufMetaKey::find("qwen2.rope.scaling.yarn_beta_fast")
+            .unwrap()
+            .ty,
+        Ty::F32
+    );
+
+    let key = GGufMetaKey::find("tokenizer.ggml.tokens").unwrap();
+    assert_eq!(key.elem, Some(Ty::String));
+    let tokens = V::Array(Ty::String, vec!["a".into()]);
+    assert!(key.check(Ty::Array, &tokens.to_bytes().unwrap()).is_ok());
+    let ids = V::Array(Ty::U32, vec![1u32.into()]);
+    assert!(matches!(
+        key.check(Ty::Array, &ids.to_bytes().unwrap()),
+        Err(GGufMetaError::ArrTypeMismatch(Ty::U32))
+    ));
+
+    let mut keys = GGUF_META_KEYS.iter().map(|k| k.key).collect::<Vec<_>>();
+    let len = keys.len();
+    keys.sort_unstable();
+    keys.dedup();
+    assert_eq!(keys.len(), len)
+}
@@ -1,19 +1,111 @@
-use super::{DEFAULT_ALIGNMENT, GGufFileType, GGufMetaDataValueType as Ty, GGufMetaValueArray};
+use super::catalog::meta_keys;
+use super::{
+    DEFAULT_ALIGNMENT, GENERAL_ALIGNMENT, GGmlTokenType, GGufFileType, GGufMetaDataValueType as Ty,
+    GGufMetaValueArray, GGufTokenTypes, alignment_value, token_type::parse_byte_token,
+};
 use crate::{GGufReadError, GGufReader};
 
 pub trait GGufMetaMap {
     fn get(&self, key: &str) -> Option<(Ty, &[u8])>;
 }
 
+/// Fixed-size value types that can be read directly from metadata.
+pub trait GGufMetaScalar: Copy + 'static {
+    const TYPE: Ty;
+}
+
+macro_rules! scalar {
+    ($($ty:ty => $variant:ident),+) => {
+        $(
+            impl GGufMetaScalar for $ty {
+                const TYPE: Ty = Ty::$variant;
+            }
+        )+
+    };
+}
+
+scalar! {
+    u8  => U8 ,
+    i8  => I8 ,
+    u16 => U16,
+    i16 => I16,
+    u32 => U32,
+    i32 => I32,
+    f32 => F32,
+    u64 => U64,
+    i64 => I64,
+    f64 => F64
+}
+
 #[derive(Debug)]
 pub enum GGufMetaError {
     NotExist,
+    /// `general.architecture` is missing, so architecture-specific keys cannot be resolved.
+    NoArchitecture,
     TypeMismatch(Ty),
     ArrTypeMismatch(Ty),
     OutOfRange,
     Read(GGufReadError),
 }
 
+macro_rules! accessors {
+    ($( $($name:ident)? : $scope:ident $key:literal => $kind:ident ,)*) => {
+        $($(
+            #[inline]
+            fn $name(&self) -> Result<accessor_ty!($kind), GGufMetaError> {
+                get!(self, $kind, scoped_key!(self, $scope, $key))
+            }
+        )?)*
+    };
+}
+
+#[rustfmt::skip]
+macro_rules! accessor_ty {
+    (str    ) => { &str                            };
+    (bool   ) => { bool                            };
+    (u16    ) => { u16                             };
+    (u32    ) => { u32                             };
+    (i32    ) => { i32                             };
+    (usize  ) => { usize                           };
+    (f32    ) => { f32                             };
+    (str_arr) => { GGufMetaValueArray<'_, str>     };
+    (i32_arr) => { GGufMetaValueArray<'_, i32>     };
+    (f32_arr) => { GGufMetaValueArray<'_, f32>     };
+    (u8_arr ) => { GGufMetaValueArray<'_, u8>      };
+    (tok_arr) => { GGufTokenTypes<'_>              };
+}
+
+#[rustfmt::skip]
+macro_rules! get {
+    ($self:ident, str    , $key:expr) => { $self.get_str($key)     };
+    ($self:ident, bool   , $key:expr) => { $self.get_bool($key)    };
+    ($self:ident, usize  , $key:expr) => { $self.get_usize($key)   };
+    ($self:ident, str_arr, $key:expr) => { $self.get_str_arr($key) };
+    ($self:ident, i32_arr, $key:expr) => { $self.get_arr($key)     };
+    ($self:ident, f32_arr, $key:expr) => { $self.get_arr($key)     };
+    ($self:ident, u8_arr , $key:expr) => { $self.get_arr($key)     };
+    ($self:ident, tok_arr, $key:expr) => { $self.get_token_types($key) };
+    ($self:ident, $kind:ident, $key:expr) => { $self.get_val($key) };
+}
+
+macro_rules! scoped_key {
+    ($self:ident, gguf, $key:literal) => {
+        $key
+    };
+    ($self:ident, arch, $key:literal) => {
+        &llm_key($self, $key)?
+    };
+}
+
+/// Formats an architecture-specific key, such as `llama.context_length`.
+fn llm_key<M: GGufMetaMapExt + ?Sized>(meta: &M, key: &str) -> Result<String, GGufMetaError> {
+    match meta.general_architecture() {
+        Ok(llm) => Ok(format!("{llm}.{key}")),
+        Err(GGufMetaError::NotExist) => Err(GGufMetaError::NoArchitecture),
+        Err(e) => Err(e),
+    }
+}
+
 pub trait GGufMetaMapExt: GGufMetaMap {
     fn get_str(&self, key: &str) -> Result<&str, GGufMetaError> {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
@@ -23,7 +115,16 @@ pub trait GGufMetaMapExt: GGufMetaMap {
         }
     }
 
+    #[inline]
     fn get_usize(&self, key: &str) -> Result<usize, GGufMetaError> {
+        self.get_integer(key)
+    }
+
+    /// Reads any integer (or bool) value, converting it to `T` if it fits.
+    fn get_integer<T>(&self, key: &str) -> Result<T, GGufMetaError>
+    where
+        T: TryFrom<u64> + TryFrom<i64>,
+    {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
 
         macro_rules! read {
@@ -41,36 +142,56 @@ pub trait GGufMetaMapExt: GGufMetaMap {
 
         #[rustfmt::skip]
         let ans = match ty {
-            Ty::U8   =>          read!(u8 ).into(),
-            Ty::U16  =>          read!(u16).into(),
-            Ty::U32  => convert!(read!(u32)      ),
-            Ty::U64  => convert!(read!(u64)      ),
-            Ty::I8   => convert!(read!(i8 )      ),
-            Ty::I16  => convert!(read!(i16)      ),
-            Ty::I32  => convert!(read!(i32)      ),
-            Ty::I64  => convert!(read!(i64)      ),
-            Ty::Bool => if read!(bool) { 1 } else { 0 },
+            Ty::U8   => convert!(read!(u8 ) as u64),
+            Ty::U16  => convert!(read!(u16) as u64),
+            Ty::U32  => convert!(read!(u32) as u64),
+            Ty::U64  => convert!(read!(u64)       ),
+            Ty::I8   => convert!(read!(i8 ) as i64),
+            Ty::I16  => convert!(read!(i16) as i64),
+            Ty::I32  => convert!(read!(i32) as i64),
+            Ty::I64  => convert!(read!(i64)       ),
+            Ty::Bool => convert!(GGufReader::new(val).read_bool().map_err(GGufMetaError::Read)? as u64),
             _        => return Err(GGufMetaError::TypeMismatch(ty)),
         };
 
         Ok(ans)
     }
 
-    fn get_f32(&self, key: &str) -> Result<f32, GGufMetaError> {
+    /// Reads a value stored exactly as `T`.
+    fn get_val<T: GGufMetaScalar>(&self, key: &str) -> Result<T, GGufMetaError> {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
-        if ty == Ty::F32 {
+        if ty == T::TYPE {
             GGufReader::new(val).read().map_err(GGufMetaError::Read)
         } else {
             Err(GGufMetaError::TypeMismatch(ty))
         }
     }
 
+    #[inline]
+    fn get_f32(&self, key: &str) -> Result<f32, GGufMetaError> {
+        self.get_val(key)
+    }
+
+    #[inline]
     fn get_u32(&self, key: &str) -> Result<u32, GGufMetaError> {
-        let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
-        if ty == Ty::U32 {
-            GGufReader::new(val).read().map_err(GGufMetaError::Read)
-        } else {
-            Err(GGufMetaError::TypeMismatch(ty))
+        self.get_val(key)
+    }
+
+    #[inline]
+    fn get_u64(&self, key: &str) -> Result<u64, GGufMetaError> {
+        self.get_integer(key)
+    }
+
+    #[inline]
+    fn get_i64(&self, key: &str) -> Result<i64, GGufMetaError> {
+        self.get_integer(key)
+    }
+
+    /// Reads a f64 or f32 value as f64.
+    fn get_f64(&self, key: &str) -> Result<f64, GGufMetaError> {
+        match self.get_val::<f64>(key) {
+            Err(GGufMetaError::TypeMismatch(Ty::F32)) => self.get_val::<f32>(key).map(f64::from),
+            ans => ans,
         }
     }
 
@@ -85,7 +206,7 @@ pub trait GGufMetaMapExt: GGufMetaMap {
         }
     }
 
-    fn get_str_arr(&self, key: &str) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
+    fn get_str_arr(&self, key: &str) -> Result<GGufMetaValueArray<'_, str>, GGufMetaError> {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
         let mut reader = GGufReader::new(val);
         let (ty, len) = match ty {
@@ -99,180 +220,83 @@ pub trait GGufMetaMapExt: GGufMetaMap {
         }
     }
 
-    fn get_i32_arr(&self, key: &str) -> Result<GGufMetaValueArray<i32>, GGufMetaError> {
+    fn get_arr<T: GGufMetaScalar>(
+        &self,
+        key: &str,
+    ) -> Result<GGufMetaValueArray<'_, T>, GGufMetaError> {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
         let mut reader = GGufReader::new(val);
         let (ty, len) = match ty {
             Ty::Array => reader.read_arr_header().map_err(GGufMetaError::Read)?,
             ty => return Err(GGufMetaError::TypeMismatch(ty)),
         };
-        if ty == Ty::I32 {
+        if ty == T::TYPE {
             Ok(GGufMetaValueArray::new(reader, len))
         } else {
             Err(GGufMetaError::ArrTypeMismatch(ty))
         }
     }
 
-    fn get_f32_arr(&self, key: &str) -> Result<GGufMetaValueArray<f32>, GGufMetaError> {
+    #[inline]
+    fn get_i32_arr(&self, key: &str) -> Result<GGufMetaValueArray<'_, i32>, GGufMetaError> {
+        self.get_arr(key)
+    }
+
+    #[inline]
+    fn get_f32_arr(&self, key: &str) -> Result<GGufMetaValueArray<'_, f32>, GGufMetaError> {
+        self.get_arr(key)
+    }
+
+    /// Reads an array of token types, stored as any integer type.
+    fn get_token_types(&self, key: &str) -> Result<GGufTokenTypes<'_>, GGufMetaError> {
         let (ty, val) = self.get(key).ok_or(GGufMetaError::NotExist)?;
         let mut reader = GGufReader::new(val);
         let (ty, len) = match ty {
             Ty::Array => reader.read_arr_header().map_err(GGufMetaError::Read)?,
             ty => return Err(GGufMetaError::TypeMismatch(ty)),
         };
-        if ty == Ty::F32 {
-            Ok(GGufMetaValueArray::new(reader, len))
-        } else {
-            Err(GGufMetaError::ArrTypeMismatch(ty))
-        }
+        GGufTokenTypes::new(reader, ty, len)
     }
 
-    #[inline]
-    fn general_architecture(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.architecture")
+    /// Ids of the control and user-defined tokens.
+    fn tokenizer_ggml_special_tokens(&self) -> Result<Vec<u32>, GGufMetaError> {
+        let mut ans = Vec::new();
+        for (i, ty) in self.tokenizer_ggml_token_type()?.enumerate() {
+            if matches!(ty?, GGmlTokenType::Control | GGmlTokenType::User) {
+                ans.push(i as u32)
+            }
+        }
+        Ok(ans)
     }
 
-    #[inline]
-    fn general_quantization_version(&self) -> Result<usize, GGufMetaError> {
-        self.get_usize("general.quantization_version")
+    /// Ids of the byte-fallback tokens `<0x00>`..`<0xFF>`, indexed by byte.
+    ///
+    /// Tokens are recognized by their text as llama.cpp does.
+    fn tokenizer_ggml_byte_tokens(&self) -> Result<[Option<u32>; 256], GGufMetaError> {
+        let mut ans = [None; 256];
+        for (i, token) in self.tokenizer_ggml_tokens()?.enumerate() {
+            if let Some(b) = parse_byte_token(token.map_err(GGufMetaError::Read)?) {
+                ans[b as usize].get_or_insert(i as u32);
+            }
+        }
+        Ok(ans)
     }
 
-    #[inline]
+    /// Reads `general.alignment`, an u32 or u64 that must be a non-zero power of two.
     fn general_alignment(&self) -> Result<usize, GGufMetaError> {
-        match self.get_usize("general.alignment") {
-            Ok(n) => Ok(n),
-            Err(GGufMetaError::NotExist) => Ok(DEFAULT_ALIGNMENT),
-            Err(e) => Err(e),
+        match self.get(GENERAL_ALIGNMENT) {
+            Some((ty, val)) => alignment_value(ty, val),
+            None => Ok(DEFAULT_ALIGNMENT),
         }
     }
 
-    #[inline]
-    fn general_name(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.name")
-    }
-
-    #[inline]
-    fn general_author(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.author")
-    }
-
-    #[inline]
-    fn general_version(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.version")
-    }
-
-    #[inline]
-    fn general_organization(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.organization")
-    }
-
-    #[inline]
-    fn general_basename(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.basename")
-    }
-
-    #[inline]
-    fn general_finetune(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.finetune")
-    }
-
-    #[inline]
-    fn general_description(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.description")
-    }
-
-    #[inline]
-    fn general_quantized_by(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.quantized_by")
-    }
-
-    #[inline]
-    fn general_size_label(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.size_label")
-    }
-
-    #[inline]
-    fn general_license(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.license")
-    }
-
-    #[inline]
-    fn general_license_name(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.license.name")
-    }
-
-    #[inline]
-    fn general_license_link(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.license.link")
-    }
-
-    #[inline]
-    fn general_url(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.url")
-    }
-
-    #[inline]
-    fn general_doi(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.doi")
-    }
-
-    #[inline]
-    fn general_uuid(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.uuid")
-    }
-
-    #[inline]
-    fn general_repo_url(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.repo_url")
-    }
-
-    #[inline]
-    fn general_tags(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("general.tags")
-    }
-
-    #[inline]
-    fn general_languages(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("general.languages")
-    }
-
-    #[inline]
-    fn general_datasets(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("general.datasets")
-    }
-
     #[inline]
     fn general_filetype(&self) -> Result<GGufFileType, GGufMetaError> {
-        (self.get_usize("general.filetype")? as u32)
+        (self.get_usize("general.file_type")? as u32)
             .try_into()
             .map_err(|_| GGufMetaError::OutOfRange)
     }
 
-    #[inline]
-    fn general_source_url(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.source.url")
-    }
-
-    #[inline]
-    fn general_source_doi(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.source.doi")
-    }
-
-    #[inline]
-    fn general_source_uuid(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.source.uuid")
-    }
-
-    #[inline]
-    fn general_source_repo_url(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("general.source.repo_url")
-    }
-
-    #[inline]
-    fn general_base_model_count(&self) -> Result<usize, GGufMetaError> {
-        self.get_usize("general.base_model.count")
-    }
-
     #[inline]
     fn general_base_model_name(&self, id: usize) -> Result<&str, GGufMetaError> {
         self.get_str(&format!("general.base_model.{id}.name"))
@@ -313,103 +337,23 @@ pub trait GGufMetaMapExt: GGufMetaMap {
         self.get_str(&format!("general.base_model.{id}.repo_url"))
     }
 
-    #[inline]
-    fn llm_context_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.context_length"))
-    }
-
-    #[inline]
-    fn llm_embedding_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.embedding_length"))
-    }
-
-    #[inline]
-    fn llm_block_count(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.block_count"))
-    }
-
-    #[inline]
-    fn llm_feed_forward_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.feed_forward_length"))
-    }
-
-    #[inline]
-    fn llm_use_parallel_residual(&self) -> Result<bool, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_bool(&format!("{llm}.use_parallel_residual"))
-    }
-
-    #[inline]
-    fn llm_tensor_data_layout(&self) -> Result<&str, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_str(&format!("{llm}.tensor_data_layout"))
-    }
-
-    #[inline]
-    fn llm_expert_count(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.expert_count"))
-    }
-
-    #[inline]
-    fn llm_expert_used_count(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.expert_used_count"))
-    }
-
-    #[inline]
-    fn llm_attention_head_count(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.attention.head_count"))
-    }
-
     #[inline]
     fn llm_attention_head_count_kv(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        match self.get_usize(&format!("{llm}.attention.head_count_kv")) {
+        match self.get_usize(&llm_key(self, "attention.head_count_kv")?) {
             Ok(n) => Ok(n),
             Err(GGufMetaError::NotExist) => self.llm_attention_head_count(),
             Err(e) => Err(e),
         }
     }
 
-    #[inline]
-    fn llm_attention_max_alibi_bias(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.attention.max_alibi_bias"))
-    }
-
-    #[inline]
-    fn llm_attention_clamp_kqv(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.attention.clamp_kqv"))
-    }
-
-    #[inline]
-    fn llm_attention_layer_norm_epsilon(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.attention.layer_norm_epsilon"))
-    }
-
-    #[inline]
-    fn llm_attention_layer_norm_rms_epsilon(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.attention.layer_norm_rms_epsilon"))
-    }
-
     #[inline]
     fn llm_attention_key_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        match self.get_usize(&format!("{llm}.attention.key_length")) {
+        match self.get_usize(&llm_key(self, "attention.key_length")?) {
             Ok(n) => Ok(n),
             Err(GGufMetaError::NotExist) => {
                 let n_embed = self.llm_embedding_length()?;
                 let n_head = self.llm_attention_head_count()?;
-                Ok(n_embed / n_head)
+                n_embed.checked_div(n_head).ok_or(GGufMetaError::OutOfRange)
             }
             Err(e) => Err(e),
         }
@@ -417,148 +361,61 @@ pub trait GGufMetaMapExt: GGufMetaMap {
 
     #[inline]
     fn llm_attention_value_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        match self.get_usize(&format!("{llm}.attention.value_length")) {
+        match self.get_usize(&llm_key(self, "attention.value_length")?) {
             Ok(n) => Ok(n),
             Err(GGufMetaError::NotExist) => {
                 let n_embed = self.llm_embedding_length()?;
                 let n_head = self.llm_attention_head_count()?;
-                Ok(n_embed / n_head)
+                n_embed.checked_div(n_head).ok_or(GGufMetaError::OutOfRange)
             }
             Err(e) => Err(e),
         }
     }
 
-    #[inline]
-    fn llm_rope_dimension_count(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.rope.dimension_count"))
-    }
-
-    #[inline]
-    fn llm_rope_freq_base(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.rope.freq_base"))
-    }
-
-    #[inline]
-    fn llm_rope_scaling_type(&self) -> Result<&str, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_str(&format!("{llm}.rope.scaling.type"))
-    }
-
-    #[inline]
-    fn llm_rope_scaling_factor(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.rope.scaling.type"))
-    }
-
-    #[inline]
-    fn llm_rope_scaling_original_context_length(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.rope.scaling.original_context_length"))
-    }
-
-    #[inline]
-    fn llm_rope_scaling_finetuned(&self) -> Result<bool, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_bool(&format!("{llm}.rope.scaling.finetuned"))
-    }
-
-    #[inline]
-    fn llm_rope_scale_linear(&self) -> Result<f32, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_f32(&format!("{llm}.rope.scale_linear"))
-    }
-
-    #[inline]
-    fn llm_ssm_conv_kernel(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.ssm.conv_kernel"))
-    }
-
-    #[inline]
-    fn llm_ssm_inner_size(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.ssm.inner_size"))
-    }
-
-    #[inline]
-    fn llm_ssm_state_size(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.ssm.state_size"))
-    }
-
-    #[inline]
-    fn llm_ssm_time_step_rank(&self) -> Result<usize, GGufMetaError> {
-        let llm = self.general_architecture().unwrap();
-        self.get_usize(&format!("{llm}.ssm.time_step_rank"))
-    }
-
-    #[inline]
-    fn tokenizer_ggml_model(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("tokenizer.ggml.model")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_tokens(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("tokenizer.ggml.tokens")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_scores(&self) -> Result<GGufMetaValueArray<f32>, GGufMetaError> {
-        self.get_f32_arr("tokenizer.ggml.scores")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_token_type(&self) -> Result<GGufMetaValueArray<i32>, GGufMetaError> {
-        self.get_i32_arr("tokenizer.ggml.token_type")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_merges(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("tokenizer.ggml.merges")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_added_tokens(&self) -> Result<GGufMetaValueArray<str>, GGufMetaError> {
-        self.get_str_arr("tokenizer.ggml.added_tokens")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_bos_token_id(&self) -> Result<u32, GGufMetaError> {
-        self.get_u32("tokenizer.ggml.bos_token_id")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_eos_token_id(&self) -> Result<u32, GGufMetaError> {
-        self.get_u32("tokenizer.ggml.eos_token_id")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_unknown_token_id(&self) -> Result<u32, GGufMetaError> {
-        self.get_u32("tokenizer.ggml.unknown_token_id")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_separator_token_id(&self) -> Result<u32, GGufMetaError> {
-        self.get_u32("tokenizer.ggml.separator_token_id")
-    }
-
-    #[inline]
-    fn tokenizer_ggml_padding_token_id(&self) -> Result<u32, GGufMetaError> {
-        self.get_u32("tokenizer.ggml.padding_token_id")
-    }
-
-    #[inline]
-    fn tokenizer_rwkv_world(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("tokenizer.rwkv.world")
-    }
-
-    #[inline]
-    fn tokenizer_chat_template(&self) -> Result<&str, GGufMetaError> {
-        self.get_str("tokenizer.chat_template")
-    }
+    meta_keys!(accessors);
 }
 
 impl<T: GGufMetaMap> GGufMetaMapExt for T {}
+
+#[test]
+fn test_getters() {
+    use crate::{GGufMetaValue as V, GGufModel};
+
+    let mut model = GGufModel::default();
+    let mut insert = |k: &'static str, v: V| {
+        model.insert_meta(k, v.ty(), v.to_bytes().unwrap());
+    };
+    insert("u8", V::U8(3));
+    insert("i8", V::I8(-3));
+    insert("u64", V::U64(u64::MAX));
+    insert("f32", V::F32(0.5));
+    insert("u8_arr", V::Array(Ty::U8, vec![1u8.into(), 2u8.into()]));
+    insert("u64_arr", V::Array(Ty::U64, vec![u64::MAX.into()]));
+
+    assert_eq!(model.get_u64("u8").unwrap(), 3);
+    assert_eq!(model.get_i64("i8").unwrap(), -3);
+    assert_eq!(model.get_u64("u64").unwrap(), u64::MAX);
+    assert_eq!(model.get_integer::<i128>("u64").unwrap(), u64::MAX as i128);
+    assert!(matches!(
+        model.get_i64("u64"),
+        Err(GGufMetaError::OutOfRange)
+    ));
+    assert!(matches!(
+        model.get_usize("i8"),
+        Err(GGufMetaError::OutOfRange)
+    ));
+    assert_eq!(model.get_f64("f32").unwrap(), 0.5);
+    assert!(matches!(
+        model.get_u32("u8"),
+        Err(GGufMetaError::TypeMismatch(Ty::U8))
+    ));
+
+    let arr = model.get_arr::<u8>("u8_arr").unwrap();
+    assert_eq!(arr.map(Result::unwrap).collect::<Vec<_>>(), [1, 2]);
+    let arr = model.get_arr::<u64>("u64_arr").unwrap();
+    assert_eq!(arr.map(Result::unwrap).collect::<Vec<_>>(), [u64::MAX]);
+    assert!(matches!(
+        model.get_arr::<u32>("u8_arr"),
+        Err(GGufMetaError::ArrTypeMismatch(Ty::U8))
+    ));
+}
@@ -0,0 +1,139 @@
+use super::{GGufMetaError, GGufMetaMapExt, optional};
+
+/// Hyperparameters of a language model, read from the architecture-specific meta kvs.
+///
+/// Optional keys fall back to the defaults used by llama.cpp where there is one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LlmHyperParams {
+    pub architecture: String,
+    pub context_length: usize,
+    pub embedding_length: usize,
+    pub block_count: usize,
+    pub feed_forward_length: Option<usize>,
+    pub head_count: usize,
+    pub head_count_kv: usize,
+    /// `None` if not given and there is no attention head to derive it from.
+    pub key_length: Option<usize>,
+    /// `None` if not given and there is no attention head to derive it from.
+    pub value_length: Option<usize>,
+    pub expert_count: usize,
+    pub expert_used_count: usize,
+    pub rope_dimension_count: usize,
+    pub rope_freq_base: f32,
+    pub rope_scaling_type: Option<String>,
+    pub rope_scaling_factor: Option<f32>,
+    pub rope_scaling_original_context_length: Option<usize>,
+    pub layer_norm_epsilon: Option<f32>,
+    pub layer_norm_rms_epsilon: Option<f32>,
+}
+
+impl LlmHyperParams {
+    /// Reads the hyperparameters from any meta map.
+    ///
+    /// Returns [`GGufMetaError::NoArchitecture`] if `general.architecture` is missing.
+    pub fn new<M: GGufMetaMapExt + ?Sized>(meta: &M) -> Result<Self, GGufMetaError> {
+        let architecture = match meta.general_architecture() {
+            Ok(arch) => arch.into(),
+            Err(GGufMetaError::NotExist) => return Err(GGufMetaError::NoArchitecture),
+            Err(e) => return Err(e),
+        };
+        let head_count = meta.llm_attention_head_count()?;
+        // 没有注意力头的模型（如 mamba）无法推导头维度
+        let head_dim = |res| match res {
+            Ok(n) => Ok(Some(n)),
+            Err(GGufMetaError::OutOfRange) if head_count == 0 => Ok(None),
+            Err(e) => Err(e),
+        };
+        let key_length = head_dim(meta.llm_attention_key_length())?;
+        Ok(Self {
+            architecture,
+            context_length: meta.llm_context_length()?,
+            embedding_length: meta.llm_embedding_length()?,
+            block_count: meta.llm_block_count()?,
+            feed_forward_length: optional(meta.llm_feed_forward_length())?,
+            head_count,
+            head_count_kv: meta.llm_attention_head_count_kv()?,
+            key_length,
+            value_length: head_dim(meta.llm_attention_value_length())?,
+            expert_count: optional(meta.llm_expert_count())?.unwrap_or(0),
+            expert_used_count: optional(meta.llm_expert_used_count())?.unwrap_or(0),
+            rope_dimension_count: optional(meta.llm_rope_dimension_count())?
+                .or(key_length)
+                .unwrap_or(0),
+            rope_freq_base: optional(meta.llm_rope_freq_base())?.unwrap_or(10000.),
+            rope_scaling_type: optional(meta.llm_rope_scaling_type())?.map(Into::into),
+            rope_scaling_factor: optional(meta.llm_rope_scaling_factor())?,
+            rope_scaling_original_context_length: optional(
+                meta.llm_rope_scaling_original_context_length(),
+            )?,
+            layer_norm_epsilon: optional(meta.llm_attention_layer_norm_epsilon())?,
+            layer_norm_rms_epsilon: optional(meta.llm_attention_layer_norm_rms_epsilon())?,
+        })
+    }
+
+    /// Number of query heads sharing one kv head, `None` if there is no kv head.
+    #[inline]
+    pub const fn group_size(&self) -> Option<usize> {
+        self.head_count.checked_div(self.head_count_kv)
+    }
+
+    #[inline]
+    pub const fn is_moe(&self) -> bool {
+        self.expert_count > 0
+    }
+}
+
+#[test]
+fn test_hparams() {
+    use crate::{GGufMetaValue as V, GGufModel};
+
+    let mut model = GGufModel::default();
+    assert!(matches!(
+        LlmHyperParams::new(&model),
+        Err(GGufMetaError::NoArchitecture)
+    ));
+
+    let mut insert = |k: &'static str, v: V| {
+        model.insert_meta(k, v.ty(), v.to_bytes().unwrap());
+    };
+    insert("general.architecture", V::from("llama"));
+    insert("llama.context_length", V::U32(4096));
+    insert("llama.embedding_length", V::U32(2048));
+    insert("llama.block_count", V::U32(16));
+    insert("llama.feed_forward_length", V::U32(8192));
+    insert("llama.attention.head_count", V::U32(32));
+    insert("llama.attention.head_count_kv", V::U32(8));
+    insert("llama.attention.layer_norm_rms_epsilon", V::F32(1e-5));
+    insert("llama.rope.freq_base", V::F32(5e5));
+
+    let hparams = LlmHyperParams::new(&model).unwrap();
+    assert_eq!(hparams.architecture, "llama");
+    assert_eq!(hparams.context_length, 4096);
+    assert_eq!(hparams.feed_forward_length, Some(8192));
+    assert_eq!(hparams.head_count_kv, 8);
+    assert_eq!(hparams.group_size(), Some(4));
+    assert_eq!(hparams.key_length, Some(64));
+    assert_eq!(hparams.rope_dimension_count, 64);
+    assert_eq!(hparams.rope_freq_base, 5e5);
+    assert_eq!(hparams.layer_norm_rms_epsilon, Some(1e-5));
+    assert_eq!(hparams.layer_norm_epsilon, None);
+    assert!(!hparams.is_moe());
+
+    // 没有注意力头
+    let mut model = GGufModel::default();
+    let mut insert = |k: &'static str, v: V| {
+        model.insert_meta(k, v.ty(), v.to_bytes().unwrap());
+    };
+    insert("general.architecture", V::from("mamba"));
+    insert("mamba.context_length", V::U32(1 << 20));
+    insert("mamba.embedding_length", V::U32(768));
+    insert("mamba.block_count", V::U32(24));
+    insert("mamba.attention.head_count", V::U32(0));
+
+    let hparams = LlmHyperParams::new(&model).unwrap();
+    assert_eq!(hparams.head_count_kv, 0);
+    assert_eq!(hparams.group_size(), None);
+    assert_eq!(hparams.key_length, None);
+    assert_eq!(hparams.value_length, None);
+    assert_eq!(hparams.rope_dimension_count, 0);
+}
@@ -8,17 +8,34 @@ pub struct GGufMetaKV<'a>(&'a [u8]);
 
 impl<'a> GGufReader<'a> {
     pub fn read_meta_kv(&mut self) -> Result<GGufMetaKV<'a>, GGufReadError> {
+        self.read_meta_kv_(true)
+    }
+
+    /// Reads a meta kv, only checking the key and the structure of the value.
+    ///
+    /// Strings and bools in the value are not validated until they are read,
+    /// so this is much faster for large arrays such as vocabularies.
+    pub fn read_meta_kv_lazy(&mut self) -> Result<GGufMetaKV<'a>, GGufReadError> {
+        self.read_meta_kv_(false)
+    }
+
+    fn read_meta_kv_(&mut self, check: bool) -> Result<GGufMetaKV<'a>, GGufReadError> {
         let data = self.remaining();
 
         let _k = self.read_str()?;
         let ty = self.read()?;
-        self.read_meta_value(ty, 1)?;
+        self.skip_meta_value(ty, 1, check)?;
 
         let data = &data[..data.len() - self.remaining().len()];
         Ok(unsafe { GGufMetaKV::new_unchecked(data) })
     }
 
-    fn read_meta_value(&mut self, ty: Ty, len: usize) -> Result<&mut Self, GGufReadError> {
+    fn skip_meta_value(
+        &mut self,
+        ty: Ty,
+        len: usize,
+        check: bool,
+    ) -> Result<&mut Self, GGufReadError> {
         match ty {
             Ty::U8 => self.skip::<u8>(len),
             Ty::I8 => self.skip::<i8>(len),
@@ -30,12 +47,19 @@ impl<'a> GGufReader<'a> {
             Ty::U64 => self.skip::<u64>(len),
             Ty::I64 => self.skip::<i64>(len),
             Ty::F64 => self.skip::<f64>(len),
+            Ty::Bool if !check => self.skip::<u8>(len),
             Ty::Bool => {
                 for _ in 0..len {
                     self.read_bool()?;
                 }
                 Ok(self)
             }
+            Ty::String if !check => {
+                for _ in 0..len {
+                    self.skip_str()?;
+                }
+                Ok(self)
+            }
             Ty::String => {
                 for _ in 0..len {
                     self.read_str()?;
@@ -43,8 +67,11 @@ impl<'a> GGufReader<'a> {
                 Ok(self)
             }
             Ty::Array => {
-                let (ty, len) = self.read_arr_header()?;
-                self.read_meta_value(ty, len)
+                for _ in 0..len {
+                    let (ty, len) = self.read_arr_header()?;
+                    self.skip_meta_value(ty, len, check)?;
+                }
+                Ok(self)
             }
         }
     }
@@ -179,3 +206,27 @@ impl<T: Copy> Iterator for GGufMetaValueArray<'_, T> {
         }
     }
 }
+
+#[test]
+fn test_lazy() {
+    use crate::{GGufMetaValue, GGufWriter};
+
+    let value = GGufMetaValue::Array(Ty::String, vec!["a".into(), "b".into()]);
+    let mut buf = Vec::new();
+    GGufWriter::new(&mut buf)
+        .write_meta_kv("test", value.ty(), &value.to_bytes().unwrap())
+        .unwrap();
+    // 破坏第二个字符串
+    *buf.last_mut().unwrap() = 0xff;
+
+    assert!(GGufReader::new(&buf).read_meta_kv().is_err());
+
+    let kv = GGufReader::new(&buf).read_meta_kv_lazy().unwrap();
+    assert_eq!(kv.key(), "test");
+    let mut reader = kv.value_reader();
+    let (ty, len) = reader.read_arr_header().unwrap();
+    let mut arr = GGufMetaValueArray::<str>::new(reader, len);
+    assert_eq!(ty, Ty::String);
+    assert_eq!(arr.next(), Some(Ok("a")));
+    assert!(matches!(arr.next(), Some(Err(GGufReadError::Utf8(_)))));
+}
@@ -1,13 +1,52 @@
 //! See <https://github.com/ggerganov/ggml/blob/master/docs/gguf.md#standardized-key-value-pairs>.
 
+mod catalog;
 mod collection;
+mod hparams;
 mod meta_kv;
+mod token_type;
+mod value;
 
-pub use collection::{GGufMetaError, GGufMetaMap, GGufMetaMapExt};
+pub use catalog::{GGUF_META_KEYS, GGufMetaKey};
+pub use collection::{GGufMetaError, GGufMetaMap, GGufMetaMapExt, GGufMetaScalar};
+pub use hparams::LlmHyperParams;
 pub use meta_kv::{GGufMetaKV, GGufMetaValueArray};
+pub use token_type::GGufTokenTypes;
+pub use value::GGufMetaValue;
 
 pub const DEFAULT_ALIGNMENT: usize = 32;
 pub const GENERAL_ALIGNMENT: &str = "general.alignment";
+/// Key of the string value [`GGufMetaEditor`](crate::GGufMetaEditor) uses to fill the gap
+/// left by a shorter head, it carries no information and is dropped when models are merged.
+pub const GGUS_PADDING: &str = "ggus.padding";
+
+/// Parses the value of `general.alignment`, an u32 or u64 that must be a non-zero power of two.
+pub(crate) fn alignment_value(
+    ty: GGufMetaDataValueType,
+    val: &[u8],
+) -> Result<usize, GGufMetaError> {
+    let n = match (ty, val) {
+        (GGufMetaDataValueType::U32, &[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]) as u64,
+        (GGufMetaDataValueType::U64, val) if val.len() == 8 => {
+            u64::from_le_bytes(val.try_into().unwrap())
+        }
+        (ty, _) => return Err(GGufMetaError::TypeMismatch(ty)),
+    };
+    usize::try_from(n)
+        .ok()
+        .filter(|n| n.is_power_of_two())
+        .ok_or(GGufMetaError::OutOfRange)
+}
+
+/// Treats a missing key as `None`, other errors are returned.
+#[inline]
+pub(crate) fn optional<T>(res: Result<T, GGufMetaError>) -> Result<Option<T>, GGufMetaError> {
+    match res {
+        Ok(val) => Ok(Some(val)),
+        Err(GGufMetaError::NotExist) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(u32)]
@@ -46,6 +85,22 @@ pub enum GGufMetaDataValueType {
 }
 
 impl GGufMetaDataValueType {
+    /// Returns `true` for integer types of any width and signedness.
+    #[inline]
+    pub const fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::U8
+                | Self::I8
+                | Self::U16
+                | Self::I16
+                | Self::U32
+                | Self::I32
+                | Self::U64
+                | Self::I64
+        )
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Self::U8 => "u8",
@@ -109,7 +164,53 @@ pub enum GGufFileType {
     // GUESSED = 1024  # not specified in the model file
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+impl GGufFileType {
+    /// The name used as the encoding in file names, following llama.cpp, such as `Q4_K_M`.
+    #[allow(deprecated)]
+    #[rustfmt::skip]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AllF32            => "F32"          ,
+            Self::MostlyF16         => "F16"          ,
+            Self::MostlyQ4_0        => "Q4_0"         ,
+            Self::MostlyQ4_1        => "Q4_1"         ,
+            Self::MostlyQ4_1SomeF16 => "Q4_1_SOME_F16",
+            Self::MostlyQ4_2        => "Q4_2"         ,
+            Self::MostlyQ4_3        => "Q4_3"         ,
+            Self::MostlyQ8_0        => "Q8_0"         ,
+            Self::MostlyQ5_0        => "Q5_0"         ,
+            Self::MostlyQ51         => "Q5_1"         ,
+            Self::MostlyQ2K         => "Q2_K"         ,
+            Self::MostlyQ3KS        => "Q3_K_S"       ,
+            Self::MostlyQ3KM        => "Q3_K_M"       ,
+            Self::MostlyQ3KL        => "Q3_K_L"       ,
+            Self::MostlyQ4KS        => "Q4_K_S"       ,
+            Self::MostlyQ4KM        => "Q4_K_M"       ,
+            Self::MostlyQ5KS        => "Q5_K_S"       ,
+            Self::MostlyQ5KM        => "Q5_K_M"       ,
+            Self::MostlyQ6K         => "Q6_K"         ,
+            Self::MostlyIQ2XXS      => "IQ2_XXS"      ,
+            Self::MostlyIQ2XS       => "IQ2_XS"       ,
+            Self::MostlyQ2KS        => "Q2_K_S"       ,
+            Self::MostlyIQ3XS       => "IQ3_XS"       ,
+            Self::MostlyIQ3XXS      => "IQ3_XXS"      ,
+            Self::MostlyIQ1S        => "IQ1_S"        ,
+            Self::MostlyIQ4NL       => "IQ4_NL"       ,
+            Self::MostlyIQ3S        => "IQ3_S"        ,
+            Self::MostlyIQ3M        => "IQ3_M"        ,
+            Self::MostlyIQ2S        => "IQ2_S"        ,
+            Self::MostlyIQ2M        => "IQ2_M"        ,
+            Self::MostlyIQ4XS       => "IQ4_XS"       ,
+            Self::MostlyIQ1M        => "IQ1_M"        ,
+            Self::MostlyBF16        => "BF16"         ,
+            Self::MostlyQ4_0_4_4    => "Q4_0_4_4"     ,
+            Self::MostlyQ4_0_4_8    => "Q4_0_4_8"     ,
+            Self::MostlyQ4_0_8_8    => "Q4_0_8_8"     ,
+        }
+    }
+}
+
+#[derive(num_enum::TryFromPrimitive, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(i32)]
 pub enum GGmlTokenType {
     Normal = 1,
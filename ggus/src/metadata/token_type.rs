@@ -0,0 +1,152 @@
+use super::{GGmlTokenType, GGufMetaDataValueType as Ty, GGufMetaError};
+use crate::GGufReader;
+
+/// Token types of `tokenizer.ggml.token_type`, stored as an array of any integer type.
+pub struct GGufTokenTypes<'a> {
+    reader: GGufReader<'a>,
+    ty: Ty,
+    len: usize,
+}
+
+impl<'a> GGufTokenTypes<'a> {
+    /// Wraps the elements of an array, `ty` must be an integer type.
+    pub fn new(reader: GGufReader<'a>, ty: Ty, len: usize) -> Result<Self, GGufMetaError> {
+        if ty.is_integer() {
+            Ok(Self { reader, ty, len })
+        } else {
+            Err(GGufMetaError::ArrTypeMismatch(ty))
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    fn read(&mut self) -> Result<GGmlTokenType, GGufMetaError> {
+        macro_rules! read {
+            ($ty:ty) => {
+                self.reader
+                    .read::<$ty>()
+                    .map_err(GGufMetaError::Read)?
+                    .try_into()
+                    .map_err(|_| GGufMetaError::OutOfRange)?
+            };
+        }
+        #[rustfmt::skip]
+        let val: i32 = match self.ty {
+            Ty::U8  => read!(u8 ),
+            Ty::I8  => read!(i8 ),
+            Ty::U16 => read!(u16),
+            Ty::I16 => read!(i16),
+            Ty::U32 => read!(u32),
+            Ty::I32 => read!(i32),
+            Ty::U64 => read!(u64),
+            Ty::I64 => read!(i64),
+            _ => unreachable!(),
+        };
+        GGmlTokenType::try_from(val).map_err(|_| GGufMetaError::OutOfRange)
+    }
+}
+
+impl Iterator for GGufTokenTypes<'_> {
+    type Item = Result<GGmlTokenType, GGufMetaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len != 0 {
+            self.len -= 1;
+            Some(self.read())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl ExactSizeIterator for GGufTokenTypes<'_> {}
+
+/// Parses the text of a byte-fallback token, such as `<0x0A>`.
+pub(super) fn parse_byte_token(text: &str) -> Option<u8> {
+    let hex = text.strip_prefix("<0x")?.strip_suffix('>')?;
+    if hex.len() == 2 {
+        u8::from_str_radix(hex, 16).ok()
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_token_types() {
+    use crate::{GGufMetaMapExt, GGufMetaValue as V, GGufModel};
+
+    let mut meta = GGufModel::default();
+    let tokens = V::Array(
+        Ty::String,
+        ["<unk>", "<s>", "<0x0A>", "<0xZZ>", "<|user|>", "a"]
+            .into_iter()
+            .map(V::from)
+            .collect(),
+    );
+    meta.insert_meta(
+        "tokenizer.ggml.tokens",
+        Ty::Array,
+        tokens.to_bytes().unwrap(),
+    );
+    let types = V::Array(Ty::U8, [2u8, 3, 6, 1, 4, 1].map(V::from).to_vec());
+    meta.insert_meta(
+        "tokenizer.ggml.token_type",
+        Ty::Array,
+        types.to_bytes().unwrap(),
+    );
+
+    use GGmlTokenType as T;
+    let types = meta.tokenizer_ggml_token_type().unwrap();
+    assert_eq!(types.len(), 6);
+    assert_eq!(
+        types.collect::<Result<Vec<_>, _>>().unwrap(),
+        [
+            T::Unknown,
+            T::Control,
+            T::Byte,
+            T::Normal,
+            T::User,
+            T::Normal
+        ]
+    );
+    assert_eq!(meta.tokenizer_ggml_special_tokens().unwrap(), [1, 4]);
+
+    let bytes = meta.tokenizer_ggml_byte_tokens().unwrap();
+    assert_eq!(bytes[0x0a], Some(2));
+    assert_eq!(bytes.iter().flatten().count(), 1);
+
+    let types = V::Array(Ty::I64, vec![7i64.into()]);
+    meta.insert_meta(
+        "tokenizer.ggml.token_type",
+        Ty::Array,
+        types.to_bytes().unwrap(),
+    );
+    assert!(matches!(
+        meta.tokenizer_ggml_token_type().unwrap().next(),
+        Some(Err(GGufMetaError::OutOfRange))
+    ));
+
+    let types = V::Array(Ty::F32, vec![1f32.into()]);
+    meta.insert_meta(
+        "tokenizer.ggml.token_type",
+        Ty::Array,
+        types.to_bytes().unwrap(),
+    );
+    assert!(matches!(
+        meta.tokenizer_ggml_token_type(),
+        Err(GGufMetaError::ArrTypeMismatch(Ty::F32))
+    ));
+}
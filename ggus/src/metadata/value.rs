@@ -0,0 +1,203 @@
+use super::{GGufMetaDataValueType as Ty, GGufMetaKV};
+use crate::{GGufReadError, GGufReader, GGufWriter};
+use std::{
+    fmt,
+    io::{self, Error, ErrorKind, Write},
+};
+
+/// An owned, typed meta value.
+///
+/// Arrays keep their element type so that empty arrays round-trip exactly.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GGufMetaValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    Bool(bool),
+    String(String),
+    Array(Ty, Vec<GGufMetaValue>),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+impl GGufMetaValue {
+    pub const fn ty(&self) -> Ty {
+        match self {
+            Self::U8(_) => Ty::U8,
+            Self::I8(_) => Ty::I8,
+            Self::U16(_) => Ty::U16,
+            Self::I16(_) => Ty::I16,
+            Self::U32(_) => Ty::U32,
+            Self::I32(_) => Ty::I32,
+            Self::F32(_) => Ty::F32,
+            Self::Bool(_) => Ty::Bool,
+            Self::String(_) => Ty::String,
+            Self::Array(..) => Ty::Array,
+            Self::U64(_) => Ty::U64,
+            Self::I64(_) => Ty::I64,
+            Self::F64(_) => Ty::F64,
+        }
+    }
+
+    /// Encodes the value without its type tag, as expected by [`GGufWriter::write_meta_kv`].
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut ans = Vec::new();
+        GGufWriter::new(&mut ans).write_meta_value(self)?;
+        Ok(ans)
+    }
+}
+
+impl<'a> GGufReader<'a> {
+    pub fn read_meta_value(&mut self, ty: Ty) -> Result<GGufMetaValue, GGufReadError> {
+        use GGufMetaValue as V;
+        Ok(match ty {
+            Ty::U8 => V::U8(self.read()?),
+            Ty::I8 => V::I8(self.read()?),
+            Ty::U16 => V::U16(self.read()?),
+            Ty::I16 => V::I16(self.read()?),
+            Ty::U32 => V::U32(self.read()?),
+            Ty::I32 => V::I32(self.read()?),
+            Ty::F32 => V::F32(self.read()?),
+            Ty::U64 => V::U64(self.read()?),
+            Ty::I64 => V::I64(self.read()?),
+            Ty::F64 => V::F64(self.read()?),
+            Ty::Bool => V::Bool(self.read_bool()?),
+            Ty::String => V::String(self.read_str()?.into()),
+            Ty::Array => {
+                let (ty, len) = self.read_arr_header()?;
+                let vec = (0..len)
+                    .map(|_| self.read_meta_value(ty))
+                    .collect::<Result<_, _>>()?;
+                V::Array(ty, vec)
+            }
+        })
+    }
+}
+
+impl GGufMetaKV<'_> {
+    #[inline]
+    pub fn value(&self) -> Result<GGufMetaValue, GGufReadError> {
+        self.value_reader().read_meta_value(self.ty())
+    }
+}
+
+impl<T: Write> GGufWriter<T> {
+    /// Writes a meta value without its type tag.
+    pub fn write_meta_value(&mut self, val: &GGufMetaValue) -> io::Result<()> {
+        use GGufMetaValue as V;
+        match val {
+            V::U8(v) => self.write(&[*v]),
+            V::I8(v) => self.write(&[*v]),
+            V::U16(v) => self.write(&[*v]),
+            V::I16(v) => self.write(&[*v]),
+            V::U32(v) => self.write(&[*v]),
+            V::I32(v) => self.write(&[*v]),
+            V::F32(v) => self.write(&[*v]),
+            V::U64(v) => self.write(&[*v]),
+            V::I64(v) => self.write(&[*v]),
+            V::F64(v) => self.write(&[*v]),
+            V::Bool(v) => self.write(&[*v as u8]),
+            V::String(v) => self.write_str(v),
+            V::Array(ty, vec) => {
+                if let Some(e) = vec.iter().find(|e| e.ty() != *ty) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("array of {} contains {}", ty.name(), e.ty().name()),
+                    ));
+                }
+                self.write(&[*ty])?;
+                self.write(&[vec.len() as u64])?;
+                vec.iter().try_for_each(|e| self.write_meta_value(e))
+            }
+        }
+    }
+}
+
+macro_rules! from {
+    ($($ty:ty => $variant:ident),+) => {
+        $(
+            impl From<$ty> for GGufMetaValue {
+                #[inline]
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )+
+    };
+}
+
+from! {
+    u8     => U8    ,
+    i8     => I8    ,
+    u16    => U16   ,
+    i16    => I16   ,
+    u32    => U32   ,
+    i32    => I32   ,
+    f32    => F32   ,
+    bool   => Bool  ,
+    String => String,
+    &str   => String,
+    u64    => U64   ,
+    i64    => I64   ,
+    f64    => F64
+}
+
+impl fmt::Display for GGufMetaValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GGufMetaValue as V;
+        match self {
+            V::U8(v) => write!(f, "{v}"),
+            V::I8(v) => write!(f, "{v}"),
+            V::U16(v) => write!(f, "{v}"),
+            V::I16(v) => write!(f, "{v}"),
+            V::U32(v) => write!(f, "{v}"),
+            V::I32(v) => write!(f, "{v}"),
+            V::F32(v) => write!(f, "{v}"),
+            V::U64(v) => write!(f, "{v}"),
+            V::I64(v) => write!(f, "{v}"),
+            V::F64(v) => write!(f, "{v}"),
+            V::Bool(v) => write!(f, "{v}"),
+            V::String(v) => write!(f, "{v:?}"),
+            V::Array(_, vec) => {
+                f.write_str("[")?;
+                for (i, e) in vec.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?
+                    }
+                    write!(f, "{e}")?
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+#[test]
+fn test_value() {
+    let value = GGufMetaValue::Array(
+        Ty::Array,
+        vec![
+            GGufMetaValue::Array(Ty::String, vec!["a".into(), "b".into()]),
+            GGufMetaValue::Array(Ty::String, vec![]),
+        ],
+    );
+    assert_eq!(value.to_string(), r#"[["a", "b"], []]"#);
+
+    let mut buf = Vec::new();
+    let mut writer = GGufWriter::new(&mut buf);
+    writer
+        .write_meta_kv("test", value.ty(), &value.to_bytes().unwrap())
+        .unwrap();
+    drop(writer);
+
+    let kv = GGufMetaKV::new(&buf).unwrap();
+    assert_eq!(kv.value().unwrap(), value);
+
+    let invalid = GGufMetaValue::Array(Ty::U8, vec![1u8.into(), 2u16.into()]);
+    assert!(invalid.to_bytes().is_err());
+}
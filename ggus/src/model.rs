@@ -0,0 +1,410 @@
+use crate::{
+    DataFuture, GENERAL_ALIGNMENT, GGUS_PADDING, GGmlType, GGuf, GGufError, GGufFileHeader,
+    GGufFileWriter, GGufMetaDataValueType, GGufMetaMap, GGufMetaScalar, GGufWriter,
+};
+use indexmap::IndexMap;
+use std::{
+    borrow::Cow,
+    error, fmt,
+    io::{Error, ErrorKind, Result, Write},
+    ops::Deref,
+    sync::{Arc, LazyLock},
+};
+
+/// An owned, editable GGUF model.
+///
+/// Metadata and tensor infos are owned, tensor data may be borrowed from a mapped file,
+/// owned in memory or computed lazily when the model is written.
+#[derive(Clone)]
+pub struct GGufModel<'a> {
+    pub alignment: usize,
+    pub meta_kvs: IndexMap<Cow<'a, str>, GGufMetaBuf<'a>>,
+    pub tensors: IndexMap<Cow<'a, str>, GGufTensorBuf<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GGufMetaBuf<'a> {
+    pub ty: GGufMetaDataValueType,
+    pub value: Cow<'a, [u8]>,
+}
+
+#[derive(Clone)]
+pub struct GGufTensorBuf<'a> {
+    pub ty: GGmlType,
+    pub shape: Vec<u64>,
+    pub data: GGufTensorData<'a>,
+}
+
+impl GGufMetaBuf<'static> {
+    /// Encodes a string value.
+    pub fn string(val: impl AsRef<str>) -> Self {
+        let mut value = Vec::with_capacity(val.as_ref().len() + size_of::<u64>());
+        GGufWriter::new(&mut value).write_str(val).unwrap();
+        Self {
+            ty: GGufMetaDataValueType::String,
+            value: value.into(),
+        }
+    }
+
+    /// Encodes a scalar value, tagged with the type of `T`.
+    pub fn scalar<T: GGufMetaScalar>(val: T) -> Self {
+        let mut value = Vec::with_capacity(size_of::<T>());
+        GGufWriter::new(&mut value).write(&[val]).unwrap();
+        Self {
+            ty: T::TYPE,
+            value: value.into(),
+        }
+    }
+
+    /// Encodes an array value of scalars.
+    pub fn array<T: GGufMetaScalar>(val: &[T]) -> Self {
+        let mut value = Vec::with_capacity(size_of::<u32>() + size_of::<u64>() + size_of_val(val));
+        GGufWriter::new(&mut value).write_arr(val).unwrap();
+        Self {
+            ty: GGufMetaDataValueType::Array,
+            value: value.into(),
+        }
+    }
+
+    /// Encodes an array value of strings.
+    pub fn str_array(val: &[impl AsRef<str>]) -> Self {
+        let mut value = Vec::new();
+        GGufWriter::new(&mut value).write_str_arr(val).unwrap();
+        Self {
+            ty: GGufMetaDataValueType::Array,
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum GGufTensorData<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Arc<[u8]>),
+    Lazy(Arc<dyn DataFuture<Error = GGufDataError> + Send + Sync + 'a>),
+    /// Data not present in the source file, such as a skeleton file without tensor data.
+    Missing,
+}
+
+/// Error of getting [`GGufTensorData`], cloned to every reader of a failed lazy computation.
+#[derive(Clone, Debug)]
+pub enum GGufDataError {
+    /// The data is [`GGufTensorData::Missing`].
+    Missing,
+    /// The lazy computation failed.
+    Failed(Arc<dyn error::Error + Send + Sync>),
+}
+
+impl fmt::Display for GGufDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("tensor data is missing"),
+            Self::Failed(e) => write!(f, "tensor data computation failed: {e}"),
+        }
+    }
+}
+
+impl error::Error for GGufDataError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Missing => None,
+            Self::Failed(e) => Some(&**e),
+        }
+    }
+}
+
+impl<'a> GGufTensorData<'a> {
+    /// Creates tensor data that will be computed by `f` the first time it is accessed.
+    #[inline]
+    pub fn lazy<T, F>(f: F) -> Self
+    where
+        T: Deref<Target = [u8]> + Send + Sync + 'a,
+        F: FnOnce() -> T + Send + 'a,
+    {
+        Self::Lazy(Arc::new(Lazy(LazyLock::new(move || Ok(f())))))
+    }
+
+    /// Creates tensor data that will be computed by `f` the first time it is accessed,
+    /// a failure is reported to every access.
+    #[inline]
+    pub fn try_lazy<T, E, F>(f: F) -> Self
+    where
+        T: Deref<Target = [u8]> + Send + Sync + 'a,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+        F: FnOnce() -> std::result::Result<T, E> + Send + 'a,
+    {
+        Self::Lazy(Arc::new(Lazy(LazyLock::new(move || {
+            f().map_err(|e| GGufDataError::Failed(e.into().into()))
+        }))))
+    }
+
+    #[inline]
+    pub const fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+}
+
+impl From<Vec<u8>> for GGufTensorData<'_> {
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Self::Owned(value.into())
+    }
+}
+
+impl<'a> From<&'a [u8]> for GGufTensorData<'a> {
+    #[inline]
+    fn from(value: &'a [u8]) -> Self {
+        Self::Borrowed(value)
+    }
+}
+
+impl DataFuture for GGufTensorData<'_> {
+    type Error = GGufDataError;
+
+    #[inline]
+    fn get(&self) -> std::result::Result<&[u8], GGufDataError> {
+        match self {
+            Self::Borrowed(data) => Ok(data),
+            Self::Owned(data) => Ok(data),
+            Self::Lazy(data) => data.get(),
+            Self::Missing => Err(GGufDataError::Missing),
+        }
+    }
+}
+
+struct Lazy<T, F>(LazyLock<std::result::Result<T, GGufDataError>, F>);
+
+impl<T, F> DataFuture for Lazy<T, F>
+where
+    T: Deref<Target = [u8]>,
+    F: FnOnce() -> std::result::Result<T, GGufDataError>,
+{
+    type Error = GGufDataError;
+
+    #[inline]
+    fn get(&self) -> std::result::Result<&[u8], GGufDataError> {
+        match &*self.0 {
+            Ok(data) => Ok(data),
+            Err(e) => Err(e.clone()),
+        }
+    }
+}
+
+impl Default for GGufModel<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(crate::DEFAULT_ALIGNMENT)
+    }
+}
+
+impl GGufMetaMap for GGufModel<'_> {
+    fn get(&self, key: &str) -> Option<(GGufMetaDataValueType, &[u8])> {
+        self.meta_kvs.get(key).map(|v| (v.ty, &*v.value))
+    }
+}
+
+impl<'a> From<GGuf<'a>> for GGufModel<'a> {
+    #[inline]
+    fn from(gguf: GGuf<'a>) -> Self {
+        let mut ans = Self::new(gguf.alignment);
+        ans.merge(gguf).unwrap();
+        ans
+    }
+}
+
+impl<'a> GGufModel<'a> {
+    #[inline]
+    pub fn new(alignment: usize) -> Self {
+        Self {
+            alignment,
+            meta_kvs: Default::default(),
+            tensors: Default::default(),
+        }
+    }
+
+    /// Merges the contents of a parsed file into this model.
+    ///
+    /// `general.alignment`, `split.*` and [`GGUS_PADDING`] keys are dropped, the larger alignment is kept.
+    pub fn merge(&mut self, gguf: GGuf<'a>) -> std::result::Result<(), GGufError> {
+        self.alignment = self.alignment.max(gguf.alignment);
+
+        for (&k, kv) in &gguf.meta_kvs {
+            if k == GENERAL_ALIGNMENT || k == GGUS_PADDING || k.starts_with("split.") {
+                continue;
+            }
+            let value = GGufMetaBuf {
+                ty: kv.ty(),
+                value: kv.value_bytes().into(),
+            };
+            if self.meta_kvs.insert(k.into(), value).is_some() {
+                return Err(GGufError::DuplicateMetaKey(k.into()));
+            }
+        }
+
+        for (&name, tensor) in &gguf.tensors {
+            let info = tensor.to_info();
+            let tensor = GGufTensorBuf {
+                ty: info.ty(),
+                shape: info.shape().to_vec(),
+                data: gguf
+                    .tensor_data(name)
+                    .map_or(GGufTensorData::Missing, GGufTensorData::Borrowed),
+            };
+            if self.tensors.insert(name.into(), tensor).is_some() {
+                return Err(GGufError::DuplicateTensorName(name.into()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inserts or replaces a meta kv, keeping its position if the key already exists.
+    #[inline]
+    pub fn insert_meta(
+        &mut self,
+        key: impl Into<Cow<'a, str>>,
+        ty: GGufMetaDataValueType,
+        value: impl Into<Cow<'a, [u8]>>,
+    ) -> Option<GGufMetaBuf<'a>> {
+        let value = GGufMetaBuf {
+            ty,
+            value: value.into(),
+        };
+        self.meta_kvs.insert(key.into(), value)
+    }
+
+    /// Removes a meta kv, keeping the order of the others.
+    #[inline]
+    pub fn remove_meta(&mut self, key: &str) -> Option<GGufMetaBuf<'a>> {
+        self.meta_kvs.shift_remove(key)
+    }
+
+    /// Inserts or replaces a tensor, keeping its position if the name already exists.
+    #[inline]
+    pub fn insert_tensor(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        ty: GGmlType,
+        shape: impl Into<Vec<u64>>,
+        data: impl Into<GGufTensorData<'a>>,
+    ) -> Option<GGufTensorBuf<'a>> {
+        let tensor = GGufTensorBuf {
+            ty,
+            shape: shape.into(),
+            data: data.into(),
+        };
+        self.tensors.insert(name.into(), tensor)
+    }
+
+    /// Removes a tensor, keeping the order of the others.
+    #[inline]
+    pub fn remove_tensor(&mut self, name: &str) -> Option<GGufTensorBuf<'a>> {
+        self.tensors.shift_remove(name)
+    }
+
+    /// Writes the model as a single gguf file, returns the number of bytes written.
+    ///
+    /// Fails before writing anything if `write_data` is set and some tensor data is missing.
+    pub fn write<T: Write>(&self, writer: T, write_data: bool) -> Result<usize> {
+        if write_data
+            && let Some((name, _)) = self.tensors.iter().find(|(_, t)| t.data.is_missing())
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("data of tensor {name} is missing"),
+            ));
+        }
+
+        let meta_kvs = self
+            .meta_kvs
+            .iter()
+            .filter(|(k, _)| *k != GENERAL_ALIGNMENT)
+            .collect::<Vec<_>>();
+        let header = GGufFileHeader::new(3, self.tensors.len() as _, meta_kvs.len() as u64 + 1);
+
+        let mut writer = GGufFileWriter::with_alignment(writer, header, self.alignment)?;
+        for (k, v) in meta_kvs {
+            writer.write_meta_kv(k, v.ty, &v.value)?;
+        }
+
+        let mut writer = writer.finish(write_data);
+        for (name, tensor) in &self.tensors {
+            writer.write_tensor(name, tensor.ty, &tensor.shape, tensor.data.clone())?;
+        }
+        writer.finish()
+    }
+}
+
+#[test]
+fn test_model() {
+    use crate::GGufMetaMapExt;
+
+    let mut model = GGufModel::default();
+    let mut name = Vec::new();
+    crate::GGufWriter::new(&mut name).write_str("test").unwrap();
+    model.insert_meta("general.name", GGufMetaDataValueType::String, name);
+    model.insert_meta(
+        "test.value",
+        GGufMetaDataValueType::U32,
+        7u32.to_le_bytes().to_vec(),
+    );
+    model.insert_tensor("a", GGmlType::F32, [4], vec![1; 16]);
+    model.insert_tensor(
+        "b",
+        GGmlType::I8,
+        [3],
+        GGufTensorData::lazy(|| vec![2u8; 3]),
+    );
+    model.insert_tensor("c", GGmlType::I8, [1], vec![3]);
+    assert!(model.remove_tensor("c").is_some());
+    model.insert_meta(
+        "test.value",
+        GGufMetaDataValueType::U64,
+        8u64.to_le_bytes().to_vec(),
+    );
+
+    let mut buf = Vec::new();
+    let len = model.write(&mut buf, true).unwrap();
+    assert_eq!(len, buf.len());
+
+    let gguf = GGuf::new(&buf).unwrap();
+    assert_eq!(gguf.general_name().unwrap(), "test");
+    assert_eq!(gguf.get_usize("test.value").unwrap(), 8);
+
+    let model = GGufModel::from(gguf);
+    assert_eq!(model.meta_kvs.len(), 2);
+    assert_eq!(model.tensors.len(), 2);
+    assert_eq!(model.tensors["a"].data.get().unwrap(), &[1; 16]);
+    assert_eq!(model.tensors["b"].data.get().unwrap(), &[2; 3]);
+
+    // 骨架文件
+    let mut buf = Vec::new();
+    model.write(&mut buf, false).unwrap();
+
+    let gguf = GGuf::new(&buf).unwrap();
+    assert!(gguf.is_skeleton());
+    assert_eq!(gguf.tensor_data("a"), None);
+    assert_eq!(gguf.missing_bytes("a"), Some(16));
+    assert_eq!(gguf.missing_bytes("c"), None);
+
+    let model = GGufModel::from(gguf);
+    assert!(model.tensors["b"].data.is_missing());
+    assert!(model.write(Vec::new(), true).is_err());
+    assert!(model.write(Vec::new(), false).is_ok());
+
+    // 惰性计算失败时返回错误
+    let mut model = model;
+    model.insert_tensor(
+        "b",
+        GGmlType::I8,
+        [3],
+        GGufTensorData::try_lazy(|| Err::<Vec<u8>, _>("boom")),
+    );
+    model.insert_tensor("a", GGmlType::F32, [4], vec![1; 16]);
+    let e = model.write(Vec::new(), true).unwrap_err();
+    assert!(e.to_string().contains("boom"));
+    assert!(matches!(
+        model.tensors["b"].data.get(),
+        Err(GGufDataError::Failed(_))
+    ));
+}
@@ -3,9 +3,11 @@ mod size_label;
 mod r#type;
 mod version;
 
+use crate::{GGmlType, GGufMetaError, GGufMetaMap, GGufMetaMapExt, metadata::optional};
+use regex::Captures;
 use shard::Shard;
 use size_label::SizeLabel;
-use std::{borrow::Cow, fmt, num::NonZero, path::Path};
+use std::{borrow::Cow, collections::HashMap, fmt, num::NonZero, path::Path};
 use r#type::Type;
 use version::Version;
 
@@ -29,11 +31,15 @@ mod pattern {
     pub const TYPE_LORA: &str = r"-LoRA";
     pub const TYPE_VOCAB: &str = r"-vocab";
     pub const SHARD_: &str = r"-(\d{5})-of-(\d{5})$";
+    pub const SIZE_LABEL_: &str = r"^(\d+x)?(\d+)(\.\d+)?([QTBMK])$";
+    pub const VERSION_META_: &str = r"^v?(\d+)(\.(\d+))?$";
     pub const EXT: &str = ".gguf";
 
     pub static NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(NAME_).unwrap());
     pub static VERSION: LazyLock<Regex> = LazyLock::new(|| Regex::new(VERSION_).unwrap());
     pub static SHARD: LazyLock<Regex> = LazyLock::new(|| Regex::new(SHARD_).unwrap());
+    pub static SIZE_LABEL: LazyLock<Regex> = LazyLock::new(|| Regex::new(SIZE_LABEL_).unwrap());
+    pub static VERSION_META: LazyLock<Regex> = LazyLock::new(|| Regex::new(VERSION_META_).unwrap());
 }
 
 #[derive(Debug)]
@@ -87,23 +93,17 @@ impl<'a> TryFrom<&'a str> for GGufFileName<'a> {
                 Version::new(major.parse().unwrap(), minor.parse().unwrap())
             });
 
-        if let Some(capture) = pattern::NAME.captures(name) {
+        if let Some(capture) = pattern::NAME.captures(name)
+            && let Some(size_label) = parse_size_label(&capture)
+        {
             let base_name = &name[..name.len() - capture.get(0).unwrap().len()];
-            let e = capture.get(1).map_or(1, |m| {
-                m.as_str().strip_suffix('x').unwrap().parse().unwrap()
-            });
-            let a = capture.get(2).unwrap().as_str().parse().unwrap();
-            let b = capture.get(3).map_or(0, |m| {
-                m.as_str().strip_prefix('.').unwrap().parse().unwrap()
-            });
-            let l = capture.get(4).unwrap().as_str().chars().next().unwrap();
             let fine_tune = capture
                 .get(5)
                 .map_or("", |m| m.as_str().strip_prefix('-').unwrap());
 
             Ok(Self {
                 base_name: base_name.into(),
-                size_label: Some(SizeLabel::new(e, a, b, l)),
+                size_label: Some(size_label),
                 fine_tune: fine_tune.into(),
                 version,
                 encoding: Some(encoding.into()),
@@ -132,6 +132,137 @@ impl<'a> TryFrom<&'a Path> for GGufFileName<'a> {
     }
 }
 
+impl<'a> GGufFileName<'a> {
+    /// Builds the canonical file name of a model, following the naming convention of llama.cpp.
+    ///
+    /// `tensors` yields the type and the number of elements of each tensor,
+    /// used to compute the size label and the encoding if they are missing in metadata.
+    pub fn from_meta(
+        meta: &'a impl GGufMetaMap,
+        tensors: impl IntoIterator<Item = (GGmlType, u64)>,
+    ) -> Result<Self, GGufMetaError> {
+        let base_name = match meta.general_basename() {
+            Err(GGufMetaError::NotExist) => meta.general_name(),
+            name => name,
+        };
+        let base_name = match base_name {
+            Ok(name) => normalize(name),
+            Err(GGufMetaError::NotExist) => "model".into(),
+            Err(e) => return Err(e),
+        };
+        let fine_tune = optional(meta.general_finetune())?.map_or("".into(), normalize);
+
+        let version = match optional(meta.general_version())? {
+            Some(version) => {
+                let capture = pattern::VERSION_META
+                    .captures(version.trim())
+                    .ok_or(GGufMetaError::OutOfRange)?;
+                let major = capture[1].parse().map_err(|_| GGufMetaError::OutOfRange)?;
+                let minor = capture
+                    .get(3)
+                    .map_or(Ok(0), |m| m.as_str().parse())
+                    .map_err(|_| GGufMetaError::OutOfRange)?;
+                Version::new(major, minor)
+            }
+            None => Version::DEFAULT,
+        };
+
+        let type_ = match optional(meta.get_str("general.type"))? {
+            Some("adapter") => Type::LoRA,
+            Some("vocab") => Type::Vocab,
+            _ => Type::Default,
+        };
+
+        // 统计张量参数量和各类型的元素数
+        let mut n_params = 0;
+        let mut types = HashMap::<GGmlType, u64>::new();
+        for (ty, n) in tensors {
+            n_params += n;
+            *types.entry(ty).or_default() += n
+        }
+
+        // 无法解析的标签按参数量重新计算
+        let size_label = optional(meta.general_size_label())?
+            .and_then(|label| pattern::SIZE_LABEL.captures(label.trim()))
+            .and_then(|capture| parse_size_label(&capture));
+        let size_label = match size_label {
+            Some(label) => Some(label),
+            None if n_params > 0 => {
+                let experts = match meta.llm_expert_count() {
+                    Ok(n) if n > 0 => n as u64,
+                    Ok(_) | Err(GGufMetaError::NotExist | GGufMetaError::NoArchitecture) => 1,
+                    Err(e) => return Err(e),
+                };
+                count_label(experts, n_params / experts)
+            }
+            None => None,
+        };
+
+        // 未知的文件类型按张量类型推断
+        let encoding = match meta.general_filetype() {
+            Ok(ty) => Some(ty.name()),
+            Err(GGufMetaError::NotExist | GGufMetaError::OutOfRange) => types
+                .into_iter()
+                .max_by_key(|&(ty, n)| (n, ty as u32))
+                .map(|(ty, _)| ty.name()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            base_name,
+            size_label,
+            fine_tune,
+            version,
+            encoding: encoding.map(Cow::Borrowed),
+            type_,
+            shard: Shard::default(),
+        })
+    }
+}
+
+/// Parses the size label captured by [`pattern::NAME`] or [`pattern::SIZE_LABEL`],
+/// `None` if a number overflows or the expert count is zero.
+fn parse_size_label(capture: &Captures) -> Option<SizeLabel> {
+    let e = match capture.get(1) {
+        Some(m) => m.as_str().strip_suffix('x')?.parse().ok()?,
+        None => 1,
+    };
+    let a = capture[2].parse().ok()?;
+    let b = capture.get(3).map_or("", |m| &m.as_str()[1..]);
+    let l = capture[4].chars().next()?;
+    SizeLabel::new(e, a, b, l)
+}
+
+/// Replaces characters that would break the name pattern, like llama.cpp does.
+fn normalize(name: &str) -> Cow<'_, str> {
+    let name = name.trim();
+    if name.contains([' ', '/']) {
+        name.replace([' ', '/'], "-").into()
+    } else {
+        name.into()
+    }
+}
+
+/// Rounds the number of parameters per expert to a label like `1.1B` or `135M`.
+fn count_label(experts: u64, n: u64) -> Option<SizeLabel> {
+    let (scale, l) = match n {
+        1_000_000_000_000.. => (1e12, 'T'),
+        1_000_000_000.. => (1e9, 'B'),
+        1_000_000.. => (1e6, 'M'),
+        _ => (1e3, 'K'),
+    };
+    let val = n as f64 / scale;
+    // 保留两位有效数字
+    let (a, b) = if val < 10. {
+        let x = (val * 10.).round() as u32;
+        (x / 10, x % 10)
+    } else {
+        (val.round() as u32, 0)
+    };
+    let b = if b == 0 { String::new() } else { b.to_string() };
+    SizeLabel::new(experts.try_into().ok()?, a, b, l)
+}
+
 impl GGufFileName<'_> {
     #[inline]
     pub fn shard_count(&self) -> usize {
@@ -216,3 +347,99 @@ fn test_name() {
     check("MiniCPM3-1B-sft-v0.0-F16.gguf");
     check("MiniCPM-V-Clip-1B-v2.6-F16.gguf");
 }
+
+#[test]
+fn test_from_meta() {
+    use crate::{GGufFileType, GGufMetaBuf, GGufMetaDataValueType as Ty, GGufModel};
+
+    let mut model = GGufModel::default();
+    model.insert_meta(
+        "general.basename",
+        Ty::String,
+        GGufMetaBuf::string("Tiny Llama").value,
+    );
+    model.insert_meta(
+        "general.finetune",
+        Ty::String,
+        GGufMetaBuf::string("Chat").value,
+    );
+    model.insert_meta(
+        "general.version",
+        Ty::String,
+        GGufMetaBuf::string("v1.1").value,
+    );
+    let tensors = [
+        (GGmlType::F32, 100_000_000),
+        (GGmlType::Q8_0, 1_000_000_000),
+    ];
+    let name = GGufFileName::from_meta(&model, tensors).unwrap();
+    assert_eq!(name.to_string(), "Tiny-Llama-1.1B-Chat-v1.1-Q8_0.gguf");
+
+    model.insert_meta(
+        "general.size_label",
+        Ty::String,
+        GGufMetaBuf::string("8x7B").value,
+    );
+    model.insert_meta(
+        "general.file_type",
+        Ty::U32,
+        (GGufFileType::MostlyQ4KM as u32).to_le_bytes().to_vec(),
+    );
+    model.insert_meta(
+        "general.type",
+        Ty::String,
+        GGufMetaBuf::string("adapter").value,
+    );
+    let name = GGufFileName::from_meta(&model, tensors).unwrap();
+    assert_eq!(
+        name.to_string(),
+        "Tiny-Llama-8x7B-Chat-v1.1-Q4_K_M-LoRA.gguf"
+    );
+    assert_eq!(
+        GGufFileName::try_from(&*name.to_string())
+            .unwrap()
+            .to_string(),
+        name.to_string()
+    );
+
+    // 无法解析的标签按参数量计算，小数部分保持原样
+    for (label, expected) in [
+        ("0x7B", "1.1B"),
+        ("99999999999B", "1.1B"),
+        ("large", "1.1B"),
+        ("1.05B", "1.05B"),
+    ] {
+        model.insert_meta(
+            "general.size_label",
+            Ty::String,
+            GGufMetaBuf::string(label).value,
+        );
+        let name = GGufFileName::from_meta(&model, tensors).unwrap();
+        assert_eq!(
+            name.to_string(),
+            format!("Tiny-Llama-{expected}-Chat-v1.1-Q4_K_M-LoRA.gguf")
+        );
+    }
+
+    // 未知的文件类型按张量类型推断
+    model.insert_meta("general.file_type", Ty::U32, 1024u32.to_le_bytes().to_vec());
+    let name = GGufFileName::from_meta(&model, tensors).unwrap();
+    assert_eq!(name.encoding.as_deref(), Some("Q8_0"));
+
+    let model = GGufModel::default();
+    let name = GGufFileName::from_meta(&model, [(GGmlType::F16, 135_000_000)]).unwrap();
+    assert_eq!(name.to_string(), "model-135M-v1.0-F16.gguf");
+
+    assert_eq!(
+        GGufFileName::try_from("Tiny-1.05B-v1.0-F16.gguf")
+            .unwrap()
+            .to_string(),
+        "Tiny-1.05B-v1.0-F16.gguf"
+    );
+    assert!(
+        GGufFileName::try_from("Tiny-0x7B-v1.0-F16.gguf")
+            .unwrap()
+            .size_label
+            .is_none()
+    );
+}
@@ -4,31 +4,34 @@
 pub struct SizeLabel {
     e: NonZeroU32,
     a: u32,
-    b: u32,
+    /// Fractional digits as written, empty for an integer.
+    b: String,
     l: char,
 }
 
 impl SizeLabel {
-    pub fn new(e: u32, a: u32, b: u32, l: char) -> Self {
-        Self {
-            e: NonZeroU32::new(e).unwrap(),
+    /// Returns `None` if the expert count is zero.
+    pub fn new(e: u32, a: u32, b: impl Into<String>, l: char) -> Option<Self> {
+        Some(Self {
+            e: NonZeroU32::new(e)?,
             a,
-            b,
+            b: b.into(),
             l,
-        }
+        })
     }
 }
 
 impl fmt::Display for SizeLabel {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let &Self { e, a, b, l } = self;
+        let Self { e, a, b, l } = self;
         match e.get() {
             1 => {}
             _ => write!(f, "{e}x")?,
         }
-        match b {
-            0 => write!(f, "{a}{l}"),
-            _ => write!(f, "{a}.{b}{l}"),
+        if b.is_empty() {
+            write!(f, "{a}{l}")
+        } else {
+            write!(f, "{a}.{b}{l}")
         }
     }
 }
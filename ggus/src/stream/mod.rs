@@ -0,0 +1,123 @@
+use crate::{GGufError, GGufIndex, GGufReadError};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+#[cfg(feature = "tokio")]
+mod tokio;
+
+#[cfg(feature = "tokio")]
+pub use tokio::GGufAsyncStreamReader;
+
+/// Reads a gguf file from any seekable stream, loading tensor data on demand.
+pub struct GGufStreamReader<R> {
+    reader: R,
+    start: u64,
+    index: GGufIndex,
+}
+
+/// Size of the first read when looking for the end of the file head.
+const HEAD_CHUNK: usize = 1 << 16;
+
+/// Parses the head in `buf`, returns `None` if more data is needed.
+fn try_parse(buf: &[u8], eof: bool) -> Option<std::result::Result<GGufIndex, GGufError>> {
+    match GGufIndex::new(buf) {
+        Err(GGufError::Reading(GGufReadError::Eos)) if !eof => None,
+        index => Some(index),
+    }
+}
+
+impl<R: Read + Seek> GGufStreamReader<R> {
+    /// Parses the head of the gguf file starting at the current position of `reader`.
+    pub fn new(mut reader: R) -> std::result::Result<Self, GGufError> {
+        let start = reader.stream_position().map_err(GGufError::Io)?;
+
+        let mut buf = Vec::new();
+        let mut chunk = HEAD_CHUNK;
+        loop {
+            let len = buf.len();
+            (&mut reader)
+                .take(chunk as _)
+                .read_to_end(&mut buf)
+                .map_err(GGufError::Io)?;
+            if let Some(index) = try_parse(&buf, buf.len() < len + chunk) {
+                return index.map(|index| Self {
+                    reader,
+                    start,
+                    index,
+                });
+            }
+            chunk = buf.len()
+        }
+    }
+
+    #[inline]
+    pub fn index(&self) -> &GGufIndex {
+        &self.index
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads `buf.len()` bytes at `offset` of the tensor data section.
+    pub fn read_data(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let pos = self.start + self.index.data_offset + offset;
+        self.reader.seek(SeekFrom::Start(pos))?;
+        self.reader.read_exact(buf)
+    }
+
+    /// Reads the whole data of a tensor.
+    pub fn read_tensor(&mut self, name: &str) -> Result<Vec<u8>> {
+        let (offset, mut ans) = tensor_buf(&self.index, name)?;
+        self.read_data(offset, &mut ans)?;
+        Ok(ans)
+    }
+}
+
+/// Finds a tensor, returns its offset and a buffer for its data.
+fn tensor_buf(index: &GGufIndex, name: &str) -> Result<(u64, Vec<u8>)> {
+    match index.tensors.get(name) {
+        Some(tensor) => Ok((tensor.offset, vec![0; tensor.nbytes()])),
+        None => Err(Error::new(
+            ErrorKind::NotFound,
+            format!("tensor {name} not found"),
+        )),
+    }
+}
+
+/// Builds a file with a head longer than [`HEAD_CHUNK`], after 5 bytes of garbage.
+#[cfg(test)]
+fn test_file() -> Vec<u8> {
+    use crate::{GGmlType, GGufMetaDataValueType, GGufModel, GGufWriter};
+
+    let mut value = Vec::new();
+    GGufWriter::new(&mut value)
+        .write_str("x".repeat(HEAD_CHUNK * 3))
+        .unwrap();
+
+    let mut model = GGufModel::default();
+    model.insert_meta("test.long", GGufMetaDataValueType::String, value);
+    model.insert_tensor("a", GGmlType::F32, [4], vec![1; 16]);
+    model.insert_tensor("b", GGmlType::I8, [3], vec![2; 3]);
+
+    let mut buf = vec![0xff; 5];
+    model.write(&mut buf, true).unwrap();
+    buf
+}
+
+#[test]
+fn test_stream() {
+    use crate::GGufMetaMapExt;
+    use std::io::Cursor;
+
+    let mut cursor = Cursor::new(test_file());
+    cursor.set_position(5);
+    let mut reader = GGufStreamReader::new(cursor).unwrap();
+    assert_eq!(
+        reader.index().get_str("test.long").unwrap().len(),
+        HEAD_CHUNK * 3
+    );
+    assert_eq!(reader.read_tensor("b").unwrap(), [2; 3]);
+    assert_eq!(reader.read_tensor("a").unwrap(), [1; 16]);
+    assert!(reader.read_tensor("c").is_err());
+}
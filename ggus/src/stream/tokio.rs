@@ -0,0 +1,77 @@
+use super::{HEAD_CHUNK, tensor_buf, try_parse};
+use crate::{GGufError, GGufIndex};
+use std::io::{Result, SeekFrom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// Async version of [`super::GGufStreamReader`].
+pub struct GGufAsyncStreamReader<R> {
+    reader: R,
+    start: u64,
+    index: GGufIndex,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> GGufAsyncStreamReader<R> {
+    /// Parses the head of the gguf file starting at the current position of `reader`.
+    pub async fn new(mut reader: R) -> std::result::Result<Self, GGufError> {
+        let start = reader.stream_position().await.map_err(GGufError::Io)?;
+
+        let mut buf = Vec::new();
+        let mut chunk = HEAD_CHUNK;
+        loop {
+            let len = buf.len();
+            (&mut reader)
+                .take(chunk as _)
+                .read_to_end(&mut buf)
+                .await
+                .map_err(GGufError::Io)?;
+            if let Some(index) = try_parse(&buf, buf.len() < len + chunk) {
+                return index.map(|index| Self {
+                    reader,
+                    start,
+                    index,
+                });
+            }
+            chunk = buf.len()
+        }
+    }
+
+    #[inline]
+    pub fn index(&self) -> &GGufIndex {
+        &self.index
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads `buf.len()` bytes at `offset` of the tensor data section.
+    pub async fn read_data(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let pos = self.start + self.index.data_offset + offset;
+        self.reader.seek(SeekFrom::Start(pos)).await?;
+        self.reader.read_exact(buf).await.map(|_| ())
+    }
+
+    /// Reads the whole data of a tensor.
+    pub async fn read_tensor(&mut self, name: &str) -> Result<Vec<u8>> {
+        let (offset, mut ans) = tensor_buf(&self.index, name)?;
+        self.read_data(offset, &mut ans).await?;
+        Ok(ans)
+    }
+}
+
+#[tokio::test]
+async fn test_async_stream() {
+    use crate::GGufMetaMapExt;
+    use std::io::Cursor;
+
+    let mut cursor = Cursor::new(super::test_file());
+    cursor.set_position(5);
+    let mut reader = GGufAsyncStreamReader::new(cursor).await.unwrap();
+    let index = reader.index();
+    assert_eq!(index.get_str("test.long").unwrap().len(), HEAD_CHUNK * 3);
+    assert_eq!(index.tensors.len(), 2);
+    assert_eq!(reader.read_tensor("b").await.unwrap(), [2; 3]);
+    assert_eq!(reader.read_tensor("a").await.unwrap(), [1; 16]);
+    assert!(reader.read_tensor("c").await.is_err());
+}
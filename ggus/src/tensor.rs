@@ -1,9 +1,5 @@
 use crate::{GGufReadError, GGufReader};
-use std::{
-    alloc::{Layout, alloc, dealloc},
-    ptr::{NonNull, copy_nonoverlapping},
-    slice::from_raw_parts,
-};
+use std::fmt;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(u32)]
@@ -170,6 +166,48 @@ impl GGmlType {
         };
         ans
     }
+
+    /// The name of the type in ggml, such as `Q4_K` and `IQ2_XXS`.
+    #[allow(deprecated)]
+    #[rustfmt::skip]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::F32      => "F32"     ,
+            Self::F16      => "F16"     ,
+            Self::Q4_0     => "Q4_0"    ,
+            Self::Q4_1     => "Q4_1"    ,
+            Self::Q4_2     => "Q4_2"    ,
+            Self::Q4_3     => "Q4_3"    ,
+            Self::Q5_0     => "Q5_0"    ,
+            Self::Q5_1     => "Q5_1"    ,
+            Self::Q8_0     => "Q8_0"    ,
+            Self::Q8_1     => "Q8_1"    ,
+            Self::Q2K      => "Q2_K"    ,
+            Self::Q3K      => "Q3_K"    ,
+            Self::Q4K      => "Q4_K"    ,
+            Self::Q5K      => "Q5_K"    ,
+            Self::Q6K      => "Q6_K"    ,
+            Self::Q8K      => "Q8_K"    ,
+            Self::IQ2XXS   => "IQ2_XXS" ,
+            Self::IQ2XS    => "IQ2_XS"  ,
+            Self::IQ3XXS   => "IQ3_XXS" ,
+            Self::IQ1S     => "IQ1_S"   ,
+            Self::IQ4NL    => "IQ4_NL"  ,
+            Self::IQ3S     => "IQ3_S"   ,
+            Self::IQ2S     => "IQ2_S"   ,
+            Self::IQ4XS    => "IQ4_XS"  ,
+            Self::I8       => "I8"      ,
+            Self::I16      => "I16"     ,
+            Self::I32      => "I32"     ,
+            Self::I64      => "I64"     ,
+            Self::F64      => "F64"     ,
+            Self::IQ1M     => "IQ1_M"   ,
+            Self::BF16     => "BF16"    ,
+            Self::Q4_0_4_4 => "Q4_0_4_4",
+            Self::Q4_0_4_8 => "Q4_0_4_8",
+            Self::Q4_0_8_8 => "Q4_0_8_8",
+        }
+    }
 }
 
 #[repr(transparent)]
@@ -213,43 +251,44 @@ impl<'a> GGufTensorMeta<'a> {
     }
 
     #[inline]
-    pub fn to_info(&self) -> GGufTensorInfo {
+    pub fn to_info(&self) -> GGufTensorInfo<'a> {
         let mut reader = GGufReader::new(self.0);
         let ndim: u32 = reader.skip_str().unwrap().read().unwrap();
-        let layout = Layout::array::<u64>(ndim as _).unwrap();
-        let shape = unsafe {
-            let dst = alloc(layout);
-            copy_nonoverlapping(reader.remaining().as_ptr(), dst, layout.size());
-            NonNull::new_unchecked(dst).cast()
+        let (shape, tail) = reader
+            .remaining()
+            .split_at(ndim as usize * size_of::<u64>());
+        let (shape, []) = shape.as_chunks() else {
+            unreachable!()
         };
-        let ty = reader.skip::<u64>(ndim as _).unwrap().read().unwrap();
+        let mut reader = GGufReader::new(tail);
+        let ty = reader.read().unwrap();
         let offset = reader.read().unwrap();
 
         GGufTensorInfo {
             ty,
-            ndim,
-            shape,
+            shape: GGufTensorShape(shape),
             offset,
         }
     }
 }
 
-pub struct GGufTensorInfo {
+/// Tensor info read in place from the file bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GGufTensorInfo<'a> {
     ty: GGmlType,
-    ndim: u32,
-    shape: NonNull<u64>,
+    shape: GGufTensorShape<'a>,
     offset: u64,
 }
 
-impl GGufTensorInfo {
+impl<'a> GGufTensorInfo<'a> {
     #[inline]
     pub const fn ty(&self) -> GGmlType {
         self.ty
     }
 
     #[inline]
-    pub const fn shape(&self) -> &[u64] {
-        unsafe { from_raw_parts(self.shape.as_ptr(), self.ndim as _) }
+    pub const fn shape(&self) -> GGufTensorShape<'a> {
+        self.shape
     }
 
     #[inline]
@@ -259,14 +298,76 @@ impl GGufTensorInfo {
 
     #[inline]
     pub fn nbytes(&self) -> usize {
-        self.ty.size().elements_to_bytes(self.shape())
+        self.shape.nbytes(self.ty)
+    }
+}
+
+/// Shape of a tensor, stored as unaligned little-endian `u64`s in the file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct GGufTensorShape<'a>(&'a [[u8; size_of::<u64>()]]);
+
+impl<'a> GGufTensorShape<'a> {
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<u64> {
+        self.0.get(i).copied().map(u64::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u64> + Clone + 'a {
+        self.0.iter().copied().map(u64::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+
+    /// Number of elements, the product of all dimensions.
+    #[inline]
+    pub fn nelements(&self) -> u64 {
+        self.iter().product()
+    }
+
+    /// Number of bytes of a tensor of this shape and the given type.
+    pub fn nbytes(&self, ty: GGmlType) -> usize {
+        let size = ty.size();
+        match self.get(0) {
+            None => size.elements_to_bytes(&[]),
+            Some(ne0) => size.elements_to_bytes(&[ne0, self.iter().skip(1).product()]),
+        }
     }
 }
 
-impl Drop for GGufTensorInfo {
-    fn drop(&mut self) {
-        let ptr = self.shape.as_ptr().cast();
-        let layout = Layout::array::<u64>(self.ndim as _).unwrap();
-        unsafe { dealloc(ptr, layout) }
+impl fmt::Debug for GGufTensorShape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
     }
 }
+
+#[test]
+fn test_info() {
+    let mut buf = vec![0u8];
+    crate::GGufWriter::new(&mut buf)
+        .write_tensor_info("a", &[32, 4, 2], GGmlType::Q8_0, 64)
+        .unwrap();
+    // 故意错开对齐
+    let meta = GGufTensorMeta::new(&buf[1..]).unwrap();
+    let info = meta.to_info();
+    assert_eq!(meta.name(), "a");
+    assert_eq!(info.ty(), GGmlType::Q8_0);
+    assert_eq!(info.shape().to_vec(), [32, 4, 2]);
+    assert_eq!(format!("{:?}", info.shape()), "[32, 4, 2]");
+    assert_eq!(info.offset(), 64);
+    assert_eq!(info.nbytes(), 8 * 34);
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+/// A structured tensor name such as `blk.12.attn_q.weight`.
+///
+/// Names are split into an optional prefix before `blk` (`v`, `enc`, `dec`…),
+/// the block index, the component, the legacy expert index and the suffix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GGufTensorName<'a> {
+    pub prefix: &'a str,
+    pub block: Option<usize>,
+    pub component: &'a str,
+    pub expert: Option<usize>,
+    pub suffix: Option<&'a str>,
+}
+
+const BLK: &str = "blk";
+const SUFFIXES: &[&str] = &["weight", "bias"];
+
+/// Standard llama.cpp tensors outside blocks.
+#[rustfmt::skip]
+const GLOBAL: &[&str] = &[
+    "token_embd", "token_embd_norm", "token_types", "position_embd",
+    "output", "output_norm", "rope_freqs", "rope_factors_long", "rope_factors_short",
+    "cls", "cls.output", "conv1d", "dec.output_norm", "enc.output_norm",
+    "mm.0", "mm.2", "v.patch_embd", "v.position_embd", "v.class_embd",
+    "v.pre_ln", "v.post_ln",
+];
+
+/// Standard llama.cpp tensors inside blocks.
+#[rustfmt::skip]
+const BLOCK: &[&str] = &[
+    "attn_norm", "attn_norm_2", "attn_q", "attn_k", "attn_v", "attn_qkv", "attn_output",
+    "attn_q_norm", "attn_k_norm", "attn_rot_embd", "attn_output_norm", "attn_post_norm",
+    "attn_sub_norm", "attn_q_a", "attn_q_b", "attn_kv_a_mqa", "attn_kv_b",
+    "attn_q_a_norm", "attn_kv_a_norm", "attn_rel_b", "cross_attn_q", "cross_attn_k",
+    "cross_attn_v", "cross_attn_o", "cross_attn_norm", "cross_attn_rel_b",
+    "ffn_norm", "ffn_pre_norm", "post_ffw_norm", "ffn_sub_norm", "layer_output_norm",
+    "ffn_gate", "ffn_up", "ffn_down", "ffn_act", "ffn_gate_up",
+    "ffn_gate_inp", "ffn_gate_exps", "ffn_up_exps", "ffn_down_exps", "ffn_gate_up_exps",
+    "ffn_norm_exps", "exp_probs_b",
+    "ffn_gate_inp_shexp", "ffn_gate_shexp", "ffn_up_shexp", "ffn_down_shexp",
+    "ssm_in", "ssm_conv1d", "ssm_x", "ssm_dt", "ssm_a", "ssm_d", "ssm_out",
+    "ln1", "ln2", "attn_out",
+];
+
+impl<'a> GGufTensorName<'a> {
+    /// Parses a tensor name, returns `None` if no component can be found.
+    pub fn parse(name: &'a str) -> Option<Self> {
+        let (prefix, block, body) = split_block(name);
+
+        let (body, suffix) = match body.rsplit_once('.') {
+            Some((body, suffix)) if SUFFIXES.contains(&suffix) => (body, Some(suffix)),
+            _ => (body, None),
+        };
+
+        // 旧格式的专家张量：blk.N.ffn_gate.E.weight
+        let (component, expert) = match body.rsplit_once('.') {
+            Some((component, expert)) if block.is_some() => match parse_index(expert) {
+                Some(expert) => (component, Some(expert)),
+                None => (body, None),
+            },
+            _ => (body, None),
+        };
+
+        if component.is_empty() {
+            return None;
+        }
+        Some(Self {
+            prefix,
+            block,
+            component,
+            expert,
+            suffix,
+        })
+    }
+
+    /// Returns `true` if the component is a standard llama.cpp tensor.
+    pub fn is_standard(&self) -> bool {
+        let table = if self.block.is_some() { BLOCK } else { GLOBAL };
+        table.contains(&self.component)
+    }
+}
+
+fn split_block(name: &str) -> (&str, Option<usize>, &str) {
+    let (prefix, body) = if let Some(body) = name.strip_prefix("blk.") {
+        ("", body)
+    } else if let Some(i) = name.find(".blk.") {
+        (&name[..i], &name[i + ".blk.".len()..])
+    } else {
+        return ("", None, name);
+    };
+    match body.split_once('.') {
+        Some((index, body)) => match parse_index(index) {
+            Some(index) => (prefix, Some(index), body),
+            None => ("", None, name),
+        },
+        None => ("", None, name),
+    }
+}
+
+fn parse_index(s: &str) -> Option<usize> {
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for GGufTensorName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.prefix.is_empty() {
+            write!(f, "{}.", self.prefix)?
+        }
+        if let Some(block) = self.block {
+            write!(f, "{BLK}.{block}.")?
+        }
+        f.write_str(self.component)?;
+        if let Some(expert) = self.expert {
+            write!(f, ".{expert}")?
+        }
+        if let Some(suffix) = self.suffix {
+            write!(f, ".{suffix}")?
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_tensor_name() {
+    let name = GGufTensorName::parse("blk.12.attn_q.weight").unwrap();
+    assert_eq!(name.prefix, "");
+    assert_eq!(name.block, Some(12));
+    assert_eq!(name.component, "attn_q");
+    assert_eq!(name.expert, None);
+    assert_eq!(name.suffix, Some("weight"));
+    assert!(name.is_standard());
+
+    let name = GGufTensorName::parse("blk.3.ffn_gate.7.weight").unwrap();
+    assert_eq!(name.component, "ffn_gate");
+    assert_eq!(name.expert, Some(7));
+
+    let name = GGufTensorName::parse("v.blk.0.attn_out.bias").unwrap();
+    assert_eq!(name.prefix, "v");
+    assert_eq!(name.block, Some(0));
+    assert_eq!(name.component, "attn_out");
+    assert_eq!(name.suffix, Some("bias"));
+
+    let name = GGufTensorName::parse("token_embd.weight").unwrap();
+    assert_eq!(name.block, None);
+    assert_eq!(name.component, "token_embd");
+    assert!(name.is_standard());
+
+    let name = GGufTensorName::parse("resampler.attn.q.weight").unwrap();
+    assert_eq!(name.component, "resampler.attn.q");
+    assert!(!name.is_standard());
+
+    let name = GGufTensorName::parse("blk.x.attn_q").unwrap();
+    assert_eq!(name.block, None);
+    assert_eq!(name.component, "blk.x.attn_q");
+    assert_eq!(name.suffix, None);
+
+    assert!(GGufTensorName::parse(".weight").is_none());
+
+    for name in [
+        "blk.12.attn_q.weight",
+        "blk.3.ffn_gate.7.weight",
+        "v.blk.0.attn_out.bias",
+        "token_embd.weight",
+        "blk.1.ssm_a",
+    ] {
+        assert_eq!(GGufTensorName::parse(name).unwrap().to_string(), name)
+    }
+}
@@ -0,0 +1,301 @@
+use super::{GGufTokenizer, GGufTokenizerError};
+use crate::{GGufMetaError, GGufMetaMapExt};
+use regex::Regex;
+use std::{collections::HashMap, sync::LazyLock};
+
+/// Byte-level BPE, merging the pair with the lowest rank first.
+pub(super) struct Bpe {
+    /// Ranks of merges by the ids of the two parts, merges of unknown tokens are ignored.
+    ranks: HashMap<(u32, u32), usize>,
+    pre: Vec<Regex>,
+    /// Words already in the vocabulary are not merged, as llama3 does.
+    ignore_merges: bool,
+}
+
+impl Bpe {
+    pub fn new<M: GGufMetaMapExt + ?Sized>(
+        meta: &M,
+        index: &HashMap<String, u32>,
+    ) -> Result<Self, GGufTokenizerError> {
+        let mut ranks = HashMap::new();
+        for (i, merge) in meta.tokenizer_ggml_merges()?.enumerate() {
+            let merge = merge.map_err(GGufMetaError::Read)?;
+            // 首字符本身可能是空格
+            let first = merge.chars().next().map_or(0, char::len_utf8);
+            let Some(pos) = merge[first..].find(' ') else {
+                return Err(GGufTokenizerError::InvalidMerge(merge.into()));
+            };
+            let (a, b) = merge.split_at(first + pos);
+            if let (Some(&a), Some(&b)) = (index.get(a), index.get(&b[1..])) {
+                ranks.entry((a, b)).or_insert(i);
+            }
+        }
+
+        let pre = match meta.tokenizer_ggml_pre() {
+            Ok(pre) => pre,
+            Err(GGufMetaError::NotExist) => "default",
+            Err(e) => return Err(e.into()),
+        };
+        let Some(&(_, patterns, ignore_merges)) = PRE_TOKENIZERS
+            .iter()
+            .find(|(names, ..)| names.contains(&pre))
+        else {
+            return Err(GGufTokenizerError::UnsupportedPre(pre.into()));
+        };
+
+        Ok(Self {
+            ranks,
+            pre: patterns.iter().map(|p| compile(p)).collect(),
+            ignore_merges,
+        })
+    }
+
+    pub fn encode(&self, tok: &GGufTokenizer, text: &str, ans: &mut Vec<u32>) {
+        let mut words = vec![text];
+        for regex in &self.pre {
+            words = words.into_iter().flat_map(|w| split(regex, w)).collect()
+        }
+
+        let map = &*BYTES_TO_CHARS;
+        for word in words {
+            let word = word.bytes().map(|b| map[b as usize]).collect::<String>();
+            if self.ignore_merges
+                && let Some(&id) = tok.index.get(&word)
+            {
+                ans.push(id);
+                continue;
+            }
+
+            // 符号是词中的区间和对应的词表 id
+            let mut symbols = word
+                .char_indices()
+                .map(|(i, c)| {
+                    let range = i..i + c.len_utf8();
+                    let id = tok.index.get(&word[range.clone()]).copied();
+                    (range, id)
+                })
+                .collect::<Vec<_>>();
+            loop {
+                let best = symbols
+                    .windows(2)
+                    .enumerate()
+                    .filter_map(|(i, pair)| {
+                        let rank = self.ranks.get(&(pair[0].1?, pair[1].1?))?;
+                        Some((*rank, i))
+                    })
+                    .min();
+                let Some((_, i)) = best else {
+                    break;
+                };
+                let (right, _) = symbols.remove(i + 1);
+                let range = symbols[i].0.start..right.end;
+                symbols[i].1 = tok.index.get(&word[range.clone()]).copied();
+                symbols[i].0 = range
+            }
+
+            for (range, id) in symbols {
+                match id {
+                    Some(id) => ans.push(id),
+                    // 不在词表中的片段逐字符查找
+                    None => {
+                        let mut buf = [0; 4];
+                        for c in word[range].chars() {
+                            tok.lookup_or_unk(c.encode_utf8(&mut buf), ans)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn decode(text: &str, bytes: &mut Vec<u8>) {
+    let map = &*CHARS_TO_BYTES;
+    for c in text.chars() {
+        match map.get(&c) {
+            Some(&b) => bytes.push(b),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+/// Splits a word by the regex, text between matches is kept as words too.
+fn split<'a>(regex: &Regex, text: &'a str) -> Vec<&'a str> {
+    let mut ans = Vec::new();
+    let mut pos = 0;
+    while let Some(captures) = regex.captures_at(text, pos) {
+        let m = captures.get(0).unwrap();
+        if m.is_empty() {
+            break;
+        }
+        let mut end = m.end();
+        // 模拟 `\s+(?!\S)`：后面还有非空白字符时，留下最后一个空白字符
+        if captures.name("ws").is_some() && end < text.len() {
+            let last = m.as_str().chars().next_back().unwrap();
+            if m.len() > last.len_utf8() {
+                end -= last.len_utf8()
+            }
+        }
+        if pos < m.start() {
+            ans.push(&text[pos..m.start()])
+        }
+        ans.push(&text[m.start()..end]);
+        pos = end
+    }
+    if pos < text.len() {
+        ans.push(&text[pos..])
+    }
+    ans
+}
+
+/// The regex crate has no lookahead, `\s+(?!\S)` is emulated in [`split`].
+fn compile(pattern: &str) -> Regex {
+    let pattern = pattern
+        .replace(r"\s+(?!\S)|\s+", r"(?<ws>\s+)")
+        .replace(r"\s+(?!\S)", r"(?<ws>\s+)");
+    Regex::new(&pattern).unwrap()
+}
+
+const GPT2: &str = r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+const LLAMA3: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+const QWEN2: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+const GPT4O: &str = r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenizers by `tokenizer.ggml.pre`, as in llama.cpp.
+type PreTokenizer = (&'static [&'static str], &'static [&'static str], bool);
+
+#[rustfmt::skip]
+const PRE_TOKENIZERS: &[PreTokenizer] = &[
+    (&["default"],
+     &[r"[\p{P}\$\+<=>\^~\|]+", r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)", r"\p{N}+", r"[0-9][0-9][0-9]"],
+     false),
+    (&["gpt-2", "phi-2", "jina-es", "jina-de", "jina-v1-en", "jina-v2-es", "jina-v2-de", "jina-v2-code", "roberta-bpe", "mpt", "olmo", "jais"],
+     &[GPT2],
+     false),
+    (&["llama3", "llama-v3", "llama-bpe", "falcon3"],
+     &[LLAMA3],
+     true),
+    (&["dbrx", "smaug-bpe"],
+     &[LLAMA3],
+     false),
+    (&["qwen2", "deepseek-r1-qwen", "stablelm2", "megrez"],
+     &[QWEN2],
+     false),
+    (&["refact", "command-r", "starcoder", "smollm", "codeshell", "exaone", "minerva-7b"],
+     &[r"\p{N}", GPT2],
+     false),
+    (&["falcon"],
+     &[r"[\p{P}\$\+<=>\^~\|`]+", GPT2, r"[0-9][0-9][0-9]"],
+     false),
+    (&["deepseek-coder"],
+     &[r"[\r\n]", r"\s?\p{L}+", r"\s?\p{P}+", r"[一-龥ࠀ-一가-퟿]+", r"\p{N}"],
+     false),
+    (&["deepseek-v3"],
+     &[r"\p{N}{1,3}", r"[一-龥぀-ゟ゠-ヿ]+", r##"[!"#$%&'()*+,\-./:;<=>?@\[\\\]^_`{|}~][A-Za-z]+|[^\r\n\p{L}\p{P}\p{S}]?[\p{L}\p{M}]+| ?[\p{P}\p{S}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+"##],
+     false),
+    (&["gpt-4o"],
+     &[GPT4O],
+     false),
+];
+
+/// The byte to unicode mapping of GPT-2, so that every byte is a printable character.
+static BYTES_TO_CHARS: LazyLock<[char; 256]> = LazyLock::new(|| {
+    let mut ans = ['\0'; 256];
+    let mut n = 0;
+    for b in 0..=255u8 {
+        ans[b as usize] = if matches!(b, b'!'..=b'~' | 0xa1..=0xac | 0xae..=0xff) {
+            b as char
+        } else {
+            n += 1;
+            char::from_u32(255 + n).unwrap()
+        }
+    }
+    ans
+});
+
+static CHARS_TO_BYTES: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
+    BYTES_TO_CHARS
+        .iter()
+        .enumerate()
+        .map(|(b, &c)| (c, b as u8))
+        .collect()
+});
+
+#[test]
+fn test_bpe() {
+    use super::test_meta;
+    use crate::{GGufMetaDataValueType as Ty, GGufMetaValue as V};
+
+    assert_eq!(BYTES_TO_CHARS[b' ' as usize], 'Ġ');
+    assert_eq!(BYTES_TO_CHARS[b'\n' as usize], 'Ċ');
+
+    for (_, patterns, _) in PRE_TOKENIZERS {
+        patterns.iter().for_each(|p| drop(compile(p)))
+    }
+
+    let regex = compile(GPT2);
+    assert_eq!(
+        split(&regex, "Hello  world's 123!\n"),
+        ["Hello", " ", " world", "'s", " 123", "!", "\n"]
+    );
+    let regex = compile(LLAMA3);
+    assert_eq!(
+        split(&regex, "I'M 12345  ok"),
+        ["I", "'M", " ", "123", "45", " ", " ok"]
+    );
+
+    let tokens = [
+        "<|endoftext|>",
+        "H",
+        "e",
+        "l",
+        "o",
+        "Ġ",
+        "w",
+        "r",
+        "d",
+        "!",
+        "He",
+        "ll",
+        "Hell",
+        "Hello",
+        "Ġw",
+        "or",
+        "Ġwor",
+        "ld",
+        "Ġworld",
+    ];
+    let mut types = vec![1; tokens.len()];
+    types[0] = 3;
+    let mut meta = test_meta("gpt2", &tokens, &types);
+    let merges = [
+        "H e", "l l", "He ll", "Hell o", "Ġ w", "o r", "Ġw or", "l d", "Ġwor ld",
+    ];
+    let merges = V::Array(Ty::String, merges.iter().map(|&m| m.into()).collect());
+    meta.insert_meta(
+        "tokenizer.ggml.merges",
+        Ty::Array,
+        merges.to_bytes().unwrap(),
+    );
+    meta.insert_meta(
+        "tokenizer.ggml.pre",
+        Ty::String,
+        V::from("gpt-2").to_bytes().unwrap(),
+    );
+
+    let tok = GGufTokenizer::new(&meta).unwrap();
+    let ids = tok.encode("Hello world!<|endoftext|>", true, true);
+    assert_eq!(ids, [13, 18, 9, 0]);
+    assert_eq!(tok.decode(&ids, false), "Hello world!");
+    assert_eq!(tok.decode(&ids, true), "Hello world!<|endoftext|>");
+
+    meta.insert_meta(
+        "tokenizer.ggml.pre",
+        Ty::String,
+        V::from("unknown").to_bytes().unwrap(),
+    );
+    assert!(matches!(
+        GGufTokenizer::new(&meta),
+        Err(GGufTokenizerError::UnsupportedPre(_))
+    ));
+}
@@ -0,0 +1,315 @@
+//! Tokenizers built from the `tokenizer.ggml.*` meta kvs, following llama.cpp.
+
+mod bpe;
+mod spm;
+mod wpm;
+
+use crate::{GGmlTokenType, GGufMetaError, GGufMetaMapExt, metadata::optional};
+use std::{collections::HashMap, error::Error, fmt};
+
+/// An encoder/decoder for the vocabulary stored in a gguf file.
+///
+/// Supports the `llama` (SentencePiece), `gpt2` (byte-level BPE) and `bert` (WordPiece) models.
+pub struct GGufTokenizer {
+    vocab: Vec<String>,
+    types: Vec<GGmlTokenType>,
+    index: HashMap<String, u32>,
+    /// Control and user-defined tokens, longest first.
+    specials: Vec<u32>,
+    bos: Option<u32>,
+    eos: Option<u32>,
+    unk: Option<u32>,
+    add_bos: bool,
+    add_eos: bool,
+    model: Model,
+}
+
+enum Model {
+    Spm(spm::Spm),
+    Bpe(bpe::Bpe),
+    Wpm,
+}
+
+#[derive(Debug)]
+pub enum GGufTokenizerError {
+    Meta(GGufMetaError),
+    /// `tokenizer.ggml.model` is not one of the supported models.
+    UnsupportedModel(String),
+    /// `tokenizer.ggml.pre` names an unknown pre-tokenizer.
+    UnsupportedPre(String),
+    InvalidMerge(String),
+    /// The array of the key does not have one element per token.
+    LengthMismatch(&'static str),
+}
+
+impl fmt::Display for GGufTokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Meta(e) => write!(f, "invalid tokenizer metadata: {e:?}"),
+            Self::UnsupportedModel(model) => write!(f, "unsupported tokenizer model: {model}"),
+            Self::UnsupportedPre(pre) => write!(f, "unsupported pre-tokenizer: {pre}"),
+            Self::InvalidMerge(merge) => write!(f, "invalid merge: {merge}"),
+            Self::LengthMismatch(key) => write!(f, "{key} does not have one element per token"),
+        }
+    }
+}
+
+impl Error for GGufTokenizerError {}
+
+impl From<GGufMetaError> for GGufTokenizerError {
+    #[inline]
+    fn from(e: GGufMetaError) -> Self {
+        Self::Meta(e)
+    }
+}
+
+/// A piece of the input, either raw text or an already recognized special token.
+enum Fragment<'a> {
+    Text(&'a str),
+    Token(u32),
+}
+
+impl GGufTokenizer {
+    /// Builds the tokenizer from the meta kvs of a gguf file.
+    pub fn new<M: GGufMetaMapExt + ?Sized>(meta: &M) -> Result<Self, GGufTokenizerError> {
+        let vocab = meta
+            .tokenizer_ggml_tokens()?
+            .map(|t| t.map(String::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(GGufMetaError::Read)?;
+
+        let types = match meta.tokenizer_ggml_token_type() {
+            Ok(types) => types.collect::<Result<Vec<_>, _>>()?,
+            Err(GGufMetaError::NotExist) => vec![GGmlTokenType::Normal; vocab.len()],
+            Err(e) => return Err(e.into()),
+        };
+        if types.len() != vocab.len() {
+            return Err(GGufTokenizerError::LengthMismatch(
+                "tokenizer.ggml.token_type",
+            ));
+        }
+
+        let index = vocab
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.clone(), i as u32))
+            .collect::<HashMap<_, _>>();
+
+        let mut specials = (0..vocab.len() as u32)
+            .filter(|&i| {
+                matches!(
+                    types[i as usize],
+                    GGmlTokenType::Control | GGmlTokenType::User
+                ) && !vocab[i as usize].is_empty()
+            })
+            .collect::<Vec<_>>();
+        specials.sort_by_key(|&i| std::cmp::Reverse(vocab[i as usize].len()));
+
+        let name = meta.tokenizer_ggml_model()?;
+        let (model, add_bos, add_eos) = match name {
+            "llama" => (Model::Spm(spm::Spm::new(meta, &vocab)?), true, false),
+            "gpt2" => (Model::Bpe(bpe::Bpe::new(meta, &index)?), false, false),
+            "bert" => (Model::Wpm, true, true),
+            _ => return Err(GGufTokenizerError::UnsupportedModel(name.into())),
+        };
+
+        let (bos, eos) = match model {
+            Model::Wpm => (
+                optional(meta.tokenizer_ggml_cls_token_id())?
+                    .or(optional(meta.tokenizer_ggml_bos_token_id())?),
+                optional(meta.tokenizer_ggml_separator_token_id())?
+                    .or(optional(meta.tokenizer_ggml_eos_token_id())?),
+            ),
+            _ => (
+                optional(meta.tokenizer_ggml_bos_token_id())?,
+                optional(meta.tokenizer_ggml_eos_token_id())?,
+            ),
+        };
+
+        Ok(Self {
+            bos,
+            eos,
+            unk: optional(meta.tokenizer_ggml_unknown_token_id())?,
+            add_bos: optional(meta.tokenizer_ggml_add_bos_token())?.unwrap_or(add_bos),
+            add_eos: optional(meta.tokenizer_ggml_add_eos_token())?.unwrap_or(add_eos),
+            vocab,
+            types,
+            index,
+            specials,
+            model,
+        })
+    }
+
+    #[inline]
+    pub fn vocab_size(&self) -> usize {
+        self.vocab.len()
+    }
+
+    /// Text of the token in the vocabulary, as stored in the file.
+    #[inline]
+    pub fn token(&self, id: u32) -> Option<&str> {
+        self.vocab.get(id as usize).map(String::as_str)
+    }
+
+    #[inline]
+    pub fn token_id(&self, text: &str) -> Option<u32> {
+        self.index.get(text).copied()
+    }
+
+    #[inline]
+    pub fn token_type(&self, id: u32) -> Option<GGmlTokenType> {
+        self.types.get(id as usize).copied()
+    }
+
+    #[inline]
+    pub const fn bos(&self) -> Option<u32> {
+        self.bos
+    }
+
+    #[inline]
+    pub const fn eos(&self) -> Option<u32> {
+        self.eos
+    }
+
+    /// Encodes `text` into token ids.
+    ///
+    /// With `add_special`, bos and eos are added as configured by the model.
+    /// With `parse_special`, control tokens in the text are recognized, user-defined tokens always are.
+    pub fn encode(&self, text: &str, add_special: bool, parse_special: bool) -> Vec<u32> {
+        let mut ans = Vec::new();
+        if add_special
+            && self.add_bos
+            && let Some(bos) = self.bos
+        {
+            ans.push(bos)
+        }
+
+        let mut is_first = true;
+        for fragment in self.partition(text, parse_special) {
+            match fragment {
+                Fragment::Token(id) => ans.push(id),
+                Fragment::Text(text) => match &self.model {
+                    Model::Spm(spm) => spm.encode(self, text, is_first, &mut ans),
+                    Model::Bpe(bpe) => bpe.encode(self, text, &mut ans),
+                    Model::Wpm => wpm::encode(self, text, &mut ans),
+                },
+            }
+            is_first = false
+        }
+
+        if add_special
+            && self.add_eos
+            && let Some(eos) = self.eos
+        {
+            ans.push(eos)
+        }
+        ans
+    }
+
+    /// Decodes token ids into text, control tokens are kept only if `special` is set.
+    pub fn decode(&self, tokens: &[u32], special: bool) -> String {
+        let mut bytes = Vec::new();
+        for &id in tokens {
+            let Some(text) = self.token(id) else {
+                continue;
+            };
+            match self.types[id as usize] {
+                GGmlTokenType::Control if !special => {}
+                GGmlTokenType::Control | GGmlTokenType::User => {
+                    bytes.extend_from_slice(text.as_bytes())
+                }
+                _ => match &self.model {
+                    Model::Spm(_) => spm::decode(text, &mut bytes),
+                    Model::Bpe(_) => bpe::decode(text, &mut bytes),
+                    Model::Wpm => wpm::decode(text, &mut bytes),
+                },
+            }
+        }
+        // 编码时添加的空格前缀在解码时去掉
+        let strip = match &self.model {
+            Model::Spm(spm) => spm.add_space_prefix,
+            Model::Bpe(_) => false,
+            Model::Wpm => true,
+        };
+        let bytes = match bytes.strip_prefix(b" ") {
+            Some(rest) if strip => rest,
+            _ => &bytes,
+        };
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+
+    /// Splits out special tokens from the text.
+    fn partition<'a>(&self, text: &'a str, parse_special: bool) -> Vec<Fragment<'a>> {
+        let mut ans = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let found = self.specials.iter().find(|&&id| {
+                (parse_special || self.types[id as usize] == GGmlTokenType::User)
+                    && text[i..].starts_with(&self.vocab[id as usize])
+            });
+            match found {
+                Some(&id) => {
+                    if start < i {
+                        ans.push(Fragment::Text(&text[start..i]))
+                    }
+                    ans.push(Fragment::Token(id));
+                    i += self.vocab[id as usize].len();
+                    start = i
+                }
+                None => i += text[i..].chars().next().unwrap().len_utf8(),
+            }
+        }
+        if start < text.len() {
+            ans.push(Fragment::Text(&text[start..]))
+        }
+        ans
+    }
+
+    /// Looks up a piece, falling back to the unknown token.
+    fn lookup_or_unk(&self, piece: &str, ans: &mut Vec<u32>) {
+        match self.index.get(piece) {
+            Some(&id) => ans.push(id),
+            None => ans.extend(self.unk),
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_meta(model: &str, tokens: &[&str], types: &[i32]) -> crate::GGufModel<'static> {
+    use crate::{GGufMetaDataValueType as Ty, GGufMetaValue as V, GGufModel};
+
+    let mut meta = GGufModel::default();
+    meta.insert_meta(
+        "tokenizer.ggml.model",
+        Ty::String,
+        V::from(model).to_bytes().unwrap(),
+    );
+    let tokens = V::Array(Ty::String, tokens.iter().map(|&t| t.into()).collect());
+    meta.insert_meta(
+        "tokenizer.ggml.tokens",
+        Ty::Array,
+        tokens.to_bytes().unwrap(),
+    );
+    let types = V::Array(Ty::I32, types.iter().map(|&t| t.into()).collect());
+    meta.insert_meta(
+        "tokenizer.ggml.token_type",
+        Ty::Array,
+        types.to_bytes().unwrap(),
+    );
+    meta
+}
+
+#[test]
+fn test_error() {
+    let meta = test_meta("rwkv", &["a"], &[1]);
+    let e: Box<dyn Error> = GGufTokenizer::new(&meta).err().unwrap().into();
+    assert_eq!(e.to_string(), "unsupported tokenizer model: rwkv");
+
+    let meta = test_meta("gpt2", &["a", "b"], &[1]);
+    let e = GGufTokenizer::new(&meta).err().unwrap();
+    assert_eq!(
+        e.to_string(),
+        "tokenizer.ggml.token_type does not have one element per token"
+    );
+}
@@ -0,0 +1,198 @@
+use super::{GGufTokenizer, GGufTokenizerError, optional};
+use crate::{GGufMetaError, GGufMetaMapExt};
+use std::{cmp::Ordering, collections::BinaryHeap, ops::Range};
+
+/// SentencePiece, merging the pair with the highest score first.
+pub(super) struct Spm {
+    scores: Vec<f32>,
+    pub add_space_prefix: bool,
+    /// `<0xXX>` tokens for byte fallback.
+    bytes: Vec<Option<u32>>,
+}
+
+const SPACE: char = '▁';
+
+impl Spm {
+    pub fn new<M: GGufMetaMapExt + ?Sized>(
+        meta: &M,
+        vocab: &[String],
+    ) -> Result<Self, GGufTokenizerError> {
+        let scores = match meta.tokenizer_ggml_scores() {
+            Ok(scores) => scores
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(GGufMetaError::Read)?,
+            Err(GGufMetaError::NotExist) => vec![0.; vocab.len()],
+            Err(e) => return Err(e.into()),
+        };
+        if scores.len() != vocab.len() {
+            return Err(GGufTokenizerError::LengthMismatch("tokenizer.ggml.scores"));
+        }
+        Ok(Self {
+            scores,
+            add_space_prefix: optional(meta.tokenizer_ggml_add_space_prefix())?.unwrap_or(true),
+            bytes: meta.tokenizer_ggml_byte_tokens()?.to_vec(),
+        })
+    }
+
+    pub fn encode(&self, tok: &GGufTokenizer, text: &str, is_first: bool, ans: &mut Vec<u32>) {
+        let mut escaped = String::with_capacity(text.len() + 3);
+        if self.add_space_prefix && is_first {
+            escaped.push(SPACE)
+        }
+        escaped.extend(text.chars().map(|c| if c == ' ' { SPACE } else { c }));
+        let text = &*escaped;
+
+        // 初始按字符切分，以双向链表连接
+        let mut symbols = text
+            .char_indices()
+            .map(|(i, c)| i..i + c.len_utf8())
+            .collect::<Vec<_>>();
+        let n = symbols.len();
+        let mut prev = (0..n).map(|i| i.checked_sub(1)).collect::<Vec<_>>();
+        let mut next = (0..n)
+            .map(|i| Some(i + 1).filter(|&j| j < n))
+            .collect::<Vec<_>>();
+
+        let mut queue = BinaryHeap::new();
+        let try_add =
+            |queue: &mut BinaryHeap<Bigram>, symbols: &[Range<usize>], l: usize, r: usize| {
+                let range = symbols[l].start..symbols[r].end;
+                if let Some(&id) = tok.index.get(&text[range.clone()]) {
+                    queue.push(Bigram {
+                        score: self.scores[id as usize],
+                        left: l,
+                        right: r,
+                        len: range.len(),
+                    })
+                }
+            };
+        for i in 1..n {
+            try_add(&mut queue, &symbols, i - 1, i)
+        }
+
+        while let Some(Bigram {
+            left, right, len, ..
+        }) = queue.pop()
+        {
+            // 跳过已失效的二元组
+            let (l, r) = (&symbols[left], &symbols[right]);
+            if l.is_empty() || r.is_empty() || l.len() + r.len() != len {
+                continue;
+            }
+            symbols[left].end = symbols[right].end;
+            symbols[right] = symbols[right].end..symbols[right].end;
+            next[left] = next[right];
+            if let Some(n) = next[left] {
+                prev[n] = Some(left)
+            }
+            if let Some(p) = prev[left] {
+                try_add(&mut queue, &symbols, p, left)
+            }
+            if let Some(n) = next[left] {
+                try_add(&mut queue, &symbols, left, n)
+            }
+        }
+
+        let mut i = Some(0).filter(|_| n > 0);
+        while let Some(j) = i {
+            let piece = &text[symbols[j].clone()];
+            match tok.index.get(piece) {
+                Some(&id) => ans.push(id),
+                // 不在词表中的字符回退到字节
+                None => {
+                    for &b in piece.as_bytes() {
+                        match self.bytes[b as usize] {
+                            Some(id) => ans.push(id),
+                            None => ans.extend(tok.unk),
+                        }
+                    }
+                }
+            }
+            i = next[j]
+        }
+    }
+}
+
+pub(super) fn decode(text: &str, bytes: &mut Vec<u8>) {
+    if let Some(hex) = text.strip_prefix("<0x").and_then(|s| s.strip_suffix('>'))
+        && hex.len() == 2
+        && let Ok(b) = u8::from_str_radix(hex, 16)
+    {
+        bytes.push(b)
+    } else {
+        let mut buf = [0; 4];
+        for c in text.chars() {
+            let c = if c == SPACE { ' ' } else { c };
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes())
+        }
+    }
+}
+
+struct Bigram {
+    score: f32,
+    left: usize,
+    right: usize,
+    len: usize,
+}
+
+impl PartialEq for Bigram {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Bigram {}
+
+impl PartialOrd for Bigram {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Bigram {
+    /// Higher score first, then the leftmost.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.left.cmp(&self.left))
+    }
+}
+
+#[test]
+fn test_spm() {
+    use super::test_meta;
+    use crate::{GGufMetaDataValueType as Ty, GGufMetaValue as V};
+
+    let tokens = [
+        "<unk>", "<s>", "</s>", "<0x21>", "▁", "h", "e", "l", "o", "▁h", "ll", "▁he", "llo",
+        "▁hello", "w", "r", "d", "▁w", "or", "▁wor", "ld", "▁world",
+    ];
+    let mut types = vec![1; tokens.len()];
+    types[..3].copy_from_slice(&[2, 3, 3]);
+    types[3] = 6;
+    let mut meta = test_meta("llama", &tokens, &types);
+    let scores = V::Array(
+        Ty::F32,
+        (0..tokens.len()).map(|i| V::F32(i as f32)).collect(),
+    );
+    meta.insert_meta(
+        "tokenizer.ggml.scores",
+        Ty::Array,
+        scores.to_bytes().unwrap(),
+    );
+    for (k, v) in [
+        ("tokenizer.ggml.unknown_token_id", 0u32),
+        ("tokenizer.ggml.bos_token_id", 1),
+        ("tokenizer.ggml.eos_token_id", 2),
+    ] {
+        meta.insert_meta(k, Ty::U32, V::U32(v).to_bytes().unwrap());
+    }
+
+    let tok = GGufTokenizer::new(&meta).unwrap();
+    let ids = tok.encode("hello world!", true, false);
+    assert_eq!(ids, [1, 13, 21, 3]);
+    assert_eq!(tok.decode(&ids, false), "hello world!");
+    assert_eq!(tok.decode(&ids, true), "<s> hello world!");
+    assert_eq!(tok.encode("hello</s>", false, true), [13, 2]);
+    assert_eq!(tok.encode("hello</s>", false, false), [13, 0, 0, 0, 0]);
+}
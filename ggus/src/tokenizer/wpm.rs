@@ -0,0 +1,110 @@
+use super::GGufTokenizer;
+
+/// Word starts are marked with `▁` in the vocabulary, continuations are not marked.
+const SPACE: char = '▁';
+
+/// WordPiece, greedily matching the longest piece from the start of each word.
+///
+/// Text is lowercased, but unlike BERT accents are not stripped.
+pub(super) fn encode(tok: &GGufTokenizer, text: &str, ans: &mut Vec<u32>) {
+    for word in split_words(text) {
+        let word = format!("{SPACE}{word}");
+        let start = ans.len();
+        let mut i = 0;
+        while i < word.len() {
+            let found = word[i..]
+                .char_indices()
+                .map(|(j, c)| i + j + c.len_utf8())
+                .rev()
+                .find_map(|end| tok.index.get(&word[i..end]).map(|&id| (id, end)));
+            match found {
+                Some((id, end)) => {
+                    ans.push(id);
+                    i = end
+                }
+                // 任何位置无法匹配时整个词视作未知
+                None => {
+                    ans.truncate(start);
+                    ans.extend(tok.unk);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn decode(text: &str, bytes: &mut Vec<u8>) {
+    let mut buf = [0; 4];
+    for c in text.chars() {
+        let c = if c == SPACE { ' ' } else { c };
+        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes())
+    }
+}
+
+/// Lowercases the text and splits it into words, punctuation and CJK characters stand alone.
+fn split_words(text: &str) -> Vec<String> {
+    let mut ans = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        // 丢弃控制字符和替换字符
+        if (c.is_control() && !c.is_whitespace()) || c == '\u{fffd}' {
+            continue;
+        }
+        if c.is_whitespace() {
+            ans.extend((!word.is_empty()).then(|| std::mem::take(&mut word)))
+        } else if is_punctuation(c) || is_cjk(c) {
+            ans.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            ans.push(c.into())
+        } else {
+            word.extend(c.to_lowercase())
+        }
+    }
+    ans.extend((!word.is_empty()).then_some(word));
+    ans
+}
+
+#[inline]
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || !(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+}
+
+#[inline]
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x4e00..=0x9fff
+            | 0x3400..=0x4dbf
+            | 0x20000..=0x2a6df
+            | 0x2a700..=0x2b73f
+            | 0x2b740..=0x2b81f
+            | 0x2b820..=0x2ceaf
+            | 0xf900..=0xfaff
+            | 0x2f800..=0x2fa1f
+    )
+}
+
+#[test]
+fn test_wpm() {
+    use super::test_meta;
+    use crate::{GGufMetaDataValueType as Ty, GGufMetaValue as V};
+
+    assert_eq!(split_words("Hello, 世界!"), ["hello", ",", "世", "界", "!"]);
+
+    let tokens = [
+        "[UNK]", "[CLS]", "[SEP]", "▁hello", "▁play", "ing", "▁,", "▁!",
+    ];
+    let types = [2, 3, 3, 1, 1, 1, 1, 1];
+    let mut meta = test_meta("bert", &tokens, &types);
+    for (k, v) in [
+        ("tokenizer.ggml.unknown_token_id", 0u32),
+        ("tokenizer.ggml.cls_token_id", 1),
+        ("tokenizer.ggml.seperator_token_id", 2),
+    ] {
+        meta.insert_meta(k, Ty::U32, V::U32(v).to_bytes().unwrap());
+    }
+
+    let tok = GGufTokenizer::new(&meta).unwrap();
+    let ids = tok.encode("Hello, playing xyz!", true, false);
+    assert_eq!(ids, [1, 3, 6, 4, 5, 0, 7, 2]);
+    assert_eq!(tok.decode(&ids, false), "hello , playing[UNK] !");
+}
@@ -0,0 +1,308 @@
+use crate::{GGmlType, GGuf, GGufTensorInfo, GGufTensorShape};
+use ggml_quants::{DataBlock, QuantExt, f16};
+use std::{error::Error, fmt, ptr::copy_nonoverlapping};
+
+/// A tensor in a parsed gguf file, with its data borrowed from the file.
+pub struct GGufTensorView<'a> {
+    name: &'a str,
+    info: GGufTensorInfo<'a>,
+    data: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GGufTensorError {
+    /// The data of the tensor is not present in the file.
+    MissingData,
+    /// The requested block type does not match the tensor type.
+    TypeMismatch(GGmlType),
+    /// The data is not aligned to the requested block type.
+    Misaligned,
+    /// Dequantization of the tensor type is not supported.
+    Unsupported(GGmlType),
+    /// Rows of the tensor are not made of whole blocks.
+    RowNotAligned,
+    /// The requested row does not exist.
+    RowOutOfRange(usize),
+}
+
+impl fmt::Display for GGufTensorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingData => f.write_str("tensor data is missing"),
+            Self::TypeMismatch(ty) => write!(f, "tensor type mismatch: {ty:?}"),
+            Self::Misaligned => f.write_str("tensor data is misaligned"),
+            Self::Unsupported(ty) => write!(f, "dequantization of {ty:?} is not supported"),
+            Self::RowNotAligned => f.write_str("tensor rows are not aligned to blocks"),
+            Self::RowOutOfRange(row) => write!(f, "row {row} out of range"),
+        }
+    }
+}
+
+impl Error for GGufTensorError {}
+
+macro_rules! dequantize {
+    ($ty:expr, $data:expr => $t:ty) => {{
+        use ggml_quants::*;
+        let ans = match $ty {
+            GGmlType::F16 => dequantize::<f16, $t, 1>($data),
+            GGmlType::BF16 => dequantize::<bf16, $t, 1>($data),
+            GGmlType::Q4_0 => dequantize::<Q4_0, $t, 32>($data),
+            GGmlType::Q4_1 => dequantize::<Q4_1, $t, 32>($data),
+            GGmlType::Q5_0 => dequantize::<Q5_0, $t, 32>($data),
+            GGmlType::Q5_1 => dequantize::<Q5_1, $t, 32>($data),
+            GGmlType::Q8_0 => dequantize::<Q8_0, $t, 32>($data),
+            GGmlType::Q8_1 => dequantize::<Q8_1, $t, 32>($data),
+            GGmlType::Q8K => dequantize::<Q8K, $t, 256>($data),
+            ty => return Err(GGufTensorError::Unsupported(ty)),
+        };
+        Ok(ans)
+    }};
+}
+
+impl<'a> GGuf<'a> {
+    /// Returns a view of the tensor named `name`, or `None` if it does not exist.
+    pub fn tensor(&self, name: &str) -> Option<GGufTensorView<'a>> {
+        let (&name, meta) = self.tensors.get_key_value(name)?;
+        Some(GGufTensorView {
+            name,
+            info: meta.to_info(),
+            data: self.tensor_data(name),
+        })
+    }
+}
+
+impl<'a> GGufTensorView<'a> {
+    #[inline]
+    pub const fn name(&self) -> &'a str {
+        self.name
+    }
+
+    #[inline]
+    pub const fn ty(&self) -> GGmlType {
+        self.info.ty()
+    }
+
+    #[inline]
+    pub const fn shape(&self) -> GGufTensorShape<'a> {
+        self.info.shape()
+    }
+
+    #[inline]
+    pub fn nelements(&self) -> usize {
+        self.shape().nelements() as _
+    }
+
+    #[inline]
+    pub fn nbytes(&self) -> usize {
+        self.info.nbytes()
+    }
+
+    /// Raw bytes of the tensor, `None` for tensors of a skeleton file.
+    #[inline]
+    pub const fn data(&self) -> Option<&'a [u8]> {
+        self.data
+    }
+
+    /// Reinterprets the data as a slice of blocks, such as `&[Q4_0]` or `&[f32]`.
+    pub fn blocks<T: DataBlock>(&self) -> Result<&'a [T], GGufTensorError> {
+        let ty = self.ty();
+        if !block_match::<T>(ty) {
+            return Err(GGufTensorError::TypeMismatch(ty));
+        }
+        let data = self.data.ok_or(GGufTensorError::MissingData)?;
+        match unsafe { data.align_to() } {
+            ([], blocks, []) => Ok(blocks),
+            _ => Err(GGufTensorError::Misaligned),
+        }
+    }
+
+    /// Dequantizes the tensor into `f32`s.
+    pub fn to_f32(&self) -> Result<Vec<f32>, GGufTensorError> {
+        let data = self.data.ok_or(GGufTensorError::MissingData)?;
+        dequantize_f32(self.ty(), data)
+    }
+
+    /// Dequantizes the tensor into `f16`s.
+    pub fn to_f16(&self) -> Result<Vec<f16>, GGufTensorError> {
+        let data = self.data.ok_or(GGufTensorError::MissingData)?;
+        dequantize_f16(self.ty(), data)
+    }
+
+    /// Number of rows, the product of all dimensions except the first one.
+    #[inline]
+    pub fn nrows(&self) -> usize {
+        self.shape().iter().skip(1).product::<u64>() as _
+    }
+
+    /// Dequantizes the selected rows into `f32`s, concatenated in the given order.
+    ///
+    /// Only the bytes of the selected rows are read, useful for embedding lookups.
+    pub fn rows_to_f32(
+        &self,
+        rows: impl IntoIterator<Item = usize>,
+    ) -> Result<Vec<f32>, GGufTensorError> {
+        self.map_rows(rows, dequantize_f32)
+    }
+
+    /// Dequantizes the selected rows into `f16`s, concatenated in the given order.
+    pub fn rows_to_f16(
+        &self,
+        rows: impl IntoIterator<Item = usize>,
+    ) -> Result<Vec<f16>, GGufTensorError> {
+        self.map_rows(rows, dequantize_f16)
+    }
+
+    fn map_rows<T>(
+        &self,
+        rows: impl IntoIterator<Item = usize>,
+        f: impl Fn(GGmlType, &[u8]) -> Result<Vec<T>, GGufTensorError>,
+    ) -> Result<Vec<T>, GGufTensorError> {
+        let ty = self.ty();
+        let data = self.data.ok_or(GGufTensorError::MissingData)?;
+        let Some(ne0) = self.shape().get(0) else {
+            return Err(GGufTensorError::RowNotAligned);
+        };
+        // 每行必须由整数个块组成
+        let size = ty.size();
+        if ne0 % size.block_size as u64 != 0 {
+            return Err(GGufTensorError::RowNotAligned);
+        }
+        let row_size = size.elements_to_bytes(&[ne0]);
+
+        let nrows = self.nrows();
+        let mut ans = Vec::new();
+        for row in rows {
+            if row >= nrows {
+                return Err(GGufTensorError::RowOutOfRange(row));
+            }
+            ans.extend(f(ty, &data[row * row_size..][..row_size])?)
+        }
+        Ok(ans)
+    }
+}
+
+fn dequantize_f32(ty: GGmlType, data: &[u8]) -> Result<Vec<f32>, GGufTensorError> {
+    match ty {
+        GGmlType::F32 => Ok(copy_blocks(data)),
+        ty => dequantize!(ty, data => f32),
+    }
+}
+
+fn dequantize_f16(ty: GGmlType, data: &[u8]) -> Result<Vec<f16>, GGufTensorError> {
+    match ty {
+        GGmlType::F32 => Ok(copy_blocks::<f32>(data)
+            .into_iter()
+            .map(f16::from_f32)
+            .collect()),
+        GGmlType::F16 => Ok(copy_blocks(data)),
+        ty => dequantize!(ty, data => f16),
+    }
+}
+
+#[cfg(feature = "types")]
+#[inline]
+fn block_match<T: DataBlock>(ty: GGmlType) -> bool {
+    T::ID == ty.to_digit_layout()
+}
+
+#[cfg(not(feature = "types"))]
+#[inline]
+fn block_match<T: DataBlock>(ty: GGmlType) -> bool {
+    let size = ty.size();
+    size.block_size as usize == T::COUNT && size.type_size as usize == size_of::<T>()
+}
+
+/// Copies bytes into a properly aligned vector of blocks.
+fn copy_blocks<T: DataBlock>(data: &[u8]) -> Vec<T> {
+    let len = data.len() / size_of::<T>();
+    let mut ans = Vec::<T>::with_capacity(len);
+    unsafe {
+        copy_nonoverlapping(data.as_ptr(), ans.as_mut_ptr().cast(), len * size_of::<T>());
+        ans.set_len(len)
+    }
+    ans
+}
+
+fn dequantize<Blk, T, const N: usize>(data: &[u8]) -> Vec<T>
+where
+    Blk: QuantExt<T, N> + DataBlock,
+    T: DataBlock + Clone,
+{
+    let copied;
+    let src = match unsafe { data.align_to::<Blk>() } {
+        ([], blocks, []) => blocks,
+        _ => {
+            copied = copy_blocks::<Blk>(data);
+            &copied
+        }
+    };
+    let mut ans = vec![T::ZEROS; src.len() * N];
+    Blk::dequantize_slice(&mut ans, src).unwrap();
+    ans
+}
+
+#[test]
+fn test_view() {
+    use crate::{GGufModel, GGufTensorData};
+    use ggml_quants::{Q8_0, Quantize};
+
+    let values = (0..64).map(|i| i as f32 / 8.).collect::<Vec<_>>();
+    let q8 = [0, 1].map(|i| Q8_0::quantize(values[i * 32..][..32].try_into().unwrap()));
+    let q8_bytes =
+        unsafe { std::slice::from_raw_parts(q8.as_ptr().cast::<u8>(), size_of_val(&q8)) };
+
+    let mut model = GGufModel::default();
+    let f32_bytes = values
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect::<Vec<_>>();
+    model.insert_tensor("a", GGmlType::F32, [32, 2], f32_bytes);
+    model.insert_tensor(
+        "b",
+        GGmlType::Q8_0,
+        [32, 2],
+        GGufTensorData::from(q8_bytes.to_vec()),
+    );
+
+    let mut buf = Vec::new();
+    model.write(&mut buf, true).unwrap();
+    let gguf = GGuf::new(&buf).unwrap();
+
+    assert!(gguf.tensor("c").is_none());
+
+    let a = gguf.tensor("a").unwrap();
+    assert_eq!(a.name(), "a");
+    assert_eq!(a.ty(), GGmlType::F32);
+    assert_eq!(a.shape().to_vec(), [32, 2]);
+    assert_eq!(a.nelements(), 64);
+    assert_eq!(a.to_f32().unwrap(), values);
+    assert_eq!(
+        a.blocks::<Q8_0>().err(),
+        Some(GGufTensorError::TypeMismatch(GGmlType::F32))
+    );
+
+    let b = gguf.tensor("b").unwrap();
+    assert_eq!(b.data().unwrap(), q8_bytes);
+    assert_eq!(b.blocks::<Q8_0>().unwrap().len(), 2);
+    let dequant = b.to_f32().unwrap();
+    assert_eq!(dequant.len(), 64);
+    for (x, y) in std::iter::zip(&values, &dequant) {
+        assert!((x - y).abs() < 0.05)
+    }
+    assert_eq!(
+        b.to_f16().unwrap(),
+        dequant
+            .iter()
+            .map(|&x| f16::from_f32(x))
+            .collect::<Vec<_>>()
+    );
+
+    assert_eq!(b.nrows(), 2);
+    assert_eq!(
+        b.rows_to_f32([1, 0]).unwrap(),
+        [&dequant[32..], &dequant[..32]].concat()
+    );
+    assert_eq!(b.rows_to_f16([1]).unwrap(), b.to_f16().unwrap()[32..]);
+    assert_eq!(b.rows_to_f32([2]), Err(GGufTensorError::RowOutOfRange(2)));
+    assert_eq!(a.rows_to_f32([1]).unwrap(), values[32..]);
+}
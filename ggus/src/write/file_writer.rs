@@ -1,9 +1,18 @@
-use super::GGufWriter;
-use crate::{DEFAULT_ALIGNMENT, GGmlType, GGufFileHeader, GGufMetaDataValueType, pad};
+use super::{GGufWriteObserver, GGufWriteProgress, GGufWriter, progress::cancelled};
+use crate::{
+    DEFAULT_ALIGNMENT, GGmlType, GGufFileHeader, GGufMetaDataValueType, GGufMetaScalar,
+    GGufMetaValue, pad,
+};
 use log::trace;
 use std::{
     borrow::Borrow,
-    io::{Result, Write},
+    collections::HashMap,
+    convert::Infallible,
+    error,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{Error, Result, Write},
+    slice::from_raw_parts,
+    sync::Arc,
     time::Instant,
 };
 
@@ -13,21 +22,41 @@ pub struct GGufFileWriter<T: Write> {
 }
 
 pub struct GGufTensorWriter<T: Write, U> {
-    writer: GGufWriter<T>,
-    alignment: usize,
-    data: Vec<U>,
-    offset: usize,
+    pub(super) writer: GGufWriter<T>,
+    pub(super) alignment: usize,
+    /// Tensor data with its name, offset and size in the data section.
+    pub(super) data: Vec<(String, usize, usize, U)>,
+    pub(super) offset: usize,
+    pub(super) observer: Option<Arc<dyn GGufWriteObserver>>,
+    dedup: Option<DedupMap>,
     write_data: bool,
 }
 
+/// Written data grouped by type and shape.
+type DedupMap = HashMap<(GGmlType, Vec<u64>), DedupGroup>;
+
+/// Written data with the same type and shape.
+#[derive(Default)]
+struct DedupGroup {
+    /// The first data, not hashed until another one arrives.
+    first: Option<usize>,
+    /// Indices of hashed data by hash.
+    hashed: HashMap<u64, Vec<usize>>,
+}
+
+/// Tensor data that may be computed when it is written.
 pub trait DataFuture {
-    fn get(&self) -> &[u8];
+    type Error: error::Error + Send + Sync + 'static;
+
+    fn get(&self) -> std::result::Result<&[u8], Self::Error>;
 }
 
 impl<T: Borrow<[u8]>> DataFuture for T {
+    type Error = Infallible;
+
     #[inline]
-    fn get(&self) -> &[u8] {
-        self.borrow()
+    fn get(&self) -> std::result::Result<&[u8], Infallible> {
+        Ok(self.borrow())
     }
 }
 
@@ -69,6 +98,38 @@ impl<T: Write> GGufFileWriter<T> {
         Ok(())
     }
 
+    #[inline]
+    pub fn write_meta_str(&mut self, key: &str, val: impl AsRef<str>) -> Result<()> {
+        self.writer.write_meta_str(key, val)
+    }
+
+    #[inline]
+    pub fn write_meta_bool(&mut self, key: &str, val: bool) -> Result<()> {
+        self.writer.write_meta_bool(key, val)
+    }
+
+    /// Writes a scalar meta kv, `general.alignment` takes effect as in [`Self::write_meta_kv`].
+    #[inline]
+    pub fn write_meta<U: GGufMetaScalar>(&mut self, key: &str, val: U) -> Result<()> {
+        let bytes = unsafe { from_raw_parts(&val as *const U as *const u8, size_of::<U>()) };
+        self.write_meta_kv(key, U::TYPE, bytes)
+    }
+
+    #[inline]
+    pub fn write_meta_arr<U: GGufMetaScalar>(&mut self, key: &str, val: &[U]) -> Result<()> {
+        self.writer.write_meta_arr(key, val)
+    }
+
+    #[inline]
+    pub fn write_meta_str_arr(&mut self, key: &str, val: &[impl AsRef<str>]) -> Result<()> {
+        self.writer.write_meta_str_arr(key, val)
+    }
+
+    #[inline]
+    pub fn write_meta_kv_value(&mut self, key: &str, val: &GGufMetaValue) -> Result<()> {
+        self.write_meta_kv(key, val.ty(), &val.to_bytes()?)
+    }
+
     #[inline]
     pub fn finish<U>(self, write_data: bool) -> GGufTensorWriter<T, U> {
         GGufTensorWriter {
@@ -76,23 +137,69 @@ impl<T: Write> GGufFileWriter<T> {
             alignment: self.alignment,
             data: Vec::new(),
             offset: 0,
+            observer: None,
+            dedup: None,
             write_data,
         }
     }
 }
 
 impl<T: Write, U: DataFuture> GGufTensorWriter<T, U> {
+    /// Sets the observer notified while tensor data is written.
+    #[inline]
+    pub fn set_observer(&mut self, observer: Arc<dyn GGufWriteObserver>) {
+        self.observer = Some(observer)
+    }
+
+    /// Enables or disables deduplication of tensor data.
+    ///
+    /// When enabled, tensors with the same type, shape and data share the offset of the first one,
+    /// so the data is written once. Data is hashed in [`Self::write_tensor`] only when another tensor
+    /// with the same type and shape arrives, lazy data is computed there.
+    #[inline]
+    pub fn set_dedup(&mut self, dedup: bool) {
+        if !dedup {
+            self.dedup = None
+        } else if self.dedup.is_none() {
+            self.dedup = Some(HashMap::new())
+        }
+    }
+
     pub fn write_tensor(&mut self, name: &str, ty: GGmlType, shape: &[u64], data: U) -> Result<()> {
+        if self.write_data
+            && let Some(dedup) = &mut self.dedup
+        {
+            let group = dedup.entry((ty, shape.to_vec())).or_default();
+            if group.first.is_none() && group.hashed.is_empty() {
+                // 同类型同形状的第一个张量，不可能重复，暂不计算数据
+                group.first = Some(self.data.len())
+            } else {
+                if let Some(i) = group.first.take() {
+                    let bytes = self.data[i].3.get().map_err(Error::other)?;
+                    group.hashed.entry(hash(bytes)).or_default().push(i)
+                }
+                let bytes = data.get().map_err(Error::other)?;
+                let same = group.hashed.entry(hash(bytes)).or_default();
+                for &i in &*same {
+                    let (_, offset, _, other) = &self.data[i];
+                    if other.get().map_err(Error::other)? == bytes {
+                        // 数据完全相同，共享已写入的数据
+                        return self.writer.write_tensor_info(name, shape, ty, *offset as _);
+                    }
+                }
+                same.push(self.data.len())
+            }
+        }
+
         self.offset += pad(self.offset, self.alignment);
         self.writer
-            .write_tensor_info(name, shape, ty, self.offset as _)
-            .unwrap();
+            .write_tensor_info(name, shape, ty, self.offset as _)?;
 
         let len = ty.size().elements_to_bytes(shape);
-        self.offset += len;
         if self.write_data {
-            self.data.push(data)
+            self.data.push((name.into(), self.offset, len, data))
         }
+        self.offset += len;
         Ok(())
     }
 
@@ -101,14 +208,19 @@ impl<T: Write, U: DataFuture> GGufTensorWriter<T, U> {
             mut writer,
             alignment,
             data,
+            observer,
             ..
         } = self;
 
-        let total = data.len().to_string();
+        let tensors_total = data.len();
+        let bytes_total = data.iter().map(|(_, _, len, _)| len).sum();
+        let mut bytes_done = 0;
+
+        let total = tensors_total.to_string();
         let width = total.len();
-        for (i, data) in data.into_iter().enumerate() {
+        for (i, (name, _, _, data)) in data.into_iter().enumerate() {
             let t0 = Instant::now();
-            let data = data.get();
+            let data = data.get().map_err(Error::other)?;
             let t1 = Instant::now();
             writer.write_padding(alignment)?;
             writer.write_data(data)?;
@@ -118,8 +230,139 @@ impl<T: Write, U: DataFuture> GGufTensorWriter<T, U> {
                 data.len(),
                 t1 - t0,
                 t2 - t1,
-            )
+            );
+
+            bytes_done += data.len();
+            if let Some(observer) = &observer {
+                let progress = GGufWriteProgress {
+                    name: &name,
+                    tensors_done: i + 1,
+                    tensors_total,
+                    bytes_done,
+                    bytes_total,
+                };
+                if observer.on_progress(progress).is_break() {
+                    return Err(cancelled());
+                }
+            }
         }
         Ok(writer.written_bytes())
     }
 }
+
+fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_typed_meta() {
+    use crate::{GGuf, GGufMetaDataValueType as Ty, GGufMetaMapExt, GGufMetaValue as V};
+
+    let mut buf = Vec::new();
+    let mut writer = GGufFileWriter::new(&mut buf, GGufFileHeader::new(3, 1, 7)).unwrap();
+    writer.write_meta_str("general.name", "test").unwrap();
+    writer.write_meta("general.alignment", 64u32).unwrap();
+    writer.write_meta("test.f32", 1.5f32).unwrap();
+    writer.write_meta_bool("test.bool", true).unwrap();
+    writer.write_meta_arr("test.arr", &[1i32, 2, 3]).unwrap();
+    writer
+        .write_meta_str_arr("test.strs", &["a", "bc"])
+        .unwrap();
+    let nested = V::Array(
+        Ty::Array,
+        vec![
+            V::Array(Ty::U8, vec![1u8.into()]),
+            V::Array(Ty::String, vec!["x".into()]),
+        ],
+    );
+    writer.write_meta_kv_value("test.nested", &nested).unwrap();
+    let mut writer = writer.finish::<&[u8]>(true);
+    writer
+        .write_tensor("t", GGmlType::I8, &[2], &[7, 8][..])
+        .unwrap();
+    writer.finish().unwrap();
+
+    let gguf = GGuf::new(&buf).unwrap();
+    assert_eq!(gguf.alignment, 64);
+    assert_eq!(
+        (gguf.data.as_ptr() as usize - buf.as_ptr() as usize) % 64,
+        0
+    );
+    assert_eq!(gguf.data, &[7, 8]);
+    assert_eq!(gguf.general_name().unwrap(), "test");
+    assert_eq!(gguf.get_val::<f32>("test.f32").unwrap(), 1.5);
+    assert!(gguf.get_bool("test.bool").unwrap());
+    assert_eq!(
+        gguf.get_arr::<i32>("test.arr")
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        gguf.get_str_arr("test.strs")
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap(),
+        ["a", "bc"]
+    );
+    assert_eq!(gguf.meta_kvs["test.nested"].value().unwrap(), nested);
+}
+
+#[test]
+fn test_dedup() {
+    use crate::GGuf;
+    use std::cell::Cell;
+
+    /// 记录数据被计算的次数
+    struct Counted<'a>(Vec<u8>, &'a Cell<usize>);
+
+    impl DataFuture for Counted<'_> {
+        type Error = Infallible;
+
+        fn get(&self) -> std::result::Result<&[u8], Infallible> {
+            self.1.set(self.1.get() + 1);
+            Ok(&self.0)
+        }
+    }
+
+    let write = |dedup: bool| {
+        let gets = Cell::new(0);
+        let mut buf = Vec::new();
+        let mut writer = GGufFileWriter::new(&mut buf, GGufFileHeader::new(3, 4, 0))
+            .unwrap()
+            .finish::<Counted>(true);
+        writer.set_dedup(dedup);
+        let tensors = [
+            ("token_embd", [4, 2], vec![1; 8]),
+            ("output", [4, 2], vec![1; 8]),
+            ("other", [8, 1], vec![1; 8]),
+            ("diff", [4, 2], vec![2; 8]),
+        ];
+        for (name, shape, data) in tensors {
+            writer
+                .write_tensor(name, GGmlType::I8, &shape, Counted(data, &gets))
+                .unwrap();
+        }
+        let gets_before_finish = gets.get();
+        writer.finish().unwrap();
+        (buf, gets_before_finish)
+    };
+
+    let (full, gets) = write(false);
+    assert_eq!(gets, 0);
+    let (buf, gets) = write(true);
+    assert!(buf.len() < full.len());
+    // other 的类型和形状唯一，写入时不计算数据；token_embd 在 output 到达时才计算哈希
+    assert_eq!(gets, 4);
+
+    let gguf = GGuf::new(&buf).unwrap();
+    let offset = |name: &str| gguf.tensors[name].to_info().offset();
+    assert_eq!(offset("token_embd"), offset("output"));
+    assert_ne!(offset("token_embd"), offset("other"));
+    assert_eq!(gguf.tensor_data("output"), Some(&[1; 8][..]));
+    assert_eq!(gguf.tensor_data("other"), Some(&[1; 8][..]));
+    assert_eq!(gguf.tensor_data("diff"), Some(&[2; 8][..]));
+}
@@ -1,7 +1,10 @@
 ﻿mod file_writer;
+mod parallel;
+mod progress;
 mod simulator;
 mod writer;
 
 pub use file_writer::{DataFuture, GGufFileWriter, GGufTensorWriter};
+pub use progress::{GGufWriteObserver, GGufWriteProgress};
 pub use simulator::{GGufFileSimulator, GGufTensorSimulator};
 pub use writer::GGufWriter;
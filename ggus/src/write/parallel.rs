@@ -0,0 +1,192 @@
+use super::{DataFuture, GGufTensorWriter, GGufWriteProgress, progress::cancelled};
+use crate::pad;
+use log::trace;
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Result},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Instant,
+};
+
+impl<U: DataFuture + Send> GGufTensorWriter<File, U> {
+    /// Writes tensor data with all available cores.
+    ///
+    /// The file is sized to the final layout first, then each tensor is computed and written
+    /// at its offset by whichever thread picks it up. Padding is left as the zeros of the sized file.
+    #[inline]
+    pub fn finish_parallel(self) -> Result<usize> {
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        self.finish_with_threads(threads)
+    }
+
+    /// Writes tensor data with `threads` threads, see [`Self::finish_parallel`].
+    pub fn finish_with_threads(self, threads: usize) -> Result<usize> {
+        let Self {
+            writer,
+            alignment,
+            data,
+            offset,
+            observer,
+            ..
+        } = self;
+
+        let header = writer.written_bytes();
+        let file = writer.into_inner()?;
+        if data.is_empty() {
+            return Ok(header);
+        }
+
+        let base = header + pad(header, alignment);
+        let total = base + offset;
+        file.set_len(total as _)?;
+
+        let count = data.len();
+        let width = count.to_string().len();
+        let bytes_total = data.iter().map(|(_, _, len, _)| len).sum();
+        let queue = Mutex::new(data.into_iter().enumerate());
+        // 已完成的张量数和字节数，持锁通知观察者以保证进度单调
+        let done = Mutex::new((0, 0));
+        let failed = AtomicBool::new(false);
+        let work = || -> Result<()> {
+            while !failed.load(Ordering::Relaxed) {
+                let Some((i, (name, offset, len, data))) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let t0 = Instant::now();
+                let data = match data.get() {
+                    Ok(data) => data,
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(Error::other(e));
+                    }
+                };
+                let t1 = Instant::now();
+                if data.len() != len {
+                    failed.store(true, Ordering::Relaxed);
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("data {i} has {} bytes, {len} expected", data.len()),
+                    ));
+                }
+                if let Err(e) = write_all_at(&file, data, (base + offset) as _) {
+                    failed.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+                let t2 = Instant::now();
+                trace!(
+                    "data {i:>width$}/{count} size = {len} bytes, calculate in {:?}, write in {:?}",
+                    t1 - t0,
+                    t2 - t1,
+                );
+
+                let mut done = done.lock().unwrap();
+                done.0 += 1;
+                done.1 += len;
+                if let Some(observer) = &observer {
+                    let progress = GGufWriteProgress {
+                        name: &name,
+                        tensors_done: done.0,
+                        tensors_total: count,
+                        bytes_done: done.1,
+                        bytes_total,
+                    };
+                    if observer.on_progress(progress).is_break() {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(cancelled());
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        thread::scope(|s| {
+            let workers = (1..threads.clamp(1, count))
+                .map(|_| s.spawn(work))
+                .collect::<Vec<_>>();
+            let mut ans = work();
+            for worker in workers {
+                let res = worker.join().unwrap();
+                if ans.is_ok() {
+                    ans = res
+                }
+            }
+            ans
+        })?;
+        Ok(total)
+    }
+}
+
+#[cfg(unix)]
+#[inline]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_write(buf, offset) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                buf = &buf[n..];
+                offset += n as u64
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parallel() {
+    use crate::{GGmlType, GGuf, GGufFileHeader, GGufFileWriter};
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("ggus-parallel-{}.gguf", std::process::id()));
+    let tensors = (0..16u8)
+        .map(|i| (format!("t{i}"), vec![i; 1 + i as usize * 7]))
+        .collect::<Vec<_>>();
+
+    let header = GGufFileHeader::new(3, tensors.len() as _, 0);
+    let mut writer = GGufFileWriter::new(File::create(&path).unwrap(), header)
+        .unwrap()
+        .finish(true);
+    for (name, data) in &tensors {
+        let shape = [data.len() as u64];
+        writer
+            .write_tensor(name, GGmlType::I8, &shape, &data[..])
+            .unwrap();
+    }
+    let len = writer.finish_with_threads(4).unwrap();
+
+    let mut buf = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut buf).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(len, buf.len());
+
+    // 与顺序写入的结果一致
+    let mut expected = Vec::new();
+    let header = GGufFileHeader::new(3, tensors.len() as _, 0);
+    let mut writer = GGufFileWriter::new(&mut expected, header)
+        .unwrap()
+        .finish(true);
+    for (name, data) in &tensors {
+        let shape = [data.len() as u64];
+        writer
+            .write_tensor(name, GGmlType::I8, &shape, &data[..])
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    assert_eq!(buf, expected);
+
+    let gguf = GGuf::new(&buf).unwrap();
+    for (name, data) in &tensors {
+        assert_eq!(gguf.tensor_data(name), Some(&data[..]));
+    }
+}
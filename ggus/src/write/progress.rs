@@ -0,0 +1,100 @@
+use std::{
+    io::{Error, ErrorKind},
+    ops::ControlFlow,
+};
+
+/// Progress of writing tensor data, reported after each tensor is written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GGufWriteProgress<'a> {
+    /// Name of the tensor just written.
+    pub name: &'a str,
+    pub tensors_done: usize,
+    pub tensors_total: usize,
+    pub bytes_done: usize,
+    pub bytes_total: usize,
+}
+
+/// Observer of [`GGufTensorWriter`](super::GGufTensorWriter) writing tensor data.
+///
+/// Reports may come from any writing thread, but never concurrently.
+pub trait GGufWriteObserver: Send + Sync {
+    /// Called after each tensor is written, return [`ControlFlow::Break`] to cancel the writing.
+    fn on_progress(&self, progress: GGufWriteProgress) -> ControlFlow<()>;
+}
+
+impl<F> GGufWriteObserver for F
+where
+    F: Fn(GGufWriteProgress) -> ControlFlow<()> + Send + Sync,
+{
+    #[inline]
+    fn on_progress(&self, progress: GGufWriteProgress) -> ControlFlow<()> {
+        self(progress)
+    }
+}
+
+/// The error returned when the observer cancels the writing.
+#[inline]
+pub(super) fn cancelled() -> Error {
+    Error::new(ErrorKind::Interrupted, "writing is cancelled by observer")
+}
+
+#[test]
+fn test_observer() {
+    use crate::{GGmlType, GGufFileHeader, GGufFileWriter};
+    use std::{
+        fs::File,
+        sync::{Arc, Mutex},
+    };
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log_ = log.clone();
+    let mut buf = Vec::new();
+    let mut writer = GGufFileWriter::new(&mut buf, GGufFileHeader::new(3, 3, 0))
+        .unwrap()
+        .finish(true);
+    writer.set_observer(Arc::new(move |p: GGufWriteProgress| {
+        log_.lock().unwrap().push((
+            p.name.to_string(),
+            p.tensors_done,
+            p.bytes_done,
+            p.bytes_total,
+        ));
+        ControlFlow::Continue(())
+    }));
+    for (name, len) in [("a", 3), ("b", 5), ("c", 1)] {
+        writer
+            .write_tensor(name, GGmlType::I8, &[len], vec![0u8; len as _])
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            ("a".into(), 1, 3, 9),
+            ("b".into(), 2, 8, 9),
+            ("c".into(), 3, 9, 9)
+        ]
+    );
+
+    // 并行写入时中途取消
+    let path = std::env::temp_dir().join(format!("ggus-cancel-{}.gguf", std::process::id()));
+    let mut writer =
+        GGufFileWriter::new(File::create(&path).unwrap(), GGufFileHeader::new(3, 8, 0))
+            .unwrap()
+            .finish(true);
+    writer.set_observer(Arc::new(|p: GGufWriteProgress| {
+        if p.tensors_done < 2 {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }));
+    for i in 0..8 {
+        writer
+            .write_tensor(&format!("t{i}"), GGmlType::I8, &[4], vec![i; 4])
+            .unwrap();
+    }
+    let err = writer.finish_with_threads(2).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(err.kind(), ErrorKind::Interrupted);
+}
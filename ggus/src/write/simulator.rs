@@ -33,23 +33,32 @@ impl GGufFileSimulator {
     }
 
     #[inline]
-    pub fn with_alignment(alignment: usize) -> Self {
+    pub fn with_alignment(alignment: usize) -> Result<Self> {
         let mut ans = Self::new();
-        ans.write_alignment(alignment);
-        ans
+        ans.write_alignment(alignment)?;
+        Ok(ans)
     }
 
+    /// Fails only if `alignment` is not a non-zero power of two.
     #[inline]
-    pub fn write_alignment(&mut self, alignment: usize) {
-        self.writer.write_alignment(alignment).unwrap();
+    pub fn write_alignment(&mut self, alignment: usize) -> Result<()> {
+        self.writer.write_alignment(alignment)?;
         self.alignment = alignment;
+        Ok(())
     }
 
+    /// Fails only if the value of `general.alignment` is invalid.
     #[inline]
-    pub fn write_meta_kv(&mut self, key: &str, ty: GGufMetaDataValueType, val: &[u8]) {
-        if let Some(alignment) = self.writer.write_meta_kv(key, ty, val).unwrap() {
+    pub fn write_meta_kv(
+        &mut self,
+        key: &str,
+        ty: GGufMetaDataValueType,
+        val: &[u8],
+    ) -> Result<()> {
+        if let Some(alignment) = self.writer.write_meta_kv(key, ty, val)? {
             self.alignment = alignment;
         }
+        Ok(())
     }
 
     #[inline]
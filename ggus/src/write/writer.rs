@@ -1,7 +1,10 @@
-use crate::{GENERAL_ALIGNMENT, GGmlType, GGufFileHeader, GGufMetaDataValueType, pad};
+use crate::{
+    GENERAL_ALIGNMENT, GGmlType, GGufFileHeader, GGufMetaDataValueType as Ty, GGufMetaScalar,
+    GGufMetaValue, metadata::alignment_value, pad,
+};
 use internal::Internal;
 use std::{
-    io::{Result, Write},
+    io::{Error, ErrorKind, Result, Write},
     slice::from_raw_parts,
 };
 
@@ -39,33 +42,109 @@ impl<T: Write> GGufWriter<T> {
         self.write(val)
     }
 
+    /// Writes `general.alignment` as an u32, or an u64 if it does not fit.
     pub fn write_alignment(&mut self, alignment: usize) -> Result<()> {
-        self.write_meta_kv(
-            GENERAL_ALIGNMENT,
-            GGufMetaDataValueType::U32,
-            &(alignment as u32).to_le_bytes(),
-        )?;
+        check_alignment(alignment)?;
+        match u32::try_from(alignment) {
+            Ok(n) => self.write_meta_kv(GENERAL_ALIGNMENT, Ty::U32, &n.to_le_bytes()),
+            Err(_) => self.write_meta_kv(
+                GENERAL_ALIGNMENT,
+                Ty::U64,
+                &(alignment as u64).to_le_bytes(),
+            ),
+        }?;
         Ok(())
     }
 
-    pub fn write_meta_kv(
-        &mut self,
-        key: &str,
-        ty: GGufMetaDataValueType,
-        val: &[u8],
-    ) -> Result<Option<usize>> {
+    /// Writes a meta kv, returns the alignment if the key is `general.alignment`.
+    ///
+    /// The alignment must be an u32 or u64 of a non-zero power of two, nothing is written otherwise.
+    pub fn write_meta_kv(&mut self, key: &str, ty: Ty, val: &[u8]) -> Result<Option<usize>> {
+        let alignment = if key == GENERAL_ALIGNMENT {
+            let alignment = alignment_value(ty, val).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid {GENERAL_ALIGNMENT} {ty:?} {val:?}: {e:?}"),
+                )
+            })?;
+            Some(alignment)
+        } else {
+            None
+        };
+
         self.write_str(key)?;
         self.write(&[ty])?;
         self.write(val)?;
+        Ok(alignment)
+    }
 
-        Ok(if key == GENERAL_ALIGNMENT {
-            let &[a, b, c, d] = val else {
-                panic!("general.alignment must be an u32")
-            };
-            Some(u32::from_le_bytes([a, b, c, d]) as _)
-        } else {
-            None
-        })
+    /// Writes a string meta kv.
+    pub fn write_meta_str(&mut self, key: &str, val: impl AsRef<str>) -> Result<()> {
+        self.write_meta_head(key, Ty::String)?;
+        self.write_str(val)
+    }
+
+    /// Writes a bool meta kv.
+    pub fn write_meta_bool(&mut self, key: &str, val: bool) -> Result<()> {
+        self.write_meta_head(key, Ty::Bool)?;
+        self.write(&[val as u8])
+    }
+
+    /// Writes a scalar meta kv, tagged with the type of `U`.
+    pub fn write_meta<U: GGufMetaScalar>(&mut self, key: &str, val: U) -> Result<()> {
+        let bytes = unsafe { from_raw_parts(&val as *const U as *const u8, size_of::<U>()) };
+        self.write_meta_kv(key, U::TYPE, bytes)?;
+        Ok(())
+    }
+
+    /// Writes an array meta kv of scalars.
+    #[inline]
+    pub fn write_meta_arr<U: GGufMetaScalar>(&mut self, key: &str, val: &[U]) -> Result<()> {
+        self.write_meta_head(key, Ty::Array)?;
+        self.write_arr(val)
+    }
+
+    /// Writes an array meta kv of strings.
+    #[inline]
+    pub fn write_meta_str_arr(&mut self, key: &str, val: &[impl AsRef<str>]) -> Result<()> {
+        self.write_meta_head(key, Ty::Array)?;
+        self.write_str_arr(val)
+    }
+
+    /// Writes an array value of scalars, with its element type and length.
+    pub fn write_arr<U: GGufMetaScalar>(&mut self, val: &[U]) -> Result<()> {
+        self.write(&[U::TYPE])?;
+        self.write(&[val.len() as u64])?;
+        self.write(val)
+    }
+
+    /// Writes an array value of strings, with its element type and length.
+    pub fn write_str_arr(&mut self, val: &[impl AsRef<str>]) -> Result<()> {
+        self.write(&[Ty::String])?;
+        self.write(&[val.len() as u64])?;
+        val.iter().try_for_each(|s| self.write_str(s))
+    }
+
+    /// Writes a meta kv of any value, including nested arrays.
+    pub fn write_meta_kv_value(&mut self, key: &str, val: &GGufMetaValue) -> Result<()> {
+        if key == GENERAL_ALIGNMENT {
+            self.write_meta_kv(key, val.ty(), &val.to_bytes()?)?;
+            return Ok(());
+        }
+        self.write_meta_head(key, val.ty())?;
+        self.write_meta_value(val)
+    }
+
+    /// Writes the key and type of a meta kv, whose type can never be valid for `general.alignment`.
+    fn write_meta_head(&mut self, key: &str, ty: Ty) -> Result<()> {
+        if key == GENERAL_ALIGNMENT {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{GENERAL_ALIGNMENT} must be an u32 or u64, not {ty:?}"),
+            ));
+        }
+        self.write_str(key)?;
+        self.write(&[ty])
     }
 
     pub fn write_tensor_info(
@@ -83,8 +162,12 @@ impl<T: Write> GGufWriter<T> {
     }
 
     pub fn write_padding(&mut self, alignment: usize) -> Result<()> {
-        for _ in 0..pad(self.written_bytes(), alignment) {
-            self.write(&[0u8])?;
+        const ZEROS: [u8; 64] = [0; 64];
+        let mut len = pad(self.written_bytes(), alignment);
+        while len > 0 {
+            let n = len.min(ZEROS.len());
+            self.write(&ZEROS[..n])?;
+            len -= n
         }
         Ok(())
     }
@@ -92,6 +175,24 @@ impl<T: Write> GGufWriter<T> {
     pub fn write_data(&mut self, data: &[u8]) -> Result<()> {
         self.write(data)
     }
+
+    /// Flushes the buffer and returns the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> Result<T> {
+        self.0.into_inner()
+    }
+}
+
+/// Checks that `alignment` is a non-zero power of two.
+pub(crate) fn check_alignment(alignment: usize) -> Result<()> {
+    if alignment.is_power_of_two() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("alignment {alignment} is not a non-zero power of two"),
+        ))
+    }
 }
 
 mod internal {
@@ -110,6 +211,11 @@ mod internal {
             self.1
         }
 
+        #[inline]
+        pub fn into_inner(self) -> Result<T> {
+            self.0.into_inner().map_err(|e| e.into_error())
+        }
+
         #[inline]
         pub fn write_bytes(&mut self, val: &[u8]) -> Result<()> {
             self.1 += val.len();
@@ -117,3 +223,61 @@ mod internal {
         }
     }
 }
+
+#[test]
+fn test_alignment() {
+    use crate::{GGuf, GGufError, GGufFileWriter, GGufMetaMap};
+
+    let mut writer = GGufWriter::new(Vec::new());
+    for alignment in [0, 3, 48] {
+        assert!(writer.write_alignment(alignment).is_err());
+    }
+    assert!(
+        writer
+            .write_meta_kv(GENERAL_ALIGNMENT, Ty::U16, &[64, 0])
+            .is_err()
+    );
+    assert!(
+        writer
+            .write_meta_kv(GENERAL_ALIGNMENT, Ty::U32, &[64, 0])
+            .is_err()
+    );
+    assert!(writer.write_meta_str(GENERAL_ALIGNMENT, "64").is_err());
+    assert!(writer.write_meta(GENERAL_ALIGNMENT, 64i32).is_err());
+    assert_eq!(writer.written_bytes(), 0);
+    assert_eq!(
+        writer
+            .write_meta_kv(GENERAL_ALIGNMENT, Ty::U64, &64u64.to_le_bytes())
+            .unwrap(),
+        Some(64)
+    );
+
+    // u64 的对齐可以读回
+    let mut buf = Vec::new();
+    let mut writer = GGufFileWriter::new(&mut buf, GGufFileHeader::new(3, 1, 1)).unwrap();
+    writer.write_meta(GENERAL_ALIGNMENT, 128u64).unwrap();
+    let mut writer = writer.finish::<&[u8]>(true);
+    writer
+        .write_tensor("t", GGmlType::I8, &[1], &[1][..])
+        .unwrap();
+    writer.finish().unwrap();
+    let gguf = GGuf::new(&buf).unwrap();
+    assert_eq!(gguf.alignment, 128);
+    assert_eq!(gguf.get(GENERAL_ALIGNMENT).unwrap().0, Ty::U64);
+    assert_eq!(
+        (gguf.data.as_ptr() as usize - buf.as_ptr() as usize) % 128,
+        0
+    );
+
+    // 读取时拒绝非 2 的幂的对齐
+    let mut writer = GGufWriter::new(Vec::new());
+    writer.write_header(GGufFileHeader::new(3, 0, 1)).unwrap();
+    writer.write_str(GENERAL_ALIGNMENT).unwrap();
+    writer.write(&[Ty::U32]).unwrap();
+    writer.write(&[48u32]).unwrap();
+    let buf = writer.into_inner().unwrap();
+    assert!(matches!(
+        GGuf::new(&buf),
+        Err(GGufError::InvalidAlignment(48))
+    ));
+}
@@ -51,7 +51,7 @@ impl ConvertArgs {
             }),
             output.into(),
         )
-        .unwrap();
+        .unwrap_or_else(|e| e.exit());
 
         show_file_info(&files);
     }
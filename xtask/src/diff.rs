@@ -22,7 +22,7 @@ impl DiffArgs {
         let Self { a, b, output, log } = self;
         log.init();
 
-        let files = diff(a, b, output.into()).unwrap();
+        let files = diff(a, b, output.into()).unwrap_or_else(|e| e.exit());
         show_file_info(&files);
     }
 }
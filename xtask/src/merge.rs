@@ -49,7 +49,7 @@ impl MergeArgs {
                 write_data: !no_data,
             },
         )
-        .unwrap();
+        .unwrap_or_else(|e| e.exit());
 
         show_file_info(&files);
     }
@@ -1,6 +1,6 @@
 use crate::{
     LogArgs,
-    utils::{Operator, OutputArgs, operate, show_file_info},
+    utils::{Operator, OutputArgs, operate, set_meta_in_place, show_file_info},
 };
 use ggus::GGufFileName;
 use std::{
@@ -14,6 +14,9 @@ pub struct SetMetaArgs {
     file: PathBuf,
     /// Meta data to set for the file
     meta_kvs: String,
+    /// If set, only the head of the file is rewritten, output options are ignored
+    #[clap(long, short)]
+    in_place: bool,
 
     #[clap(flatten)]
     output: OutputArgs,
@@ -26,6 +29,7 @@ impl SetMetaArgs {
         let Self {
             file,
             meta_kvs,
+            in_place,
             output,
             log,
         } = self;
@@ -44,13 +48,19 @@ impl SetMetaArgs {
             meta_kvs
         };
 
+        if in_place {
+            let info = set_meta_in_place(&file, &cfg).unwrap_or_else(|e| e.exit());
+            show_file_info(&[info]);
+            return;
+        }
+
         let files = operate(
             GGufFileName::try_from(&*file).unwrap(),
             [&file],
             [Operator::set_meta_by_cfg(&cfg)],
             output.into(),
         )
-        .unwrap();
+        .unwrap_or_else(|e| e.exit());
 
         show_file_info(&files);
     }
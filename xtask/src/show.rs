@@ -1,6 +1,7 @@
 use crate::{LogArgs, utils::compile_patterns};
 use ggus::{
-    GGufFileHeader, GGufFileName, GGufMetaDataValueType, GGufMetaKV, GGufReadError, GGufReader,
+    GGUS_PADDING, GGufFileHeader, GGufFileName, GGufMetaDataValueType, GGufMetaKV, GGufMetaKey,
+    GGufReadError, GGufReader,
 };
 use indexmap::IndexMap;
 use memmap2::Mmap;
@@ -13,6 +14,7 @@ use std::{
 
 const YES: &str = "✔️  ";
 const ERR: &str = "❌  ";
+const WARN: &str = "⚠️  ";
 
 #[derive(Args, Default)]
 pub struct ShowArgs {
@@ -174,7 +176,7 @@ fn show_meta_kvs(
     let mut width = 0;
     let mut meta_kvs = IndexMap::new();
     for _ in 0..count {
-        let kv = match reader.read_meta_kv() {
+        let kv = match reader.read_meta_kv_lazy() {
             Ok(kv) => kv,
             Err(e) => {
                 println!("{ERR}Failed to read meta kv: {e:?}");
@@ -186,7 +188,8 @@ fn show_meta_kvs(
             println!("{ERR}Duplicate meta key: {k}");
             return Err(Failed);
         }
-        if filter.is_match(k) {
+        // 就地编辑留下的填充不含信息
+        if filter.is_match(k) && k != GGUS_PADDING {
             width = k.len().max(width);
             meta_kvs.insert(k, kv);
         }
@@ -262,7 +265,20 @@ fn show_meta_kv(kv: GGufMetaKV, width: usize, detail: usize) -> Result<(), Faile
     let mut buf = String::new();
     match fmt_meta_val(&mut reader, ty, 1, detail, &mut buf) {
         Ok(()) => {
-            println!("{YES}{key:·<width$}{:·>5}: {buf}", ty.name());
+            // 标准键的类型与约定不符时给出警告
+            match GGufMetaKey::find(key) {
+                Some(std) if std.check(ty, kv.value_bytes()).is_err() => {
+                    let expected = match std.elem {
+                        Some(elem) => format!("[{}]", elem.name()),
+                        None => std.ty.name().into(),
+                    };
+                    println!(
+                        "{WARN}{key:·<width$}{:·>5}: {buf} (expected {expected})",
+                        ty.name()
+                    )
+                }
+                _ => println!("{YES}{key:·<width$}{:·>5}: {buf}", ty.name()),
+            }
             Ok(())
         }
         Err(e) => {
@@ -316,7 +332,7 @@ fn fmt_meta_val(
                 T::I64 => buf.push_str(&reader.read::<i64>()?.to_string()),
                 T::F32 => buf.push_str(&fmt_exp(reader.read::<f32>()?)),
                 T::F64 => buf.push_str(&fmt_exp(reader.read::<f64>()? as _)),
-                T::Bool => buf.push(if reader.read()? { '√' } else { '×' }),
+                T::Bool => buf.push(if reader.read_bool()? { '√' } else { '×' }),
                 T::String => {
                     let str = reader.read_str()?;
                     if str.lines().nth(1).is_some() {
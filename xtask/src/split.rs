@@ -27,7 +27,7 @@ impl SplitArgs {
             return;
         }
 
-        let files = operate(name, [&file], [], output.into()).unwrap();
+        let files = operate(name, [&file], [], output.into()).unwrap_or_else(|e| e.exit());
         show_file_info(&files);
     }
 }
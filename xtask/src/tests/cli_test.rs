@@ -8,8 +8,8 @@ mod tests {
         let args = vec!["gguf-utils", "show", "test.gguf"];
         let cli = Cli::parse_from(args);
         match cli.command {
-            Commands::Show(_) => assert!(true),
-            _ => assert!(false, "Expected Show command"),
+            Commands::Show(_) => {}
+            _ => panic!("Expected Show command"),
         }
     }
 
@@ -18,8 +18,8 @@ mod tests {
         let args = vec!["gguf-utils", "split", "test.gguf", "-t", "2"];
         let cli = Cli::parse_from(args);
         match cli.command {
-            Commands::Split(_) => assert!(true),
-            _ => assert!(false, "Expected Split command"),
+            Commands::Split(_) => {}
+            _ => panic!("Expected Split command"),
         }
     }
 
@@ -28,8 +28,8 @@ mod tests {
         let args = vec!["gguf-utils", "merge", "shard.00.gguf"];
         let cli = Cli::parse_from(args);
         match cli.command {
-            Commands::Merge(_) => assert!(true),
-            _ => assert!(false, "Expected Merge command"),
+            Commands::Merge(_) => {}
+            _ => panic!("Expected Merge command"),
         }
     }
 
@@ -38,8 +38,7 @@ mod tests {
         let log_args = LogArgs {
             log: Some("debug".to_string()),
         };
-        log_args.init();
         // 由于日志初始化是全局的，我们只能验证它不会panic
-        assert!(true);
+        log_args.init();
     }
 } 
\ No newline at end of file
@@ -18,7 +18,6 @@ mod tests {
         match cli.command {
             Commands::Show(_) => {
                 // 验证命令类型正确
-                assert!(true);
             },
             _ => panic!("Wrong command parsed"),
         }
@@ -3,36 +3,52 @@ mod file_info;
 mod name_pattern;
 mod operator;
 mod output;
+mod progress;
 mod read;
 mod write;
 
 use file_info::FileInfo;
-use ggus::{GGmlType, GGufError, GGufFileName, GGufMetaDataValueType, GGufMetaMap, GGufWriter};
-use indexmap::IndexMap;
+use ggus::{GGufError, GGufFileName, GGufModel};
 use log::info;
-use memmap2::{Mmap, MmapMut};
+use memmap2::Mmap;
 use std::{
-    borrow::Cow,
+    fmt,
     fs::File,
     io,
+    ops::{Deref, DerefMut},
     path::Path,
-    sync::{Arc, LazyLock},
     time::Instant,
 };
 
 pub(crate) use diff::diff;
 pub(crate) use file_info::show_file_info;
 pub(crate) use name_pattern::compile_patterns;
-pub(crate) use operator::Operator;
+pub(crate) use operator::{Operator, set_meta_in_place};
 pub(crate) use output::{OutputArgs, OutputConfig};
 
-#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum OperateError {
     GGuf(GGufError),
     Io(io::Error),
 }
 
+impl fmt::Display for OperateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GGuf(e) => write!(f, "{e}"),
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl OperateError {
+    /// 打印错误并以失败状态退出
+    pub fn exit(self) -> ! {
+        eprintln!("error: {self}");
+        std::process::exit(1)
+    }
+}
+
 pub(crate) fn operate<T: AsRef<Path>>(
     name: GGufFileName,
     input_files: impl IntoIterator<Item = T>,
@@ -49,7 +65,7 @@ pub(crate) fn operate<T: AsRef<Path>>(
     for op in operations {
         let name = op.to_string();
         let time = Instant::now();
-        content.apply(op);
+        content.apply(op)?;
         info!("run step {name} in {:?}", time.elapsed());
     }
     let time = Instant::now();
@@ -60,70 +76,20 @@ pub(crate) fn operate<T: AsRef<Path>>(
 
 struct Content<'a> {
     name: GGufFileName<'a>,
-    alignment: usize,
-    meta_kvs: IndexMap<Cow<'a, str>, MetaValue<'a>>,
-    tensors: IndexMap<Cow<'a, str>, Tensor<'a>>,
-}
-
-impl GGufMetaMap for Content<'_> {
-    fn get(&self, key: &str) -> Option<(GGufMetaDataValueType, &[u8])> {
-        self.meta_kvs.get(key).map(|v| (v.ty, &*v.value))
-    }
-}
-
-struct MetaValue<'a> {
-    ty: GGufMetaDataValueType,
-    value: Cow<'a, [u8]>,
-}
-
-impl MetaValue<'_> {
-    fn string(s: &str) -> Self {
-        Self {
-            ty: GGufMetaDataValueType::String,
-            value: {
-                let mut vec = Vec::with_capacity(s.len() + size_of::<u64>());
-                GGufWriter::new(&mut vec).write_str(s).unwrap();
-                vec.into()
-            },
-        }
-    }
-}
-
-struct Tensor<'a> {
-    ty: GGmlType,
-    shape: Vec<u64>,
-    data: DataPromise<'a>,
-}
-
-#[derive(Clone)]
-enum DataPromise<'a> {
-    Borrowed(&'a [u8]),
-    Lazy(Arc<dyn LazyData + Send + Sync + 'a>),
+    model: GGufModel<'a>,
 }
 
-impl<'a> DataPromise<'a> {
-    fn lazy(f: impl FnOnce() -> MmapMut + Send + Sync + 'a) -> Self {
-        Self::Lazy(Arc::new(LazyLock::new(f)))
-    }
-}
-
-impl ggus::DataFuture for DataPromise<'_> {
+impl<'a> Deref for Content<'a> {
+    type Target = GGufModel<'a>;
     #[inline]
-    fn get(&self) -> &[u8] {
-        match self {
-            Self::Borrowed(data) => data,
-            Self::Lazy(data) => data.get(),
-        }
+    fn deref(&self) -> &Self::Target {
+        &self.model
     }
 }
 
-trait LazyData {
-    fn get(&self) -> &[u8];
-}
-
-impl<F: FnOnce() -> MmapMut> LazyData for LazyLock<MmapMut, F> {
+impl DerefMut for Content<'_> {
     #[inline]
-    fn get(&self) -> &[u8] {
-        self
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.model
     }
 }
@@ -1,12 +1,12 @@
-use super::{Content, DataPromise, Operator};
+use super::{Content, DataResult, Operator};
 use ggus::{
-    DataFuture, GGmlType as Ty, GGufMetaMapExt,
+    DataFuture, GGmlType as Ty, GGufMetaMapExt, GGufTensorData,
     ggml_quants::{Q4_0, Q4_1, Q5_0, Q5_1, Q8_0, Q8_1, QuantExt, bf16, f16},
 };
 use log::debug;
 use memmap2::MmapMut;
 use regex::Regex;
-use std::{alloc::Layout, collections::HashMap, sync::LazyLock};
+use std::{alloc::Layout, collections::HashMap, io, sync::LazyLock};
 
 impl Operator {
     #[inline]
@@ -72,7 +72,7 @@ impl Content<'_> {
 
     fn cast_(&mut self, main: Option<Ty>, mut ty: impl FnMut(&str, &[u64]) -> Option<Ty>) {
         if let Some(main) = main {
-            self.name.encoding = Some(format!("{main:?}").into());
+            self.name.encoding = Some(main.name().into());
         }
         for (name, tensor) in self.tensors.as_mut_slice() {
             let from = tensor.ty;
@@ -82,16 +82,20 @@ impl Content<'_> {
                 debug!("Casting tensor {name} from {from:?} to {to:?}");
                 tensor.ty = to;
 
+                if tensor.data.is_missing() {
+                    continue;
+                }
                 let data = tensor.data.clone();
                 let row = tensor.shape[0];
-                tensor.data = DataPromise::lazy(move || cast(row as _, data.get(), from, to))
+                tensor.data =
+                    GGufTensorData::try_lazy(move || cast(row as _, data.get()?, from, to))
             }
         }
     }
 }
 
 #[rustfmt::skip]
-fn cast(row: usize, data: &[u8], from: Ty, to: Ty) -> MmapMut {
+fn cast(row: usize, data: &[u8], from: Ty, to: Ty) -> DataResult {
     match from {
         Ty::F32 => match to {
             Ty::F32      => unreachable!(),
@@ -103,7 +107,7 @@ fn cast(row: usize, data: &[u8], from: Ty, to: Ty) -> MmapMut {
             Ty::Q8_0     => quantize::<Q8_0, f32, 32>(data, row),
             Ty::Q8_1     => quantize::<Q8_1, f32, 32>(data, row),
             Ty::BF16     => quantize::<bf16, f32,  1>(data, row),
-            _ => todo!(),
+            _ => unsupported(from, to),
         },
         Ty::F16 => match to {
             Ty::F32      => dequantize::<f16 , f32,  1>(data),
@@ -115,7 +119,7 @@ fn cast(row: usize, data: &[u8], from: Ty, to: Ty) -> MmapMut {
             Ty::Q8_0     =>   quantize::<Q8_0, f16, 32>(data, row),
             Ty::Q8_1     =>   quantize::<Q8_1, f16, 32>(data, row),
             Ty::BF16     =>   quantize::<bf16, f16,  1>(data, row),
-            _ => todo!(),
+            _ => unsupported(from, to),
         },
         Ty::BF16 => match to {
             Ty::F32      => dequantize::<bf16, f32 ,  1>(data),
@@ -127,53 +131,71 @@ fn cast(row: usize, data: &[u8], from: Ty, to: Ty) -> MmapMut {
             Ty::Q8_0     =>   quantize::<Q8_0, bf16, 32>(data, row),
             Ty::Q8_1     =>   quantize::<Q8_1, bf16, 32>(data, row),
             Ty::BF16     => unreachable!(),
-            _ => todo!(),
+            _ => unsupported(from, to),
         },
-        Ty::Q8_0 => match to {
-            Ty::F32      => dequantize::<Q8_0, f32, 32>(data),
-            _            => todo!(),
-        },
-        _ => cast(row, &cast(row, data, from, Ty::F32), Ty::F32, to),
+        // 先反量化到 f32 再转换
+        Ty::Q4_0 | Ty::Q4_1 | Ty::Q5_0 | Ty::Q5_1 | Ty::Q8_0 | Ty::Q8_1 => {
+            let f32 = match from {
+                Ty::Q4_0 => dequantize::<Q4_0, f32, 32>(data)?,
+                Ty::Q4_1 => dequantize::<Q4_1, f32, 32>(data)?,
+                Ty::Q5_0 => dequantize::<Q5_0, f32, 32>(data)?,
+                Ty::Q5_1 => dequantize::<Q5_1, f32, 32>(data)?,
+                Ty::Q8_0 => dequantize::<Q8_0, f32, 32>(data)?,
+                Ty::Q8_1 => dequantize::<Q8_1, f32, 32>(data)?,
+                _        => unreachable!(),
+            };
+            match to {
+                Ty::F32 => Ok(f32),
+                _       => cast(row, &f32, Ty::F32, to),
+            }
+        }
+        _ => unsupported(from, to),
     }
 }
 
-fn quantize<Ext: QuantExt<T, N>, T, const N: usize>(data: &[u8], row: usize) -> MmapMut {
-    let src = reslice::<T>(data);
-    assert_eq!(src.len() % row, 0);
-    assert_eq!(row % N, 0);
-    let mut ans = malloc::<Ext>(src.len() / N);
-    let dst = reslice_mut::<Ext>(&mut ans);
-    Ext::quantize_slice(dst, src).unwrap();
-    ans
+fn unsupported(from: Ty, to: Ty) -> DataResult {
+    Err(format!("cast from {from:?} to {to:?} is not supported").into())
+}
+
+fn quantize<Ext: QuantExt<T, N>, T, const N: usize>(data: &[u8], row: usize) -> DataResult {
+    let src = reslice::<T>(data)?;
+    if !src.len().is_multiple_of(row) || !row.is_multiple_of(N) {
+        return Err(format!("row of {row} elements does not fit blocks of {N}").into());
+    }
+    let mut ans = malloc::<Ext>(src.len() / N)?;
+    let dst = reslice_mut::<Ext>(&mut ans)?;
+    Ext::quantize_slice(dst, src).map_err(|e| format!("quantize failed: {e:?}"))?;
+    Ok(ans)
 }
 
-fn dequantize<Ext: QuantExt<T, N>, T, const N: usize>(data: &[u8]) -> MmapMut {
-    let src = reslice::<Ext>(data);
-    let mut ans = malloc::<T>(src.len() * N);
-    let dst = reslice_mut::<T>(&mut ans);
-    Ext::dequantize_slice(dst, src).unwrap();
-    ans
+fn dequantize<Ext: QuantExt<T, N>, T, const N: usize>(data: &[u8]) -> DataResult {
+    let src = reslice::<Ext>(data)?;
+    let mut ans = malloc::<T>(src.len() * N)?;
+    let dst = reslice_mut::<T>(&mut ans)?;
+    Ext::dequantize_slice(dst, src).map_err(|e| format!("dequantize failed: {e:?}"))?;
+    Ok(ans)
 }
 
 #[inline]
-fn malloc<T>(len: usize) -> MmapMut {
-    MmapMut::map_anon(Layout::array::<T>(len).unwrap().size()).unwrap()
+fn malloc<T>(len: usize) -> io::Result<MmapMut> {
+    let size = Layout::array::<T>(len).map_err(io::Error::other)?.size();
+    MmapMut::map_anon(size)
 }
 
 #[inline]
-fn reslice<T>(data: &[u8]) -> &[T] {
-    let ([], data, []) = (unsafe { data.align_to() }) else {
-        panic!("data is not aligned");
-    };
-    data
+fn reslice<T>(data: &[u8]) -> Result<&[T], &'static str> {
+    match unsafe { data.align_to() } {
+        ([], data, []) => Ok(data),
+        _ => Err("data is not aligned"),
+    }
 }
 
 #[inline]
-fn reslice_mut<T>(data: &mut [u8]) -> &mut [T] {
-    let ([], data, []) = (unsafe { data.align_to_mut() }) else {
-        panic!("data is not aligned");
-    };
-    data
+fn reslice_mut<T>(data: &mut [u8]) -> Result<&mut [T], &'static str> {
+    match unsafe { data.align_to_mut() } {
+        ([], data, []) => Ok(data),
+        _ => Err("data is not aligned"),
+    }
 }
 
 #[rustfmt::skip]
@@ -225,3 +247,22 @@ fn test_parse() {
     assert_eq!(types.get("mat"), Some(&Ty::Q8_0));
     assert_eq!(types.get("norm"), Some(&Ty::F32));
 }
+
+#[test]
+fn test_cast_legacy_quants() {
+    let src = (0..64).map(|i| i as f32 / 8.).collect::<Vec<_>>();
+    let (_, src, _) = unsafe { src.align_to::<u8>() };
+    let q4_0 = cast(32, src, Ty::F32, Ty::Q4_0).unwrap();
+    assert_eq!(q4_0.len(), 2 * 18);
+
+    let f16 = cast(32, &q4_0, Ty::Q4_0, Ty::F16).unwrap();
+    assert_eq!(f16.len(), 64 * 2);
+    let f32 = cast(32, &q4_0, Ty::Q4_0, Ty::F32).unwrap();
+    assert_eq!(f32.len(), 64 * 4);
+
+    let q8_0 = cast(32, src, Ty::F32, Ty::Q8_0).unwrap();
+    let bf16 = cast(32, &q8_0, Ty::Q8_0, Ty::BF16).unwrap();
+    assert_eq!(bf16.len(), 64 * 2);
+
+    assert!(cast(256, &[0; 144], Ty::Q4K, Ty::F16).is_err());
+}
@@ -1,13 +1,22 @@
-use super::{super::Tensor, Content, DataPromise};
-use ggus::{DataFuture, GGmlType, GGmlTypeSize, GGufMetaError::NotExist, GGufMetaMapExt};
+use super::{Content, DataResult};
+use ggus::{
+    DataFuture, GGmlType, GGmlTypeSize, GGufMetaError::NotExist, GGufMetaMapExt, GGufTensorBuf,
+    GGufTensorData, GGufTensorName,
+};
 use mem_rearrange::{Rearranging, ndarray_layout::ArrayLayout};
 use memmap2::MmapMut;
-use regex::Regex;
-use std::{borrow::Cow, collections::HashMap, hash::Hash, iter::zip, sync::LazyLock};
-
-const MERGE: &str =
-    r"(attn_q|attn_k|attn_v|ffn_gate|ffn_up|ffn_gate_exps|ffn_up_exps)\.(weight|bias)$";
-const SPLIT: &str = r"(attn_qkv|ffn_gate_up)\.(weight|bias)$";
+use std::{borrow::Cow, collections::HashMap, hash::Hash, iter::zip};
+
+const MERGE: &[&str] = &[
+    ATTN_Q,
+    ATTN_K,
+    ATTN_V,
+    FFN_GATE,
+    FFN_UP,
+    FFN_GATE_EXPS,
+    FFN_UP_EXPS,
+];
+const SPLIT: &[&str] = &[ATTN_QKV, FFN_GATE_UP, FFN_GATE_UP_EXPS];
 const ATTN_QKV: &str = "attn_qkv";
 const ATTN_Q: &str = "attn_q";
 const ATTN_K: &str = "attn_k";
@@ -47,12 +56,15 @@ impl Content<'_> {
             };
 
             for (name, tensor) in tensors {
-                static SPLIT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(SPLIT).unwrap());
-                if let Some(captures) = SPLIT_REGEX.captures(&name) {
-                    let pre = &name[..captures.get(0).unwrap().start()];
-                    let (_, [name, wb]) = captures.extract();
-                    let key = |name: &str| format!("{pre}{name}.{wb}").into();
-                    match name {
+                if let Some(parsed) = parse_linear(&name, SPLIT) {
+                    let key = |component| {
+                        let name = GGufTensorName {
+                            component,
+                            ..parsed
+                        };
+                        name.to_string().into()
+                    };
+                    match parsed.component {
                         ATTN_QKV => {
                             let [q, k, v] = split_qkv(tensor, nh, nkvh);
                             self.tensors.insert(key(ATTN_Q), q);
@@ -96,48 +108,60 @@ enum WB {
 
 enum Collecting<'a> {
     Collected,
-    Done((Cow<'a, str>, Tensor<'a>)),
-    Irrelevant(Tensor<'a>),
+    Done((Cow<'a, str>, GGufTensorBuf<'a>)),
+    Irrelevant(GGufTensorBuf<'a>),
 }
 
-struct MergeCollector<'a>(HashMap<String, GroupCollector<'a>>);
-struct GroupCollector<'a>(HashMap<(Layer, WB), [Option<Tensor<'a>>; 3]>);
+struct MergeCollector<'a>(HashMap<(String, Option<usize>), GroupCollector<'a>>);
+struct GroupCollector<'a>(HashMap<(Layer, WB), [Option<GGufTensorBuf<'a>>; 3]>);
 
 impl<'a> MergeCollector<'a> {
     fn new() -> Self {
         Self(HashMap::new())
     }
 
-    fn collect(&mut self, name: &str, tensor: Tensor<'a>) -> Collecting<'a> {
-        static MERGE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(MERGE).unwrap());
-        let Some(captures) = MERGE_REGEX.captures(name) else {
+    fn collect(&mut self, name: &str, tensor: GGufTensorBuf<'a>) -> Collecting<'a> {
+        let Some(parsed) = parse_linear(name, MERGE) else {
             return Collecting::Irrelevant(tensor);
         };
-        let pre = &name[..captures.get(0).unwrap().start()];
-        let (_, [name, wb]) = captures.extract();
-        match self.0.get_mut(pre) {
-            Some(group) => group
-                .put(name, wb, tensor)
-                .map_or(Collecting::Collected, |(name, tensor)| {
-                    Collecting::Done((format!("{pre}{name}.{wb}").into(), tensor))
-                }),
+        let GGufTensorName {
+            prefix,
+            block,
+            component,
+            suffix: Some(wb),
+            ..
+        } = parsed
+        else {
+            unreachable!()
+        };
+        match self.0.get_mut(&(prefix.into(), block)) {
+            Some(group) => group.put(component, wb, tensor).map_or(
+                Collecting::Collected,
+                |(component, tensor)| {
+                    let name = GGufTensorName {
+                        component,
+                        ..parsed
+                    };
+                    Collecting::Done((name.to_string().into(), tensor))
+                },
+            ),
             None => {
                 let mut group = GroupCollector(HashMap::new());
-                assert!(group.put(name, wb, tensor).is_none());
-                self.0.insert(pre.into(), group);
+                assert!(group.put(component, wb, tensor).is_none());
+                self.0.insert((prefix.into(), block), group);
                 Collecting::Collected
             }
         }
     }
 
-    fn into_iter(self) -> impl IntoIterator<Item = (Cow<'a, str>, Tensor<'a>)> {
-        self.0.into_iter().flat_map(|(pre, group)| {
+    fn into_iter(self) -> impl IntoIterator<Item = (Cow<'a, str>, GGufTensorBuf<'a>)> {
+        self.0.into_iter().flat_map(|((prefix, block), group)| {
             group.0.into_iter().flat_map(move |((layer, wb), tensors)| {
                 let wb = match wb {
                     WB::Weight => "weight",
                     WB::Bias => "bias",
                 };
-                let pre = pre.clone();
+                let prefix = prefix.clone();
                 tensors
                     .into_iter()
                     .enumerate()
@@ -153,7 +177,14 @@ impl<'a> MergeCollector<'a> {
                                 (Layer::FfnMoe, 1) => FFN_UP_EXPS,
                                 _ => unreachable!(),
                             };
-                            (format!("{pre}{name}.{wb}").into(), tensor)
+                            let name = GGufTensorName {
+                                prefix: &prefix,
+                                block,
+                                component: name,
+                                expert: None,
+                                suffix: Some(wb),
+                            };
+                            (name.to_string().into(), tensor)
                         })
                     })
             })
@@ -161,13 +192,20 @@ impl<'a> MergeCollector<'a> {
     }
 }
 
+/// 解析可合并或拆分的线性层张量名
+fn parse_linear<'n>(name: &'n str, components: &[&str]) -> Option<GGufTensorName<'n>> {
+    GGufTensorName::parse(name).filter(|name| {
+        components.contains(&name.component) && name.expert.is_none() && name.suffix.is_some()
+    })
+}
+
 impl<'a> GroupCollector<'a> {
     fn put(
         &mut self,
         name: &str,
         wb: &str,
-        tensor: Tensor<'a>,
-    ) -> Option<(&'static str, Tensor<'a>)> {
+        tensor: GGufTensorBuf<'a>,
+    ) -> Option<(&'static str, GGufTensorBuf<'a>)> {
         let (layer, i) = match name {
             ATTN_Q => (Layer::Attn, 0),
             ATTN_K => (Layer::Attn, 1),
@@ -221,7 +259,7 @@ impl<'a> GroupCollector<'a> {
     }
 }
 
-pub(crate) fn merge_qkv(tensors: [Option<Tensor>; 3]) -> (&'static str, Tensor) {
+pub(crate) fn merge_qkv(tensors: [Option<GGufTensorBuf>; 3]) -> (&'static str, GGufTensorBuf) {
     let [Some(q), Some(k), Some(v)] = tensors else {
         unreachable!()
     };
@@ -234,7 +272,7 @@ pub(crate) fn merge_qkv(tensors: [Option<Tensor>; 3]) -> (&'static str, Tensor)
     (ATTN_QKV, concat(1, [q, k, v]))
 }
 
-fn merge_gate_up(tensors: [Option<Tensor>; 3]) -> (&'static str, Tensor) {
+fn merge_gate_up(tensors: [Option<GGufTensorBuf>; 3]) -> (&'static str, GGufTensorBuf) {
     let [Some(gate), Some(up), None] = tensors else {
         unreachable!()
     };
@@ -242,14 +280,14 @@ fn merge_gate_up(tensors: [Option<Tensor>; 3]) -> (&'static str, Tensor) {
     (FFN_GATE_UP, concat(1, [gate, up]))
 }
 
-fn merge_gate_up_exps(tensors: [Option<Tensor>; 3]) -> (&'static str, Tensor) {
+fn merge_gate_up_exps(tensors: [Option<GGufTensorBuf>; 3]) -> (&'static str, GGufTensorBuf) {
     let [Some(gate), Some(up), None] = tensors else {
         unreachable!()
     };
     (FFN_GATE_UP_EXPS, concat(1, [gate, up]))
 }
 
-pub(crate) fn split_qkv(tensor: Tensor, nh: usize, nkvh: usize) -> [Tensor; 3] {
+pub(crate) fn split_qkv(tensor: GGufTensorBuf, nh: usize, nkvh: usize) -> [GGufTensorBuf; 3] {
     let nh = nh as u64;
     let nkvh = nkvh as u64;
     let [_, r] = distruct(&tensor);
@@ -257,18 +295,18 @@ pub(crate) fn split_qkv(tensor: Tensor, nh: usize, nkvh: usize) -> [Tensor; 3] {
     split(1, tensor, [nh * dh, nkvh * dh, nkvh * dh])
 }
 
-fn split_gate_up(tensor: Tensor) -> [Tensor; 2] {
+fn split_gate_up(tensor: GGufTensorBuf) -> [GGufTensorBuf; 2] {
     let r = tensor.shape[1] / 2;
     split(1, tensor, [r, r])
 }
 
-fn split_gate_up_exps(tensor: Tensor) -> [Tensor; 2] {
+fn split_gate_up_exps(tensor: GGufTensorBuf) -> [GGufTensorBuf; 2] {
     let r = tensor.shape[1] / 2;
     split(1, tensor, [r, r])
 }
 
 /// 解构形状，补充维度
-fn distruct(t: &Tensor) -> [u64; 2] {
+fn distruct(t: &GGufTensorBuf) -> [u64; 2] {
     match *t.shape {
         [r] => [1, r],
         [c, r] => [c, r],
@@ -276,7 +314,7 @@ fn distruct(t: &Tensor) -> [u64; 2] {
     }
 }
 
-fn concat<const N: usize>(mut axis: usize, tensors: [Tensor; N]) -> Tensor {
+fn concat<const N: usize>(mut axis: usize, tensors: [GGufTensorBuf; N]) -> GGufTensorBuf {
     let ty = tensors[0].ty;
     let mut shape = tensors[0].shape.clone();
     if shape.len() == 1 {
@@ -295,8 +333,13 @@ fn concat<const N: usize>(mut axis: usize, tensors: [Tensor; N]) -> Tensor {
         }
     }
 
+    if tensors.iter().any(|t| t.data.is_missing()) {
+        let data = GGufTensorData::Missing;
+        return GGufTensorBuf { ty, shape, data };
+    }
+
     let shape_ = shape.clone();
-    let data = DataPromise::lazy(move || {
+    let data = GGufTensorData::try_lazy(move || -> DataResult {
         let GGmlTypeSize {
             block_size,
             type_size,
@@ -312,19 +355,24 @@ fn concat<const N: usize>(mut axis: usize, tensors: [Tensor; N]) -> Tensor {
             })
             .collect::<Vec<_>>();
 
-        let mut ans = MmapMut::map_anon(ty.size().elements_to_bytes(&shape_)).unwrap();
+        let mut ans = MmapMut::map_anon(ty.size().elements_to_bytes(&shape_))?;
         for (t, out_layout) in zip(tensors, layout(ty, &shape_).split(axis, &parts)) {
-            let rearranging = Rearranging::new(&out_layout, &layout(ty, &t.shape), unit).unwrap();
-            unsafe { rearranging.launch(ans.as_mut_ptr(), t.data.get().as_ptr()) }
+            let rearranging = Rearranging::new(&out_layout, &layout(ty, &t.shape), unit)
+                .map_err(|e| format!("{e:?}"))?;
+            unsafe { rearranging.launch(ans.as_mut_ptr(), t.data.get()?.as_ptr()) }
         }
-        ans
+        Ok(ans)
     });
 
-    Tensor { ty, shape, data }
+    GGufTensorBuf { ty, shape, data }
 }
 
-fn split<const N: usize>(mut axis: usize, tensor: Tensor, split: [u64; N]) -> [Tensor; N] {
-    let Tensor { ty, shape, data } = tensor;
+fn split<const N: usize>(
+    mut axis: usize,
+    tensor: GGufTensorBuf,
+    split: [u64; N],
+) -> [GGufTensorBuf; N] {
+    let GGufTensorBuf { ty, shape, data } = tensor;
     if shape.len() == 1 {
         axis = 0
     }
@@ -347,13 +395,19 @@ fn split<const N: usize>(mut axis: usize, tensor: Tensor, split: [u64; N]) -> [T
         let src = data_layout.next().unwrap();
         let rearranging = Rearranging::new(&dst, &src, unit as _).unwrap();
 
+        if data.is_missing() {
+            let data = GGufTensorData::Missing;
+            return GGufTensorBuf { ty, shape, data };
+        }
+
         let data = data.clone();
-        let data = DataPromise::lazy(move || {
-            let mut ans = MmapMut::map_anon(len).unwrap();
-            unsafe { rearranging.launch(ans.as_mut_ptr(), data.get().as_ptr()) }
-            ans
+        let data = GGufTensorData::try_lazy(move || -> DataResult {
+            let data = data.get()?;
+            let mut ans = MmapMut::map_anon(len)?;
+            unsafe { rearranging.launch(ans.as_mut_ptr(), data.as_ptr()) }
+            Ok(ans)
         });
-        Tensor { ty, shape, data }
+        GGufTensorBuf { ty, shape, data }
     })
 }
 
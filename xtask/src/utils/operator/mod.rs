@@ -5,10 +5,16 @@ mod set_meta;
 mod sort;
 mod to_llama;
 
-use super::{Content, DataPromise, compile_patterns};
+use super::{Content, OperateError, compile_patterns};
 use ggus::{GGmlType, GGufMetaDataValueType};
+use memmap2::MmapMut;
 use regex::Regex;
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, error::Error, fmt};
+
+pub(crate) use set_meta::set_meta_in_place;
+
+/// 惰性计算张量数据的结果
+type DataResult = Result<MmapMut, Box<dyn Error + Send + Sync>>;
 
 #[allow(unused)]
 pub(crate) enum Operator {
@@ -56,7 +62,7 @@ impl Operator {
 }
 
 impl Content<'_> {
-    pub fn apply(&mut self, op: Operator) {
+    pub fn apply(&mut self, op: Operator) -> Result<(), OperateError> {
         use Operator::*;
         match op {
             ToLlama(extra) => self.convert_to_llama(extra),
@@ -66,7 +72,8 @@ impl Content<'_> {
             MergeLinear(ty) => self.merge_linear(ty),
             PermuteQK => self.permute_qk(),
             SortTensors => self.sort_tensors(),
-            SetMeta(map) => self.set_meta(map),
+            SetMeta(map) => return self.set_meta(map),
         }
+        Ok(())
     }
 }
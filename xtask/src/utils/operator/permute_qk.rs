@@ -1,13 +1,13 @@
 use super::{
-    super::Tensor,
-    Content, DataPromise,
+    Content, DataResult,
     merge::{merge_qkv, split_qkv},
 };
-use ggus::{DataFuture, GGufMetaError::NotExist, GGufMetaMapExt};
+use ggus::{
+    DataFuture, GGufMetaError::NotExist, GGufMetaMapExt, GGufTensorBuf, GGufTensorData,
+    GGufTensorName,
+};
 use mem_rearrange::{Rearranging, ndarray_layout::Endian::LittleEndian};
 use memmap2::MmapMut;
-use regex::Regex;
-use std::sync::LazyLock;
 
 impl Content<'_> {
     pub(super) fn permute_qk(&mut self) {
@@ -20,31 +20,29 @@ impl Content<'_> {
 
         let tensors = std::mem::take(&mut self.tensors);
         for (name, tensor) in tensors {
-            static QK_REGEX: LazyLock<Regex> =
-                LazyLock::new(|| Regex::new(r"(attn_q|attn_k|attn_qkv)\.(weight|bias)$").unwrap());
-
-            let tensor = if let Some(captures) = QK_REGEX.captures(&name) {
-                match &captures[1] {
-                    "attn_q" => permute_qk(tensor, nh),
-                    "attn_k" => permute_qk(tensor, nkvh),
-                    "attn_qkv" => {
-                        let [q, k, v] = split_qkv(tensor, nh, nkvh);
-                        let q = permute_qk(q, nh);
-                        let k = permute_qk(k, nkvh);
-                        merge_qkv([Some(q), Some(k), Some(v)]).1
-                    }
-                    _ => unreachable!(),
+            let parsed = GGufTensorName::parse(&name)
+                .filter(|name| name.expert.is_none() && name.suffix.is_some());
+            let tensor = match parsed.map(|name| name.component) {
+                Some("attn_q") => permute_qk(tensor, nh),
+                Some("attn_k") => permute_qk(tensor, nkvh),
+                Some("attn_qkv") => {
+                    let [q, k, v] = split_qkv(tensor, nh, nkvh);
+                    let q = permute_qk(q, nh);
+                    let k = permute_qk(k, nkvh);
+                    merge_qkv([Some(q), Some(k), Some(v)]).1
                 }
-            } else {
-                tensor
+                _ => tensor,
             };
             self.tensors.insert(name, tensor);
         }
     }
 }
 
-fn permute_qk(tensor: Tensor, nh: usize) -> Tensor {
-    let Tensor { ty, shape, data } = tensor;
+fn permute_qk(tensor: GGufTensorBuf, nh: usize) -> GGufTensorBuf {
+    let GGufTensorBuf { ty, shape, data } = tensor;
+    if data.is_missing() {
+        return GGufTensorBuf { ty, shape, data };
+    }
     let [c, r] = match &*shape {
         &[r] => [1, r],
         &[c, r] => [c, r],
@@ -60,10 +58,11 @@ fn permute_qk(tensor: Tensor, nh: usize) -> Tensor {
     let dst = Layout::new_contiguous(src.shape(), LittleEndian, 1);
     let rearrange = Rearranging::new(&dst, &src, 1).unwrap();
 
-    let data = DataPromise::lazy(move || {
-        let mut ans = MmapMut::map_anon(c * r).unwrap();
-        unsafe { rearrange.launch(ans.as_mut_ptr(), data.get().as_ptr()) };
-        ans
+    let data = GGufTensorData::try_lazy(move || -> DataResult {
+        let data = data.get()?;
+        let mut ans = MmapMut::map_anon(c * r)?;
+        unsafe { rearrange.launch(ans.as_mut_ptr(), data.as_ptr()) };
+        Ok(ans)
     });
-    Tensor { ty, shape, data }
+    GGufTensorBuf { ty, shape, data }
 }
@@ -1,29 +1,95 @@
-use super::{super::MetaValue, Content, Operator};
-use ggus::{GENERAL_ALIGNMENT, GGufMetaDataValueType as Ty, GGufWriter};
+use super::{super::FileInfo, Content, OperateError, Operator};
+use ggus::{
+    GENERAL_ALIGNMENT, GGufMetaDataValueType as Ty, GGufMetaEditor, GGufMetaKey, GGufMetaMapExt,
+    GGufWriter,
+};
 use internal::StrCollector;
-use log::warn;
+use log::{info, warn};
 use regex::Regex;
-use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::LazyLock};
+use std::{
+    collections::HashMap, fmt::Debug, io, path::Path, str::FromStr, sync::LazyLock, time::Instant,
+};
 
 impl Operator {
     #[inline]
     pub fn set_meta_by_cfg(cfg: &str) -> Self {
-        let mut ans = HashMap::new();
+        Self::SetMeta(parse_cfg(cfg))
+    }
+}
+
+fn parse_cfg(cfg: &str) -> HashMap<String, (Ty, Vec<u8>)> {
+    let mut ans = HashMap::new();
+
+    let mut state = None;
+    for line in cfg.lines() {
+        state = State::transfer(state, line, &mut ans);
+    }
+    if let Some(State::StrPedding(str) | State::StrAppending(str)) = state {
+        str.save_to(&mut ans)
+    }
+
+    ans
+}
 
-        let mut state = None;
-        for line in cfg.lines() {
-            state = State::transfer(state, line, &mut ans);
+/// 检查标准键的值类型
+fn check_standard(map: &HashMap<String, (Ty, Vec<u8>)>) {
+    for (k, (ty, vec)) in map {
+        if let Some(std) = GGufMetaKey::find(k)
+            && let Err(e) = std.check(*ty, vec)
+        {
+            warn!(
+                "Meta {k} does not match the standard type {:?}: {e:?}",
+                std.ty
+            )
         }
-        if let Some(State::StrPedding(str) | State::StrAppending(str)) = state {
-            str.save_to(&mut ans)
+    }
+}
+
+/// 就地修改文件的元数据，只重写文件头，必要时移动张量数据
+pub(crate) fn set_meta_in_place(path: &Path, cfg: &str) -> Result<FileInfo, OperateError> {
+    let time = Instant::now();
+    let map = parse_cfg(cfg);
+    check_standard(&map);
+
+    let mut editor = GGufMetaEditor::open(path).map_err(OperateError::GGuf)?;
+    for (k, (ty, vec)) in map {
+        if k.starts_with("split.") {
+            return Err(invalid_input(format!("Split is not allowed: {k}")));
         }
+        if let Some(v) = editor.insert_meta(&*k, ty, vec)
+            && v.ty != ty
+        {
+            warn!("Meta {k} type changed from {:?} to {:?}", v.ty, ty)
+        }
+    }
 
-        Self::SetMeta(ans)
+    let n_tensors = editor.index().tensors.len();
+    let n_meta_kvs = editor.index().meta_kvs.len();
+    let data_offset = editor.index().data_offset;
+    let new_offset = editor.commit().map_err(OperateError::Io)?;
+    if new_offset != data_offset {
+        info!("tensor data moved from {data_offset} to {new_offset}")
     }
+    info!("set meta in place in {:?}", time.elapsed());
+
+    let n_bytes = std::fs::metadata(path).map_err(OperateError::Io)?.len() as _;
+    Ok(FileInfo {
+        path: path.into(),
+        n_tensors,
+        n_meta_kvs,
+        n_bytes,
+    })
 }
 
 impl Content<'_> {
-    pub(super) fn set_meta(&mut self, mut map: HashMap<String, (Ty, Vec<u8>)>) {
+    pub(super) fn set_meta(
+        &mut self,
+        mut map: HashMap<String, (Ty, Vec<u8>)>,
+    ) -> Result<(), OperateError> {
+        check_standard(&map);
+        if let Some(k) = map.keys().find(|k| k.starts_with("split.")) {
+            return Err(invalid_input(format!("Split is not allowed: {k}")));
+        }
         for (k, v) in &mut self.meta_kvs {
             if let Some((ty, vec)) = map.remove(&**k) {
                 if v.ty != ty {
@@ -34,27 +100,22 @@ impl Content<'_> {
             }
         }
         for (k, (ty, vec)) in map {
-            if k == GENERAL_ALIGNMENT {
-                assert_eq!(ty, Ty::U32);
-                let &[a, b, c, d] = &*vec else {
-                    panic!("Invalid alignment value: {vec:?}");
-                };
-                self.alignment = u32::from_le_bytes([a, b, c, d]) as _;
-            } else if k.starts_with("split.") {
-                panic!("Split is not allowed: {k}");
-            } else {
-                self.meta_kvs.insert(
-                    k.into(),
-                    MetaValue {
-                        ty,
-                        value: vec.into(),
-                    },
-                );
-            }
+            self.insert_meta(k, ty, vec);
         }
+        // 对齐方式不保存在元信息中，按 ggus 的规则校验后取出
+        let alignment = self.general_alignment();
+        self.remove_meta(GENERAL_ALIGNMENT);
+        self.alignment =
+            alignment.map_err(|e| invalid_input(format!("Invalid {GENERAL_ALIGNMENT}: {e:?}")))?;
+        Ok(())
     }
 }
 
+#[inline]
+fn invalid_input(msg: String) -> OperateError {
+    OperateError::Io(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
+
 #[derive(Debug)]
 enum State {
     StrPedding(StrCollector),
@@ -85,7 +146,9 @@ impl State {
 
                 // 匹配元信息配置项
                 let matches = KV_REGEX.captures(line).expect("Expect meta kv config item");
-                let key = matches.name("Key").unwrap().as_str().to_string();
+                let key = matches.name("Key").unwrap();
+                let rest = line[key.end() + 1..].trim();
+                let key = key.as_str().to_string();
                 let ty = matches.name("Type").unwrap();
                 let val = line[ty.end()..].trim();
                 let ty = ty.as_str();
@@ -106,9 +169,21 @@ impl State {
                 } else if let Some(arr) = ARR_REGEX.captures(ty) {
                     // TODO: 配置数组类型
                     todo!("arr: {}", &arr[0])
-                } else {
+                } else if let Some(ty) = parse_ty(ty) {
                     // 配置代数类型
-                    map.insert(key, write_val(parse_ty(ty), val));
+                    map.insert(key, write_val(ty, val));
+                    None
+                } else {
+                    // 省略类型，按标准键的约定推断
+                    let std = GGufMetaKey::find(&key).unwrap_or_else(|| {
+                        panic!("Unknown type: {ty}, and {key} is not a standard key")
+                    });
+                    let val = match std.ty {
+                        Ty::String => rest.strip_prefix('"').unwrap().strip_suffix('"').unwrap(),
+                        Ty::Array => panic!("Cannot infer array type of {key}"),
+                        _ => rest,
+                    };
+                    map.insert(key, write_val(std.ty, val));
                     None
                 }
             }
@@ -190,8 +265,8 @@ mod internal {
     }
 }
 
-fn parse_ty(ty: &str) -> Ty {
-    match ty {
+fn parse_ty(ty: &str) -> Option<Ty> {
+    Some(match ty {
         "u8" => Ty::U8,
         "i8" => Ty::I8,
         "u16" => Ty::U16,
@@ -205,8 +280,8 @@ fn parse_ty(ty: &str) -> Ty {
         "bool" => Ty::Bool,
         "str" => Ty::String,
         "arr" => Ty::Array,
-        _ => panic!("Unknown type: {ty}"),
-    }
+        _ => return None,
+    })
 }
 
 fn write_val(ty: Ty, val: impl AsRef<str>) -> (Ty, Vec<u8>) {
@@ -247,3 +322,29 @@ fn write_val(ty: Ty, val: impl AsRef<str>) -> (Ty, Vec<u8>) {
 
     (ty, val)
 }
+
+#[test]
+fn test_set_meta() {
+    use ggus::{GGufFileName, GGufModel};
+
+    let mut content = Content {
+        name: GGufFileName::try_from("test.gguf").unwrap(),
+        model: GGufModel::default(),
+    };
+    let mut set = |cfg: &str| {
+        let Operator::SetMeta(map) = Operator::set_meta_by_cfg(cfg) else {
+            unreachable!()
+        };
+        content.set_meta(map)
+    };
+    set("'general.alignment' u32 64").unwrap();
+    for cfg in [
+        "'general.alignment' u32 48",
+        "'general.alignment' u8 32",
+        "'split.count' u16 2",
+    ] {
+        assert!(matches!(set(cfg), Err(OperateError::Io(_))))
+    }
+    assert_eq!(content.alignment, 64);
+    assert!(content.meta_kvs.is_empty())
+}
@@ -1,6 +1,6 @@
 ﻿use super::Content;
+use ggus::GGufTensorName;
 use itertools::Itertools;
-use regex::Regex;
 use std::{cmp::Ordering, collections::HashMap, sync::LazyLock};
 
 impl Content<'_> {
@@ -59,7 +59,7 @@ struct Pre<'a>(Vec<PreSeg<'a>>);
 #[derive(PartialEq, Eq, Debug)]
 struct Mid<'a>(&'a str);
 #[derive(PartialEq, Eq, Debug)]
-struct Post<'a>(&'a str);
+struct Post<'a>(Option<usize>, &'a str);
 
 #[derive(PartialEq, Eq, Debug)]
 enum PreSeg<'a> {
@@ -69,37 +69,33 @@ enum PreSeg<'a> {
 
 impl Name<'static> {
     fn new_key(value: &str) -> Self {
-        static REGEX: LazyLock<Regex> = LazyLock::new(|| {
-            let mut mid = String::new();
-            for name in MID {
-                for c in name.chars() {
-                    if c.is_ascii_alphanumeric() || c == '_' {
-                        mid.push(c)
-                    } else if c == '.' {
-                        mid.push_str(r"\.")
-                    } else {
-                        panic!("invalid char: {c}")
-                    }
-                }
-                mid.push('|')
-            }
-            mid.pop();
-            Regex::new(&mid).unwrap()
-        });
-
         let value = unsafe {
             std::str::from_utf8_unchecked(std::slice::from_raw_parts(value.as_ptr(), value.len()))
         };
-        let (start, end) = REGEX
-            .find(value)
-            .map_or((value.len(), value.len()), |mid| (mid.start(), mid.end()));
-        let pre = value[..start]
-            .split('.')
-            .map(|s| s.parse::<usize>().map_or(PreSeg::Str(s), PreSeg::Num))
-            .collect();
-        let mid = &value[start..end];
-        let post = &value[end..];
-        Self(Pre(pre), Mid(mid), Post(post))
+        match GGufTensorName::parse(value) {
+            Some(GGufTensorName {
+                prefix,
+                block: Some(block),
+                component,
+                expert,
+                suffix,
+            }) => {
+                let pre = prefix
+                    .split('.')
+                    .filter(|s| !s.is_empty())
+                    .map(PreSeg::Str)
+                    .chain([PreSeg::Str("blk"), PreSeg::Num(block)])
+                    .collect();
+                Self(Pre(pre), Mid(component), Post(expert, suffix.unwrap_or("")))
+            }
+            _ => {
+                let pre = value
+                    .split('.')
+                    .map(|s| s.parse::<usize>().map_or(PreSeg::Str(s), PreSeg::Num))
+                    .collect();
+                Self(Pre(pre), Mid(""), Post(None, ""))
+            }
+        }
     }
 }
 
@@ -147,22 +143,11 @@ impl Ord for Mid<'_> {
 
 impl Ord for Post<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
-        use Ordering::{Equal, Greater, Less};
-
         static ORDER_MAP: LazyLock<HashMap<&str, usize>> =
             LazyLock::new(|| POST.iter().enumerate().map(|(i, s)| (*s, i)).collect());
-        let mut a = self.0.split('.');
-        let mut b = other.0.split('.');
-        loop {
-            match (a.next(), b.next()) {
-                (Some(a), Some(b)) => match cmp_by_map(a, b, &ORDER_MAP) {
-                    Equal => {}
-                    ord => break ord,
-                },
-                (Some(_), None) => break Greater,
-                (None, Some(_)) => break Less,
-                (None, None) => break Equal,
-            }
+        match self.0.cmp(&other.0) {
+            Ordering::Equal => cmp_by_map(self.1, other.1, &ORDER_MAP),
+            ord => ord,
         }
     }
 }
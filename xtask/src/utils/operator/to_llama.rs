@@ -1,14 +1,12 @@
-use super::{
-    super::{DataPromise, MetaValue, Tensor},
-    Content, Operator,
-};
+use super::{Content, DataResult, Operator};
 use ggus::{
-    DataFuture, GGmlType, GGufMetaError, GGufMetaMapExt,
+    DataFuture, GGmlType, GGufMetaBuf, GGufMetaError, GGufMetaMapExt, GGufTensorBuf,
+    GGufTensorData, GGufTensorName,
     ggml_quants::{bf16, f16},
 };
 use memmap2::MmapMut;
 use regex::Regex;
-use std::{alloc::Layout, collections::HashMap, ops::MulAssign, sync::LazyLock};
+use std::{collections::HashMap, ops::MulAssign, sync::LazyLock};
 
 impl Operator {
     #[inline]
@@ -64,15 +62,17 @@ fn from_minicpm(content: &mut Content, extra: HashMap<String, String>) {
     let res_scale = res_scale.expect(ERR_MSG) / (nblk as f64).sqrt();
 
     for (name, tensor) in content.tensors.iter_mut() {
-        static BLK_TENSOR_REGEX: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"^blk\.(\d+)\.(\w+)\.weight$").unwrap());
         if name == "token_embd.weight" {
             scale_tensor(tensor, embd_scale);
-        } else if let Some(captures) = BLK_TENSOR_REGEX.captures(name) {
-            match &captures[2] {
-                "attn_output" | "ffn_down" => scale_tensor(tensor, res_scale),
-                _ => {}
-            }
+        } else if let Some(GGufTensorName {
+            prefix: "",
+            block: Some(_),
+            component: "attn_output" | "ffn_down",
+            expert: None,
+            suffix: Some("weight"),
+        }) = GGufTensorName::parse(name)
+        {
+            scale_tensor(tensor, res_scale)
         }
     }
 
@@ -85,7 +85,7 @@ fn set_arch(content: &mut Content, old: &str, new: &str, mut f: impl FnMut(&str)
     let old = format!("{old}.");
     for (k, v) in std::mem::take(&mut content.meta_kvs) {
         if k == "general.architecture" {
-            content.meta_kvs.insert(k, MetaValue::string(new));
+            content.meta_kvs.insert(k, GGufMetaBuf::string(new));
         } else if f(&k) {
             let k = match k.strip_prefix(&old) {
                 Some(body) => format!("{new}.{body}").into(),
@@ -96,30 +96,38 @@ fn set_arch(content: &mut Content, old: &str, new: &str, mut f: impl FnMut(&str)
     }
 }
 
-fn scale_tensor(tensor: &mut Tensor, scale: f64) {
+fn scale_tensor(tensor: &mut GGufTensorBuf, scale: f64) {
+    if tensor.data.is_missing() {
+        return;
+    }
     let data = tensor.data.clone();
     tensor.data = match tensor.ty {
-        GGmlType::F64 => DataPromise::lazy(move || scale_data(data.get(), scale)),
-        GGmlType::F32 => DataPromise::lazy(move || scale_data(data.get(), scale as f32)),
-        GGmlType::F16 => DataPromise::lazy(move || scale_data(data.get(), f16::from_f64(scale))),
-        GGmlType::BF16 => DataPromise::lazy(move || scale_data(data.get(), bf16::from_f64(scale))),
+        GGmlType::F64 => GGufTensorData::try_lazy(move || scale_data(data.get()?, scale)),
+        GGmlType::F32 => GGufTensorData::try_lazy(move || scale_data(data.get()?, scale as f32)),
+        GGmlType::F16 => {
+            GGufTensorData::try_lazy(move || scale_data(data.get()?, f16::from_f64(scale)))
+        }
+        GGmlType::BF16 => {
+            GGufTensorData::try_lazy(move || scale_data(data.get()?, bf16::from_f64(scale)))
+        }
         ty => todo!("unsupported tensor type: {ty:?}"),
     };
 }
 
-fn scale_data<T: MulAssign + Clone>(data: &[u8], scale: T) -> MmapMut {
-    assert_eq!(data.len() % size_of::<T>(), 0);
-    let len = data.len() / size_of::<T>();
+fn scale_data<T: MulAssign + Clone>(data: &[u8], scale: T) -> DataResult {
+    if !data.len().is_multiple_of(size_of::<T>()) {
+        return Err("data size is not a multiple of the element size".into());
+    }
 
-    let mut ans = MmapMut::map_anon(Layout::array::<T>(len).unwrap().size()).unwrap();
+    let mut ans = MmapMut::map_anon(data.len())?;
     ans.copy_from_slice(data);
 
     let (&mut [], data, &mut []) = (unsafe { ans.align_to_mut::<T>() }) else {
-        panic!("data not aligned")
+        return Err("data not aligned".into());
     };
     for x in data {
         *x *= scale.clone();
     }
 
-    ans
+    Ok(ans)
 }
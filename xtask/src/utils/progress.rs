@@ -0,0 +1,120 @@
+use ggus::{GGufWriteObserver, GGufWriteProgress};
+use std::{
+    io::{Write, stderr},
+    ops::ControlFlow,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// 终端进度条，汇总所有分片的写入进度
+pub(super) struct ProgressBar {
+    total: usize,
+    start: Instant,
+    state: Mutex<State>,
+}
+
+struct State {
+    done: usize,
+    last_draw: Option<Instant>,
+}
+
+/// 单个分片的观察者，将分片内的进度转换为增量
+struct ShardObserver {
+    bar: Arc<ProgressBar>,
+    done: AtomicUsize,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(total: usize) -> Arc<Self> {
+        Arc::new(Self {
+            total,
+            start: Instant::now(),
+            state: Mutex::new(State {
+                done: 0,
+                last_draw: None,
+            }),
+        })
+    }
+
+    pub fn observer(self: &Arc<Self>) -> Arc<dyn GGufWriteObserver> {
+        Arc::new(ShardObserver {
+            bar: self.clone(),
+            done: AtomicUsize::new(0),
+        })
+    }
+
+    /// 结束进度条所在的行
+    pub fn finish(&self) {
+        if self.state.lock().unwrap().last_draw.is_some() {
+            eprintln!()
+        }
+    }
+
+    fn advance(&self, bytes: usize, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.done += bytes;
+
+        let now = Instant::now();
+        if state.done < self.total
+            && state
+                .last_draw
+                .is_some_and(|last| now - last < Self::INTERVAL)
+        {
+            return;
+        }
+        state.last_draw = Some(now);
+
+        let ratio = state.done as f64 / self.total.max(1) as f64;
+        let filled = (ratio * Self::WIDTH as f64) as usize;
+        let elapsed = now - self.start;
+        let eta = if state.done == 0 {
+            "--:--:--".to_string()
+        } else {
+            let secs = elapsed.as_secs_f64() * (self.total - state.done) as f64 / state.done as f64;
+            hms(secs as u64)
+        };
+        let _ = write!(
+            stderr(),
+            "\r\x1b[2K[{:#<filled$}{:-<rest$}] {:>5.1}% {}/{} ETA {eta} {name}",
+            "",
+            "",
+            ratio * 100.,
+            human_bytes(state.done),
+            human_bytes(self.total),
+            rest = Self::WIDTH - filled,
+        );
+    }
+}
+
+impl GGufWriteObserver for ShardObserver {
+    fn on_progress(&self, progress: GGufWriteProgress) -> ControlFlow<()> {
+        let last = self.done.swap(progress.bytes_done, Ordering::Relaxed);
+        self.bar.advance(progress.bytes_done - last, progress.name);
+        ControlFlow::Continue(())
+    }
+}
+
+fn hms(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn human_bytes(n: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut val = n as f64;
+    let mut unit = 0;
+    while val >= 1024. && unit < UNITS.len() - 1 {
+        val /= 1024.;
+        unit += 1
+    }
+    if unit == 0 {
+        format!("{n}B")
+    } else {
+        format!("{val:.2}{}", UNITS[unit])
+    }
+}
@@ -1,14 +1,23 @@
-﻿use super::{Content, FileInfo, OutputConfig};
-use ggus::{GGufFileHeader, GGufFileSimulator, GGufFileWriter};
-use std::{fs::File, io, iter::zip, path::PathBuf, thread};
+﻿use super::{Content, FileInfo, OutputConfig, progress::ProgressBar};
+use ggus::{GGufFileHeader, GGufFileSimulator, GGufFileWriter, GGufModel};
+use std::{
+    fs::File,
+    io::{self, IsTerminal},
+    iter::zip,
+    path::PathBuf,
+    thread,
+};
 
 impl Content<'_> {
     pub fn write_files(self, out: OutputConfig) -> Result<Vec<FileInfo>, io::Error> {
         let Self {
             name,
-            alignment,
-            meta_kvs,
-            tensors,
+            model:
+                GGufModel {
+                    alignment,
+                    meta_kvs,
+                    tensors,
+                },
         } = self;
         let OutputConfig {
             dir,
@@ -18,11 +27,18 @@ impl Content<'_> {
             write_data,
         } = out;
 
+        if write_data && let Some((name, _)) = tensors.iter().find(|(_, t)| t.data.is_missing()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("data of tensor {name} is missing, try --no-data"),
+            ));
+        }
+
         // 规划分片方案
 
-        let mut simulator = GGufFileSimulator::with_alignment(alignment);
+        let mut simulator = GGufFileSimulator::with_alignment(alignment)?;
         for (k, v) in &meta_kvs {
-            simulator.write_meta_kv(k, v.ty, &v.value);
+            simulator.write_meta_kv(k, v.ty, &v.value)?;
         }
 
         let mut simulator = simulator.finish();
@@ -30,7 +46,7 @@ impl Content<'_> {
         for (name, tensor) in tensors {
             match &mut *shards {
                 [_] if shard_no_tensor_first => {
-                    simulator = GGufFileSimulator::with_alignment(alignment).finish();
+                    simulator = GGufFileSimulator::with_alignment(alignment)?.finish();
                     simulator.write_tensor(&name, tensor.ty, &tensor.shape);
                     shards.push(vec![(name, tensor)]);
                 }
@@ -41,7 +57,7 @@ impl Content<'_> {
                     {
                         current.push((name, tensor));
                     } else {
-                        simulator = GGufFileSimulator::with_alignment(alignment).finish();
+                        simulator = GGufFileSimulator::with_alignment(alignment)?.finish();
                         simulator.write_tensor(&name, tensor.ty, &tensor.shape);
                         shards.push(vec![(name, tensor)]);
                     }
@@ -50,6 +66,17 @@ impl Content<'_> {
             }
         }
 
+        // 向终端写入数据时显示进度
+
+        let n_bytes = shards
+            .iter()
+            .flatten()
+            .map(|(_, t)| t.ty.size().elements_to_bytes(&t.shape))
+            .sum::<usize>();
+        let progress = (write_data && n_bytes > 0 && io::stderr().is_terminal())
+            .then(|| ProgressBar::new(n_bytes));
+        let progress = progress.as_ref();
+
         // 生成迭代器
 
         let meta_kvs = &meta_kvs;
@@ -58,10 +85,12 @@ impl Content<'_> {
             .split_n(shards.len())
             .map(|name| dir.join(name.to_string()));
 
-        // 并行写入文件
+        // 并行写入文件，各分片平分线程数，避免同时计算过多张量
 
+        let cores = thread::available_parallelism().map_or(1, usize::from);
+        let threads = (cores / shards.len().max(1)).max(1);
         std::fs::create_dir_all(&dir)?;
-        thread::scope(|s| {
+        let results = thread::scope(|s| {
             zip(shards, path)
                 .enumerate()
                 .map(|(i, (tensors, path))| {
@@ -72,31 +101,60 @@ impl Content<'_> {
                         let n_tensors = tensors.len();
                         let header = GGufFileHeader::new(3, n_tensors as _, n_meta_kvs as _);
 
-                        let mut writer = GGufFileWriter::new(File::create(&path)?, header)?;
-                        writer.write_alignment(alignment)?;
-                        if i == 0 {
-                            for (k, v) in meta_kvs {
-                                writer.write_meta_kv(k, v.ty, &v.value)?;
+                        let write = || -> Result<usize, io::Error> {
+                            let mut writer = GGufFileWriter::new(File::create(&path)?, header)?;
+                            writer.write_alignment(alignment)?;
+                            if i == 0 {
+                                for (k, v) in meta_kvs {
+                                    writer.write_meta_kv(k, v.ty, &v.value)?;
+                                }
                             }
-                        }
 
-                        let mut writer = writer.finish(write_data);
-                        for (name, tensor) in tensors {
-                            writer.write_tensor(&name, tensor.ty, &tensor.shape, tensor.data)?;
+                            let mut writer = writer.finish(write_data);
+                            if let Some(progress) = progress {
+                                writer.set_observer(progress.observer())
+                            }
+                            for (name, tensor) in tensors {
+                                writer.write_tensor(
+                                    &name,
+                                    tensor.ty,
+                                    &tensor.shape,
+                                    tensor.data,
+                                )?;
+                            }
+                            writer.finish_with_threads(threads)
+                        };
+                        match write() {
+                            Ok(n_bytes) => Ok(FileInfo {
+                                path,
+                                n_tensors,
+                                n_meta_kvs,
+                                n_bytes,
+                            }),
+                            Err(e) => {
+                                // 删除写了一半的文件
+                                let _ = std::fs::remove_file(&path);
+                                Err(e)
+                            }
                         }
-                        writer.finish().map(|n_bytes| FileInfo {
-                            path,
-                            n_tensors,
-                            n_meta_kvs,
-                            n_bytes,
-                        })
                     })
                 })
                 .collect::<Vec<_>>()
                 .into_iter()
                 .map(|j| j.join().unwrap())
-                .collect::<Result<Vec<_>, _>>()
-        })
+                .collect::<Vec<_>>()
+        });
+        if let Some(progress) = progress {
+            progress.finish()
+        }
+
+        // 任一分片失败时，删除其他已写完的分片
+        if results.iter().any(Result::is_err) {
+            for info in results.iter().flatten() {
+                let _ = std::fs::remove_file(&info.path);
+            }
+        }
+        results.into_iter().collect()
     }
 }
 